@@ -13,6 +13,8 @@ pub enum WgConfErrKind {
     CriticalKeepTmp,
     EOF,
     WgEngineError,
+    QrCapacityExceeded,
+    DecryptionFailed,
 }
 
 #[derive(Debug)]
@@ -37,6 +39,25 @@ pub enum WgConfError {
     EOF,
     /// Error when using WG engine
     WgEngineError(String),
+    /// Config doesn't fit into QR code
+    QrCapacityExceeded(String),
+    /// Encrypted data couldn't be decrypted, e.g. passphrase is wrong or data is corrupted
+    DecryptionFailed(String),
+    /// Parse or validation error with its location in WG config
+    Located(Box<WgConfError>, Box<WgConfErrLocation>),
+}
+
+/// Location of the WG config text which caused [`WgConfError`]
+///
+/// Line and columns are 1-based, `columns` is the span `[start, end)` of the offending part of the line
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WgConfErrLocation {
+    pub(crate) file: Option<String>,
+    pub(crate) line: Option<usize>,
+    pub(crate) columns: Option<(usize, usize)>,
+    pub(crate) section: Option<String>,
+    pub(crate) key: Option<String>,
+    pub(crate) line_text: Option<String>,
 }
 
 impl Clone for WgConfError {
@@ -52,6 +73,9 @@ impl Clone for WgConfError {
             Self::CriticalKeepTmp(arg0) => Self::CriticalKeepTmp(arg0.clone()),
             Self::EOF => Self::EOF,
             Self::WgEngineError(arg0) => Self::WgEngineError(arg0.clone()),
+            Self::QrCapacityExceeded(arg0) => Self::QrCapacityExceeded(arg0.clone()),
+            Self::DecryptionFailed(arg0) => Self::DecryptionFailed(arg0.clone()),
+            Self::Located(arg0, arg1) => Self::Located(arg0.clone(), arg1.clone()),
         }
     }
 }
@@ -69,6 +93,206 @@ impl WgConfError {
             WgConfError::CriticalKeepTmp(_) => WgConfErrKind::CriticalKeepTmp,
             WgConfError::EOF => WgConfErrKind::EOF,
             WgConfError::WgEngineError(_) => WgConfErrKind::WgEngineError,
+            WgConfError::QrCapacityExceeded(_) => WgConfErrKind::QrCapacityExceeded,
+            WgConfError::DecryptionFailed(_) => WgConfErrKind::DecryptionFailed,
+            WgConfError::Located(err, _) => err.kind(),
+        }
+    }
+
+    /// Returns location of the error in WG config if it's known
+    pub fn location(&self) -> Option<&WgConfErrLocation> {
+        match self {
+            WgConfError::Located(_, location) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without location
+    pub fn cause(&self) -> &WgConfError {
+        match self {
+            WgConfError::Located(err, _) => err,
+            _ => self,
+        }
+    }
+
+    /// Renders the error with the offending config line and carets under the offending part, e.g.
+    ///
+    /// ```text
+    /// error: WG validation failed: invalid endpoint raw value
+    ///  --> wg0.conf:7:12
+    ///   |
+    /// 7 | Endpoint = 10.0.0.1
+    ///   |            ^^^^^^^^
+    ///   = [Peer] section, key 'Endpoint'
+    /// ```
+    pub fn render(&self) -> String {
+        let location = match self {
+            WgConfError::Located(_, location) => location,
+            _ => return format!("error: {self}"),
+        };
+
+        let mut rendered = format!("error: {}\n", self.cause());
+
+        let line_no = location
+            .line
+            .map(|line| line.to_string())
+            .unwrap_or_default();
+        let gutter = " ".repeat(line_no.len().max(1));
+
+        let position = location.position();
+        if !position.is_empty() {
+            rendered += &format!("{gutter}--> {position}\n");
+        }
+
+        if let (Some(_), Some(line_text)) = (location.line, &location.line_text) {
+            rendered += &format!("{gutter} |\n{line_no} | {line_text}\n");
+            if let Some((start, end)) = location.columns {
+                let carets = "^".repeat(end.saturating_sub(start).max(1));
+                let indent = " ".repeat(start.saturating_sub(1));
+                rendered += &format!("{gutter} | {indent}{carets}\n");
+            }
+
+            let context = location.context();
+            if !context.is_empty() {
+                rendered += &format!("{gutter} = {context}\n");
+            }
+        }
+
+        rendered
+    }
+
+    /// Adds location to the error, already known location fields are kept.
+    ///
+    /// [`WgConfError::EOF`] is returned as is
+    pub(crate) fn with_location(self, location: WgConfErrLocation) -> WgConfError {
+        match self {
+            WgConfError::EOF => WgConfError::EOF,
+            WgConfError::Located(err, mut known_location) => {
+                known_location.fill_from(location);
+                WgConfError::Located(err, known_location)
+            }
+            err => WgConfError::Located(Box::new(err), Box::new(location)),
+        }
+    }
+}
+
+impl WgConfErrLocation {
+    /// Creates location of the line of WG config file
+    pub(crate) fn new(
+        file: Option<&str>,
+        line: usize,
+        line_text: &str,
+        section: &str,
+    ) -> WgConfErrLocation {
+        WgConfErrLocation {
+            file: file.map(|file| file.to_owned()),
+            line: Some(line),
+            columns: None,
+            section: Some(section.to_owned()),
+            key: None,
+            line_text: Some(line_text.to_owned()),
+        }
+    }
+
+    /// Creates location of the key of the section with provided tag (e.g. \[Peer\])
+    pub(crate) fn for_key(section_tag: &str, key: &str) -> WgConfErrLocation {
+        WgConfErrLocation {
+            section: Some(
+                section_tag
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .to_owned(),
+            ),
+            key: Some(key.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the offending key and its columns span
+    pub(crate) fn at_key(mut self, key: &str, columns: (usize, usize)) -> WgConfErrLocation {
+        self.key = Some(key.to_owned());
+        self.columns = Some(columns);
+
+        self
+    }
+
+    /// Sets the columns span of the offending part of the line
+    pub(crate) fn at_columns(mut self, columns: (usize, usize)) -> WgConfErrLocation {
+        self.columns = Some(columns);
+
+        self
+    }
+
+    // getters
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+    pub fn columns(&self) -> Option<(usize, usize)> {
+        self.columns
+    }
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+    pub fn line_text(&self) -> Option<&str> {
+        self.line_text.as_deref()
+    }
+
+    fn fill_from(&mut self, other: WgConfErrLocation) {
+        self.file = self.file.take().or(other.file);
+        self.line = self.line.or(other.line);
+        self.columns = self.columns.or(other.columns);
+        self.section = self.section.take().or(other.section);
+        self.key = self.key.take().or(other.key);
+        self.line_text = self.line_text.take().or(other.line_text);
+    }
+
+    /// `file:line:column` part of the location
+    fn position(&self) -> String {
+        let mut position: Vec<String> = vec![];
+
+        if let Some(file) = &self.file {
+            position.push(file.clone());
+        }
+        if let Some(line) = self.line {
+            position.push(line.to_string());
+        }
+        if let Some((start, _)) = self.columns {
+            position.push(start.to_string());
+        }
+
+        position.join(":")
+    }
+
+    /// `[Section] section, key 'Key'` part of the location
+    fn context(&self) -> String {
+        let mut context: Vec<String> = vec![];
+
+        if let Some(section) = &self.section {
+            context.push(format!("[{section}] section"));
+        }
+        if let Some(key) = &self.key {
+            context.push(format!("key '{key}'"));
+        }
+
+        context.join(", ")
+    }
+}
+
+impl Display for WgConfErrLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let position = self.position();
+        let context = self.context();
+
+        match (position.is_empty(), context.is_empty()) {
+            (false, false) => write!(f, "{position} ({context})"),
+            (false, true) => write!(f, "{position}"),
+            (true, _) => write!(f, "{context}"),
         }
     }
 }
@@ -95,6 +319,11 @@ impl Display for WgConfError {
             WgConfError::WgEngineError(err) => {
                 write!(f, "Error occurred when using WG engine: {err}")
             }
+            WgConfError::QrCapacityExceeded(details) => {
+                write!(f, "WG config doesn't fit into QR code: {details}")
+            }
+            WgConfError::DecryptionFailed(details) => write!(f, "Couldn't decrypt: {details}"),
+            WgConfError::Located(err, location) => write!(f, "{location}: {err}"),
         }
     }
 }
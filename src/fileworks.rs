@@ -1,21 +1,22 @@
 use std::{
     fs::{self, File, OpenOptions},
-    io::{ErrorKind, Read, Seek, SeekFrom},
+    io::{self, BufRead, ErrorKind, Read, Seek, SeekFrom, Write},
 };
 
 use crate::WgConfError;
 
-/// Copies from `src` to `dst` all the bytes except the interval `except_from` - `except_to`
-pub(crate) fn copy_bytes_except(
+/// Copies from `src` to `dst` all the bytes, the interval `replace_from` - `replace_to` is replaced by `replacement`
+pub(crate) fn copy_bytes_replacing(
     src: &mut File,
     dst: &mut File,
-    except_from: u64,
-    except_to: u64,
+    replace_from: u64,
+    replace_to: u64,
+    replacement: &[u8],
     parent_err_msg: &str,
 ) -> Result<(), WgConfError> {
     seek_to_start(src, parent_err_msg)?;
 
-    let mut all_up_to_peer = src.take(except_from);
+    let mut all_up_to_peer = src.take(replace_from);
     let _ = std::io::copy(&mut all_up_to_peer, dst).map_err(|err| {
         WgConfError::Unexpected(format!(
             "Couldn't copy config file to tmp: {}",
@@ -23,7 +24,11 @@ pub(crate) fn copy_bytes_except(
         ))
     })?;
 
-    src.seek(SeekFrom::Start(except_to)).map_err(|err| {
+    dst.write_all(replacement).map_err(|err| {
+        WgConfError::Unexpected(format!("Couldn't write config file to tmp: {err}"))
+    })?;
+
+    src.seek(SeekFrom::Start(replace_to)).map_err(|err| {
         WgConfError::Unexpected(format!(
             "Couldn't copy config file to tmp: {}",
             err.to_string()
@@ -79,6 +84,34 @@ pub(crate) fn open_file_w_all_permissions(file_name: &str) -> Result<File, WgCon
         })
 }
 
+/// Creates or truncates the file which is readable and writable by the owner only (0600 on unix) and writes `content` into it
+pub(crate) fn write_owner_only(file_name: &str, content: &[u8]) -> Result<(), WgConfError> {
+    let mut open_options = OpenOptions::new();
+    open_options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open_options.mode(0o600);
+    }
+
+    let mut file = open_options
+        .open(file_name)
+        .map_err(|err| WgConfError::Unexpected(format!("Couldn't create {file_name}: {err}")))?;
+
+    // mode is applied only to the new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))
+            .map_err(|err| {
+                WgConfError::Unexpected(format!("Couldn't set {file_name} permissions: {err}"))
+            })?;
+    }
+
+    file.write_all(content)
+        .map_err(|err| WgConfError::Unexpected(format!("Couldn't write {file_name}: {err}")))
+}
+
 pub(crate) fn create_tmp_file(base_name: &str) -> Result<(String, File), WgConfError> {
     let tmp_file_name = base_name.to_owned() + ".tmp";
 
@@ -97,9 +130,77 @@ pub(crate) fn create_tmp_file(base_name: &str) -> Result<(String, File), WgConfE
     Ok((tmp_file_name, file))
 }
 
+/// Reads the interval `from` - `to` of the file as string
+pub(crate) fn read_string(
+    file: &mut File,
+    from: u64,
+    to: u64,
+    err_msg: &str,
+) -> Result<String, WgConfError> {
+    file.seek(SeekFrom::Start(from))
+        .map_err(|err| WgConfError::Unexpected(format!("{err_msg}: {err}")))?;
+
+    let mut raw = String::with_capacity((to - from) as usize);
+    file.take(to - from)
+        .read_to_string(&mut raw)
+        .map_err(|err| WgConfError::Unexpected(format!("{err_msg}: {err}")))?;
+
+    Ok(raw)
+}
+
 pub(crate) fn seek_to_start(file: &mut File, err_msg: &str) -> Result<(), WgConfError> {
     file.seek(std::io::SeekFrom::Start(0))
         .map_err(|err| WgConfError::Unexpected(format!("{}: {}", err_msg, err.to_string())))?;
 
     Ok(())
 }
+
+/// Line of the file without line ending and the length of its line ending in bytes
+pub(crate) struct RawLine {
+    pub(crate) text: String,
+    pub(crate) eol_len: usize,
+}
+
+impl RawLine {
+    /// Length of the line in bytes including line ending
+    pub(crate) fn len(&self) -> usize {
+        self.text.len() + self.eol_len
+    }
+}
+
+/// Iterator over lines which keeps lengths of line endings ("\n" or "\r\n"),
+/// so byte positions of the lines are calculated exactly
+pub(crate) struct RawLines<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> Iterator for RawLines<R> {
+    type Item = io::Result<RawLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = String::new();
+
+        match self.reader.read_line(&mut text) {
+            Ok(0) => None,
+            Ok(_) => {
+                let mut eol_len = 0;
+                if text.ends_with('\n') {
+                    text.pop();
+                    eol_len += 1;
+
+                    if text.ends_with('\r') {
+                        text.pop();
+                        eol_len += 1;
+                    }
+                }
+
+                Some(Ok(RawLine { text, eol_len }))
+            }
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+pub(crate) fn raw_lines<R: BufRead>(reader: R) -> RawLines<R> {
+    RawLines { reader }
+}
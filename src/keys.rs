@@ -1,27 +1,166 @@
-use std::{fmt::Debug, str::FromStr};
-#[cfg(feature = "wg_engine")]
+use std::{
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+#[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
 use std::{
     io::Write,
     process::{Command, Output, Stdio},
 };
 
 use base64::Engine;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
 
 use crate::WgConfError;
 
-/// WG key (private, public or preshared)
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Length of raw (decoded) WG key
+#[cfg(feature = "native_keys")]
+const KEY_LEN: usize = 32;
+
+/// Base64-encoded WG key which kind (private, public or preshared) is unknown, e.g. just parsed one
+///
+/// Configs use distinct [`WgPrivateKey`], [`WgPublicKey`] and [`WgPresharedKey`] types,
+/// the key is converted to public one explicitly with [`WgPublicKey::new`], secret keys are parsed from base64 string
+///
+/// The key is zeroed in memory on drop. As the key may be a secret one, `Debug` doesn't print it,
+/// there is no `Display` and the base64 key is read with [`WgKey::expose_secret`]
+#[derive(Clone, PartialEq, Eq)]
 pub struct WgKey {
-    pub(crate) key: String,
+    pub(crate) key: Zeroizing<String>,
+}
+
+/// Value which secret keys are replaced by in redacted output, e.g. [`WgInterface::to_string_redacted`](crate::WgInterface::to_string_redacted)
+pub const REDACTED: &'static str = "***";
+
+/// Implements common traits and conversions of the WG key newtype
+macro_rules! impl_key_type {
+    ($($t:ident),*) => {
+        $(
+            impl FromStr for $t {
+                type Err = WgConfError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    s.parse().map($t)
+                }
+            }
+
+        )*
+    };
+}
+
+/// Implements secret key traits: constant-time comparison, redacted `Debug` and explicit access to the key
+macro_rules! impl_secret_key_type {
+    ($($t:ident),*) => {
+        $(
+            impl $t {
+                /// Returns base64 secret key, e.g. to write it into WG config.
+                /// The key isn't printed by `Debug` and has no `Display` to not leak it by accident
+                pub fn expose_secret(&self) -> &str {
+                    &self.0.key
+                }
+            }
+
+            /// Secret keys are compared in constant time to not leak the key through timing
+            impl PartialEq for $t {
+                fn eq(&self, other: &Self) -> bool {
+                    self.0.key.as_bytes().ct_eq(other.0.key.as_bytes()).into()
+                }
+            }
+
+            impl Debug for $t {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    f.debug_tuple(stringify!($t)).field(&REDACTED).finish()
+                }
+            }
+        )*
+    };
 }
 
-pub type WgPrivateKey = WgKey;
-pub type WgPresharedKey = WgKey;
-pub type WgPublicKey = WgKey;
+/// WG private key of \[Interface\], see [`WgPrivateKey::expose_secret`]
+#[derive(Clone, Eq)]
+pub struct WgPrivateKey(pub(crate) WgKey);
+
+/// WG public key of \[Peer\]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WgPublicKey(pub(crate) WgKey);
+
+/// WG preshared key of \[Peer\], see [`WgPresharedKey::expose_secret`]
+#[derive(Clone, Eq)]
+pub struct WgPresharedKey(pub(crate) WgKey);
+
+impl_key_type!(WgPrivateKey, WgPublicKey, WgPresharedKey);
+impl_secret_key_type!(WgPrivateKey, WgPresharedKey);
+
+impl WgPublicKey {
+    /// Converts WG key to public key, secret keys can't be converted from [`WgKey`]
+    pub fn new(key: WgKey) -> WgPublicKey {
+        WgPublicKey(key)
+    }
 
-impl ToString for WgKey {
+    /// Returns the key as WG key of unknown kind
+    pub fn as_key(&self) -> &WgKey {
+        &self.0
+    }
+}
+
+/// Only public key is converted to [`WgKey`], secret keys are read with `expose_secret()`
+impl From<WgPublicKey> for WgKey {
+    fn from(key: WgPublicKey) -> Self {
+        key.0
+    }
+}
+
+impl ToString for WgPublicKey {
     fn to_string(&self) -> String {
-        self.key.clone()
+        self.0.key.to_string()
+    }
+}
+
+/// WG private key with its public key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgKeyPair {
+    pub(crate) private_key: WgPrivateKey,
+    pub(crate) public_key: WgPublicKey,
+}
+
+impl WgKeyPair {
+    /// Generates new private key and derives its public key, see [`WgKey::generate_private_key`]
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+    pub fn generate() -> Result<WgKeyPair, WgConfError> {
+        WgKeyPair::from_private_key(WgKey::generate_private_key()?)
+    }
+
+    /// Derives public key of the private one, see [`WgKey::generate_public_key`]
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+    pub fn from_private_key(private_key: WgPrivateKey) -> Result<WgKeyPair, WgConfError> {
+        let public_key = WgKey::generate_public_key(&private_key)?;
+
+        Ok(WgKeyPair {
+            private_key,
+            public_key,
+        })
+    }
+
+    // getters
+    pub fn private_key(&self) -> &WgPrivateKey {
+        &self.private_key
+    }
+    pub fn public_key(&self) -> &WgPublicKey {
+        &self.public_key
+    }
+}
+
+impl Debug for WgKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WgKey").field("key", &REDACTED).finish()
+    }
+}
+
+impl Hash for WgKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.as_str().hash(state);
     }
 }
 
@@ -31,19 +170,26 @@ impl FromStr for WgKey {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         WgKey::validate(s)?;
 
-        Ok(WgKey { key: s.to_owned() })
+        Ok(WgKey {
+            key: Zeroizing::new(s.to_owned()),
+        })
     }
 }
 
 impl Default for WgKey {
     fn default() -> Self {
         Self {
-            key: "".to_string(),
+            key: Zeroizing::new(String::new()),
         }
     }
 }
 
 impl WgKey {
+    /// Returns base64 key which may be a secret one, so it isn't printed by `Debug` and has no `Display`
+    pub fn expose_secret(&self) -> &str {
+        &self.key
+    }
+
     /// Validates if input string is WG key
     pub fn validate(input: &str) -> Result<(), WgConfError> {
         // WG keys should be 44 characters long (32 bytes base64-encoded).
@@ -53,9 +199,9 @@ impl WgKey {
             ));
         }
 
-        let decoded_key = base64::prelude::BASE64_STANDARD
-            .decode(input)
-            .map_err(|_| WgConfError::ValidationFailed("WG key is not valid base64".to_string()))?;
+        let decoded_key = Zeroizing::new(base64::prelude::BASE64_STANDARD.decode(input).map_err(
+            |_| WgConfError::ValidationFailed("WG key is not valid base64".to_string()),
+        )?);
 
         // decoded key should has 32 byte length
         if decoded_key.len() != 32 {
@@ -70,23 +216,23 @@ impl WgKey {
     /// Generates private key using WG
     ///
     /// **Note**, this function requires WG installed
-    #[cfg(feature = "wg_engine")]
+    #[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
     pub fn generate_private_key() -> Result<WgPrivateKey, WgConfError> {
-        generate_key(false)
+        generate_key(false).map(WgPrivateKey)
     }
 
     /// Generates preshared key using WG
     ///
     /// **Note**, this function requires WG installed
-    #[cfg(feature = "wg_engine")]
+    #[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
     pub fn generate_preshared_key() -> Result<WgPresharedKey, WgConfError> {
-        generate_key(true)
+        generate_key(true).map(WgPresharedKey)
     }
 
     /// Generates public key from private using WG
     ///
     /// **Note**, this function requires WG installed
-    #[cfg(feature = "wg_engine")]
+    #[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
     pub fn generate_public_key(private_key: &WgPrivateKey) -> Result<WgPublicKey, WgConfError> {
         let mut pubkey_proc = Command::new("wg")
             .arg("pubkey")
@@ -100,7 +246,7 @@ impl WgKey {
             .stdin
             .take()
             .unwrap() // we've always have it as we've spawned it above
-            .write_all(private_key.to_string().as_bytes())
+            .write_all(private_key.expose_secret().as_bytes())
             .map_err(|err| couldnt_generate_pub_key(err.to_string()))?;
 
         let pubkey_output = pubkey_proc
@@ -109,11 +255,81 @@ impl WgKey {
 
         check_stderr(&pubkey_output)?;
 
-        parse_key(&pubkey_output.stdout)
+        parse_key(&pubkey_output.stdout).map(WgPublicKey)
     }
+
+    /// Generates clamped X25519 private key from OS CSPRNG
+    #[cfg(feature = "native_keys")]
+    pub fn generate_private_key() -> Result<WgPrivateKey, WgConfError> {
+        let mut key = random_key_bytes()?;
+
+        // clamping like `wg genkey` does
+        key[0] &= 248;
+        key[31] &= 127;
+        key[31] |= 64;
+
+        Ok(WgPrivateKey(WgKey::from_bytes(&key)))
+    }
+
+    /// Generates random preshared key from OS CSPRNG
+    #[cfg(feature = "native_keys")]
+    pub fn generate_preshared_key() -> Result<WgPresharedKey, WgConfError> {
+        let key = random_key_bytes()?;
+
+        Ok(WgPresharedKey(WgKey::from_bytes(&key)))
+    }
+
+    /// Derives X25519 public key from private one
+    #[cfg(feature = "native_keys")]
+    pub fn generate_public_key(private_key: &WgPrivateKey) -> Result<WgPublicKey, WgConfError> {
+        let secret = x25519_dalek::StaticSecret::from(*private_key.0.to_bytes()?);
+
+        Ok(WgPublicKey(WgKey::from_bytes(
+            x25519_dalek::PublicKey::from(&secret).as_bytes(),
+        )))
+    }
+
+    /// Encodes raw key bytes as base64 WG key
+    #[cfg(feature = "native_keys")]
+    fn from_bytes(key: &[u8; KEY_LEN]) -> WgKey {
+        WgKey {
+            key: Zeroizing::new(base64::prelude::BASE64_STANDARD.encode(key)),
+        }
+    }
+
+    /// Decodes WG key to raw bytes
+    #[cfg(feature = "native_keys")]
+    fn to_bytes(&self) -> Result<Zeroizing<[u8; KEY_LEN]>, WgConfError> {
+        let invalid_key =
+            || WgConfError::ValidationFailed("WG key is not valid base64".to_string());
+
+        let decoded = Zeroizing::new(
+            base64::prelude::BASE64_STANDARD
+                .decode(self.key.as_bytes())
+                .map_err(|_| invalid_key())?,
+        );
+
+        decoded
+            .as_slice()
+            .try_into()
+            .map(Zeroizing::new)
+            .map_err(|_| invalid_key())
+    }
+}
+
+#[cfg(feature = "native_keys")]
+fn random_key_bytes() -> Result<Zeroizing<[u8; KEY_LEN]>, WgConfError> {
+    use rand_core::{OsRng, RngCore};
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    OsRng
+        .try_fill_bytes(key.as_mut_slice())
+        .map_err(|err| WgConfError::Unexpected(format!("Couldn't generate random key: {err}")))?;
+
+    Ok(key)
 }
 
-#[cfg(feature = "wg_engine")]
+#[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
 fn check_stderr(output: &Output) -> Result<(), WgConfError> {
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
@@ -124,7 +340,7 @@ fn check_stderr(output: &Output) -> Result<(), WgConfError> {
     Ok(())
 }
 
-#[cfg(feature = "wg_engine")]
+#[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
 fn parse_key(key_bytes: &Vec<u8>) -> Result<WgKey, WgConfError> {
     String::from_utf8_lossy(key_bytes)
         .trim()
@@ -132,12 +348,12 @@ fn parse_key(key_bytes: &Vec<u8>) -> Result<WgKey, WgConfError> {
         .parse()
 }
 
-#[cfg(feature = "wg_engine")]
+#[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
 fn couldnt_generate_pub_key(details: String) -> WgConfError {
     WgConfError::WgEngineError(format!("Couldn't generate public key: {}", details))
 }
 
-#[cfg(feature = "wg_engine")]
+#[cfg(all(feature = "wg_engine", not(feature = "native_keys")))]
 fn generate_key(genpsk: bool) -> Result<WgKey, WgConfError> {
     let (cmd, err_msg) = match genpsk {
         true => ("genpsk", "preshared"),
@@ -158,10 +374,10 @@ fn generate_key(genpsk: bool) -> Result<WgKey, WgConfError> {
 }
 
 #[cfg(test)]
-#[cfg(feature = "wg_engine")]
 mod tests {
     use super::*;
 
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
     #[test]
     fn generate_private_key() {
         let pkey = WgKey::generate_private_key();
@@ -169,6 +385,7 @@ mod tests {
         assert!(pkey.is_ok())
     }
 
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
     #[test]
     fn generate_preshared_key() {
         let psk = WgKey::generate_preshared_key();
@@ -176,13 +393,86 @@ mod tests {
         assert!(psk.is_ok())
     }
 
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
     #[test]
     fn generate_public_key() {
-        let pkey: WgKey = "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+        let pkey: WgPrivateKey = "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
             .parse()
             .unwrap();
         let pubkey = WgKey::generate_public_key(&pkey);
 
         assert!(pubkey.is_ok())
     }
+
+    #[cfg(feature = "native_keys")]
+    #[test]
+    fn generate_private_key_0_native_0_clamped() {
+        // Act
+        let pkey = WgKey::generate_private_key().unwrap();
+
+        // Assert
+        let bytes = pkey.0.to_bytes().unwrap();
+        assert_eq!(0, bytes[0] & 7);
+        assert_eq!(64, bytes[31] & 192);
+        assert_ne!(pkey, WgKey::generate_private_key().unwrap());
+    }
+
+    #[cfg(feature = "native_keys")]
+    #[test]
+    fn generate_public_key_0_native_0_rfc7748_vector() {
+        // Arrange
+        let pkey: WgPrivateKey = "dwdtCnMYpX08FsFyUbJmRd9ML4frwJkqsXf7pR25LCo="
+            .parse()
+            .unwrap();
+
+        // Act
+        let pubkey = WgKey::generate_public_key(&pkey);
+
+        // Assert
+        assert_eq!(
+            "hSDwCYkwp1R0i33ctD73Wg2/Og0mOBr066SpjqqbTmo=",
+            pubkey.unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn eq_0_secret_keys_0_compared_by_value_and_redacted_in_debug() {
+        // Arrange
+        const KEY: &str = "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=";
+        const OTHER_KEY: &str = "Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=";
+        let private_key: WgPrivateKey = KEY.parse().unwrap();
+        let preshared_key: WgPresharedKey = KEY.parse().unwrap();
+
+        // Act
+        let debug = format!("{private_key:?} {preshared_key:?}");
+
+        // Assert
+        assert_eq!(private_key, KEY.parse().unwrap());
+        assert_ne!(private_key, OTHER_KEY.parse().unwrap());
+        assert_eq!(preshared_key, KEY.parse().unwrap());
+        assert_ne!(preshared_key, OTHER_KEY.parse().unwrap());
+        assert_eq!(KEY, private_key.expose_secret());
+        assert_eq!(KEY, preshared_key.expose_secret());
+        assert!(!debug.contains(KEY));
+        assert!(!format!("{:?}", private_key.0).contains(KEY));
+    }
+
+    #[cfg(feature = "native_keys")]
+    #[test]
+    fn key_pair_0_from_private_key_0_derives_public_key() {
+        // Arrange
+        let private_key: WgPrivateKey = "dwdtCnMYpX08FsFyUbJmRd9ML4frwJkqsXf7pR25LCo="
+            .parse()
+            .unwrap();
+
+        // Act
+        let key_pair = WgKeyPair::from_private_key(private_key.clone()).unwrap();
+
+        // Assert
+        assert_eq!(&private_key, key_pair.private_key());
+        assert_eq!(
+            "hSDwCYkwp1R0i33ctD73Wg2/Og0mOBr066SpjqqbTmo=",
+            key_pair.public_key().to_string()
+        );
+    }
 }
@@ -3,12 +3,43 @@ mod fileworks;
 mod keys;
 mod wg_client_conf;
 mod wg_conf;
+mod wg_document;
+#[cfg(feature = "encryption")]
+mod wg_encryption;
+#[cfg(any(feature = "json", feature = "yaml", feature = "toml"))]
+mod wg_formats;
 mod wg_interface;
+#[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+mod wg_key_rotation;
+mod wg_mem_conf;
+mod wg_networkd;
+mod wg_nm_keyfile;
+mod wg_openwrt;
+mod wg_parse;
 mod wg_peer;
+#[cfg(feature = "qr")]
+mod wg_qr;
+mod wg_routeros;
+mod wg_secret;
+#[cfg(feature = "serde")]
+mod wg_serde;
 
 pub use error::*;
 pub use keys::*;
 pub use wg_client_conf::*;
 pub use wg_conf::*;
+pub use wg_document::*;
+#[cfg(feature = "encryption")]
+pub use wg_encryption::{WgKdfParams, WgPassphrase};
 pub use wg_interface::*;
+#[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+pub use wg_key_rotation::{WgKeyRotation, WgPresharedKeyRotation};
+pub use wg_mem_conf::*;
+pub use wg_networkd::{WgNetworkdPrivateKey, WgNetworkdUnits};
+pub use wg_parse::*;
 pub use wg_peer::*;
+#[cfg(feature = "qr")]
+pub use wg_qr::QR_MAX_BYTES;
+pub use wg_secret::{WgPrivateKeyRef, WgSecretProvider};
+#[cfg(feature = "serde")]
+pub use wg_serde::WgRedacted;
@@ -1,7 +1,12 @@
-use crate::{WgInterface, WgPeer};
+use std::{fs::File, io::Read};
+
+use crate::{
+    fileworks, WgConfErrLocation, WgConfError, WgInterface, WgMemConf, WgParseOptions, WgPeer,
+};
 
 /// Represents WG client configuration
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WgClientConf {
     pub(crate) interface: WgInterface,
     pub(crate) peers: Vec<WgPeer>,
@@ -25,6 +30,72 @@ impl WgClientConf {
         WgClientConf { interface, peers }
     }
 
+    /// Same as `to_string()`, but private and preshared keys are replaced by [`REDACTED`](crate::REDACTED),
+    /// so the config is safe to log
+    pub fn to_string_redacted(&self) -> String {
+        let mut str = self.interface().to_string_redacted();
+
+        for peer in self.peers() {
+            str = str + "\n" + &peer.to_string_redacted();
+        }
+
+        str
+    }
+
+    /// Parses [`WgClientConf`] from the text using [`WgParseMode::Lenient`](crate::WgParseMode::Lenient) parsing mode
+    ///
+    /// Values are validated the same way as server config ones, errors are located in the text
+    pub fn parse(text: &str) -> Result<WgClientConf, WgConfError> {
+        WgClientConf::parse_with_options(text, WgParseOptions::default())
+    }
+
+    /// Parses [`WgClientConf`] from the text using provided parse options
+    pub fn parse_with_options(
+        text: &str,
+        options: WgParseOptions,
+    ) -> Result<WgClientConf, WgConfError> {
+        let conf = WgMemConf::parse_with_options(text, options)?;
+
+        Ok(WgClientConf::new(
+            conf.interface().clone(),
+            conf.peers().to_vec(),
+        ))
+    }
+
+    /// Reads the whole `reader` and parses [`WgClientConf`] from it using [`WgParseMode::Lenient`](crate::WgParseMode::Lenient) parsing mode
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<WgClientConf, WgConfError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).map_err(|err| {
+            WgConfError::Unexpected(format!("Couldn't read WG client config: {err}"))
+        })?;
+
+        WgClientConf::parse(&text)
+    }
+
+    /// Reads and parses [`WgClientConf`] from the file using [`WgParseMode::Lenient`](crate::WgParseMode::Lenient) parsing mode
+    ///
+    /// Returns [`WgConfError::NotFound`] if the file doesn't exist, errors are located in the file
+    pub fn from_file(file_name: &str) -> Result<WgClientConf, WgConfError> {
+        let file = File::open(file_name).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => WgConfError::NotFound(file_name.to_string()),
+            _ => WgConfError::Unexpected(format!("Couldn't open {file_name}: {err}")),
+        })?;
+
+        WgClientConf::from_reader(file).map_err(|err| match err.location() {
+            Some(_) => err.with_location(WgConfErrLocation {
+                file: Some(file_name.to_owned()),
+                ..Default::default()
+            }),
+            None => err,
+        })
+    }
+
+    /// Saves the config into the file which is readable and writable by the owner only (0600 on unix),
+    /// existing file is overwritten
+    pub fn save(&self, file_name: &str) -> Result<(), WgConfError> {
+        fileworks::write_owner_only(file_name, self.to_string().as_bytes())
+    }
+
     // getters
     pub fn interface(&self) -> &WgInterface {
         &self.interface
@@ -36,9 +107,115 @@ impl WgClientConf {
 
 #[cfg(test)]
 mod tests {
-    use std::{net::IpAddr, str::FromStr};
-
     use super::*;
+    use crate::{wg_conf::tests::Deferred, WgConfErrKind};
+    use std::fs;
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/32
+DNS = 8.8.8.8
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 0.0.0.0/0
+Endpoint = 127.0.0.2:8080
+PersistentKeepalive = 25
+";
+
+    #[test]
+    fn parse_0_common_scenario() {
+        // Act
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Assert
+        assert_eq!(None, client_conf.interface().listen_port());
+        assert_eq!(1, client_conf.peers().len());
+        assert_eq!(
+            "127.0.0.2:8080",
+            client_conf.peers()[0].endpoint().unwrap().to_string()
+        );
+        assert_eq!(CLIENT_CONTENT, client_conf.to_string());
+    }
+
+    #[test]
+    fn parse_0_invalid_value_0_returns_located_err() {
+        // Arrange
+        let content = CLIENT_CONTENT.replace("10.0.0.2/32", "10.0.0.256/32");
+
+        // Act
+        let err = WgClientConf::parse(&content).unwrap_err();
+
+        // Assert
+        assert_eq!(WgConfErrKind::ValidationFailed, err.kind());
+        assert_eq!(Some(3), err.location().unwrap().line());
+    }
+
+    #[test]
+    fn save_0_from_file_0_same_conf() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_client0.conf";
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+        let _cleanup = Deferred(Box::new(|| {
+            let _ = fs::remove_file(TEST_CONF_FILE);
+        }));
+
+        // Act
+        let save_res = client_conf.save(TEST_CONF_FILE);
+        let read_res = WgClientConf::from_file(TEST_CONF_FILE);
+
+        // Assert
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(TEST_CONF_FILE).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+        assert!(save_res.is_ok());
+        assert_eq!(client_conf, read_res.unwrap());
+    }
+
+    #[test]
+    fn from_file_0_unexistent_0_returns_not_found_err() {
+        // Act
+        let res = WgClientConf::from_file("wg_client_unexistent.conf");
+
+        // Assert
+        assert_eq!(WgConfErrKind::NotFound, res.unwrap_err().kind());
+    }
+
+    #[test]
+    fn to_string_redacted_0_secrets_replaced() {
+        // Arrange
+        let content = CLIENT_CONTENT.replace(
+            "PersistentKeepalive",
+            "PresharedKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=\nPersistentKeepalive",
+        );
+        let client_conf = WgClientConf::parse(&content).unwrap();
+
+        // Act
+        let redacted = client_conf.to_string_redacted();
+
+        // Assert
+        assert_eq!(
+            content
+                .replace(
+                    "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=",
+                    crate::REDACTED
+                )
+                .replace(
+                    "Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=",
+                    crate::REDACTED
+                ),
+            redacted
+        );
+        assert!(
+            !format!("{client_conf:?}").contains("6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=")
+        );
+        assert!(
+            !format!("{client_conf:?}").contains("Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=")
+        );
+    }
 
     #[test]
     fn to_string() {
@@ -47,11 +224,11 @@ mod tests {
             "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
                 .parse()
                 .unwrap(),
-            "192.168.130.131/25".parse().unwrap(),
+            vec!["192.168.130.131/25".parse().unwrap()],
             Some(8082),
-            Some(IpAddr::from_str("8.8.8.8").unwrap()),
-            Some("some-script".to_string()),
-            Some("some-other-script".to_string()),
+            vec!["8.8.8.8".parse().unwrap()],
+            vec!["some-script".to_string()],
+            vec!["some-other-script".to_string()],
         )
         .unwrap();
 
@@ -1,30 +1,41 @@
 use std::{
-    collections::HashMap,
     ffi::OsStr,
     fs::{self, File},
-    io::{self, BufRead, BufReader, Lines, Seek, SeekFrom, Write},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
     path::Path,
 };
 
+use zeroize::Zeroizing;
+
 use crate::{
-    error::WgConfError, fileworks, wg_interface, wg_peer, WgConfErrKind, WgInterface, WgKey,
-    WgPeer, WgPublicKey,
+    error::WgConfError,
+    fileworks::{self, RawLines},
+    wg_document, wg_interface,
+    wg_parse::{self, char_columns, section_name},
+    wg_peer, WgConfErrKind, WgConfErrLocation, WgDocument, WgDocumentLineKind, WgInterface,
+    WgParseOptions, WgParseWarning, WgPeer, WgPrivateKey, WgPublicKey, WgSecretProvider,
 };
 
-#[cfg(feature = "wg_engine")]
-use crate::WgClientConf;
-#[cfg(feature = "wg_engine")]
+#[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+use crate::{WgClientConf, WgDns, WgEndpoint, WgHost, WgKey, WgKeyPair};
+#[cfg(any(feature = "wg_engine", feature = "native_keys"))]
 use ipnetwork::IpNetwork;
-#[cfg(feature = "wg_engine")]
-use std::net::{IpAddr, SocketAddr};
+#[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+use std::net::IpAddr;
 
 const CONF_EXTENSION: &'static str = "conf";
 
+/// Ordered key-values of a config section
+type RawKeyValues = Vec<(String, String)>;
+
 /// Represents WG configuration file
 #[derive(Debug)]
 pub struct WgConf {
     conf_file_name: String,
     conf_file: File,
+    options: WgParseOptions,
+    warnings: Vec<WgParseWarning>,
+    secret_provider: Option<Box<dyn WgSecretProvider>>,
     cache: WgConfCache,
 }
 
@@ -33,6 +44,7 @@ struct WgConfCache {
     pub_key: Option<WgPublicKey>,
     interface: Option<WgInterface>,
     peer_start_pos: Option<u64>,
+    peer_start_line: Option<usize>,
 }
 
 impl WgConf {
@@ -86,59 +98,135 @@ impl WgConf {
         Ok(WgConf {
             conf_file,
             conf_file_name: file_name.to_owned(),
+            options: WgParseOptions::default(),
+            warnings: vec![],
+            secret_provider: None,
             cache: WgConfCache {
                 pub_key: None,
                 interface: Some(interface),
                 peer_start_pos: Some(peer_start_pos),
+                peer_start_line: None,
             },
         })
     }
 
     /// Initializes [`WgConf``] from existing file
     ///
+    /// The file must start with exact [Interface] tag: leading comments, BOM, CRLF line endings
+    /// or whitespaces inside the tag are rejected with [`WgConfError::NotWgConfig`],
+    /// use [`WgConf::open_with_options`] with [`WgParseOptions::lenient()`] to accept them
+    ///
     /// Returns [`WgConfError::ValidationFailed`] if file validation is failed or [`WgConfError::Unexpected`] with details if other (fs) error occurred
     ///
     /// **Note**, that [`WgConf`] always keeps the underlying config file open till the end of ownership
     /// or untill drop() or WgConf.close() invoked
     pub fn open(file_name: &str) -> Result<WgConf, WgConfError> {
+        let mut wg_conf = WgConf::open_with_options(file_name, WgParseOptions::lenient())?;
+        check_starts_with_interface_tag(&mut wg_conf.conf_file)?;
+
+        Ok(wg_conf)
+    }
+
+    /// Initializes [`WgConf``] from existing file using provided parse options
+    ///
+    /// The whole file structure is checked on opening: in strict mode the first deviation from the canonical format
+    /// is returned as [`WgConfError::NotWgConfig`], in lenient mode the tolerated deviations are available with [`WgConf::warnings()`]
+    ///
+    /// **Note**, that [`WgConf`] always keeps the underlying config file open till the end of ownership
+    /// or untill drop() or WgConf.close() invoked
+    pub fn open_with_options(
+        file_name: &str,
+        options: WgParseOptions,
+    ) -> Result<WgConf, WgConfError> {
         let mut file = fileworks::open_file_w_all_permissions(file_name)?;
 
-        check_if_wg_conf(file_name, &mut file)?;
+        let warnings = check_structure(file_name, &mut file, &options)?;
 
         Ok(WgConf {
             conf_file_name: file_name.to_owned(),
             conf_file: file,
+            options,
+            warnings,
+            secret_provider: None,
             cache: WgConfCache {
                 pub_key: None,
                 interface: None,
                 peer_start_pos: None,
+                peer_start_line: None,
             },
         })
     }
 
-    /// Returns public key according to \[Interface\] private key
-    #[cfg(feature = "wg_engine")]
+    /// Returns parse options the config was opened with
+    pub fn options(&self) -> &WgParseOptions {
+        &self.options
+    }
+
+    /// Returns deviations from the canonical format which were tolerated when the config was opened
+    pub fn warnings(&self) -> &[WgParseWarning] {
+        &self.warnings
+    }
+
+    /// Sets provider which resolves [`WgPrivateKeyRef::Provider`](crate::WgPrivateKeyRef::Provider) private key
+    /// of the interface when the real key is needed, e.g. by [`WgConf::pub_key`]
+    pub fn set_secret_provider(&mut self, provider: Box<dyn WgSecretProvider>) {
+        self.secret_provider = Some(provider);
+    }
+
+    /// Returns \[Interface\] private key resolving its reference, see [`WgPrivateKeyRef::resolve`](crate::WgPrivateKeyRef::resolve)
+    pub fn private_key(&mut self) -> Result<WgPrivateKey, WgConfError> {
+        self.interface()?
+            .resolve_private_key(self.secret_provider.as_deref())
+    }
+
+    /// Reads the whole config file and returns its text with private and preshared keys replaced
+    /// by [`REDACTED`](crate::REDACTED), so the config is safe to log
+    pub fn to_string_redacted(&mut self) -> Result<String, WgConfError> {
+        Ok(WgDocument::parse(&self.read_text()?).to_string_redacted())
+    }
+
+    /// Reads the whole config file into the text which is zeroed on drop
+    pub(crate) fn read_text(&mut self) -> Result<Zeroizing<String>, WgConfError> {
+        const ERR_MSG: &'static str = "Couldn't read WG config";
+
+        fileworks::seek_to_start(&mut self.conf_file, ERR_MSG)?;
+
+        let mut text = Zeroizing::new(String::new());
+        let res = self
+            .conf_file
+            .read_to_string(&mut text)
+            .map_err(|err| WgConfError::Unexpected(format!("{ERR_MSG}: {err}")))
+            .map(|_| text);
+
+        fileworks::seek_to_start(&mut self.conf_file, ERR_MSG)?;
+
+        res
+    }
+
+    /// Returns public key according to \[Interface\] private key, referenced key is resolved,
+    /// see [`WgConf::private_key`]
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
     pub fn pub_key(&mut self) -> Result<WgPublicKey, WgConfError> {
         if let Some(pub_key) = &self.cache.pub_key {
             return Ok(pub_key.clone());
         }
 
-        let interface = self.interface()?;
-
-        WgKey::generate_public_key(&interface.private_key)
+        WgKey::generate_public_key(&self.private_key()?)
     }
 
     /// Gets Interface settings from [`WgConf``] file
     ///
-    /// Note all the not related to \[Interface\] key-values and duplications will be ignored (the last duplication value will be got) without errors
+    /// Note all the not related to \[Interface\] key-values are kept in order as [`WgInterface::extra()`].
+    /// Repeated Address, DNS and hook keys are accumulated in order, for other duplications the last value will be got
     pub fn interface(&mut self) -> Result<WgInterface, WgConfError> {
         if let Some(interface) = &self.cache.interface {
             return Ok(interface.clone());
         }
 
-        let interface_key_values = self.interface_key_values_from_file()?;
+        let (interface_key_values, interface_location) = self.interface_key_values_from_file()?;
 
-        let interface = WgInterface::from_raw_key_values(interface_key_values)?;
+        let interface = WgInterface::from_raw_key_values(interface_key_values)
+            .map_err(|err| err.with_location(interface_location))?;
         self.cache.interface = Some(interface.clone());
 
         Ok(interface)
@@ -151,7 +239,7 @@ impl WgConf {
             return Ok(self);
         }
 
-        let mut updated_conf = self.update_interface_in_file(new_inteface)?;
+        let mut updated_conf = self.update_interface_in_file(new_inteface, &[], |_| false)?;
 
         updated_conf.cache.pub_key = None;
 
@@ -161,6 +249,7 @@ impl WgConf {
     /// Returns iterator over WG config Peers
     pub fn peers(&mut self) -> Result<WgConfPeers, WgConfError> {
         let peer_start_position = self.peer_start_position(false)?;
+        let peer_start_line = self.peer_start_line()?;
 
         self.conf_file
             .seek(SeekFrom::Start(peer_start_position))
@@ -172,13 +261,17 @@ impl WgConf {
 
         Ok(WgConfPeers {
             err: None,
-            lines: BufReader::new(&mut self.conf_file).lines(),
+            conf_file_name: &self.conf_file_name,
+            lines: fileworks::raw_lines(BufReader::new(&mut self.conf_file)),
             next_peer_exist: false,
             first_iteration: true,
             peer_start_position,
             cur_position: peer_start_position,
             cur_peer_start_position: None,
             cur_peer_end_position: None,
+            cur_line: peer_start_line - 1,
+            cur_peer_line: peer_start_line,
+            next_peer_line: peer_start_line,
         })
     }
 
@@ -201,6 +294,9 @@ impl WgConf {
     }
 
     /// Updates \[Peer\] in WG config file
+    ///
+    /// The peer is updated in place: only the changed lines are rewritten,
+    /// comments and the other lines of the section are kept
     pub fn update_peer(mut self, peer: &WgPeer) -> Result<WgConf, WgConfError> {
         let mut peers = self.peers()?;
         let existing_peer = peers.find(|p| *p.public_key() == *peer.public_key());
@@ -218,112 +314,156 @@ impl WgConf {
             return Ok(self);
         }
 
-        // TODO: fix fileworks::open_file_w_all_permissions (see TODO there) and make overwriting without remove-add funcs
-        // if new peer len == old peer len
+        // get target's peer start & end pos
+        let (start_peer_pos, end_peer_pos) =
+            self.check_peer_exist(&peer.public_key, true)?.unwrap();
+
+        let raw_peer = fileworks::read_string(
+            &mut self.conf_file,
+            start_peer_pos,
+            end_peer_pos,
+            "Couldn't read peer",
+        )?;
+
+        let mut peer_document = WgDocument::parse(&raw_peer);
+        let peer_section = peer_document
+            .sections_mut()
+            .iter_mut()
+            .find(|section| section.is(wg_peer::PEER_TAG))
+            .ok_or(WgConfError::Unexpected(
+                "Couldn't find target peer section".to_string(),
+            ))?;
+        peer_section.sync_all_key_values(&peer.to_raw_key_values(), wg_peer::LIST_KEYS);
+        peer_section.sync_comment_key_values(&peer.to_raw_metadata(), wg_peer::is_metadata_key);
 
-        // remove old peer and add this as new
-        let mut updated_conf = self.remove_peer_by_pub_key(&peer.public_key)?;
-        updated_conf.add_peer(peer)?;
+        let mut updated_conf = self.replace_bytes_in_file(
+            start_peer_pos,
+            end_peer_pos,
+            peer_document.to_string().as_bytes(),
+        )?;
 
         let _ = fileworks::seek_to_start(&mut updated_conf.conf_file, "");
 
         Ok(updated_conf)
     }
 
-    /// Removes \[Peer\] with provided public key from WG config file
-    pub fn remove_peer_by_pub_key(mut self, public_key: &WgKey) -> Result<WgConf, WgConfError> {
-        // get target's peer start & end pos
-        let (start_peer_pos, end_peer_pos) = self.check_peer_exist(public_key, true)?.unwrap();
-
-        let (tmp_file_name, mut tmp_file) = fileworks::create_tmp_file(&self.conf_file_name)?;
-
-        let _ = fileworks::copy_bytes_except(
+    /// Updates provided \[Peer\]s in place rewriting the config file once, see [`WgConf::update_peer`]
+    ///
+    /// Returns [`WgConfError::NotFound`] if any of the peers doesn't exist
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+    pub(crate) fn update_peers_in_file(mut self, peers: &[WgPeer]) -> Result<WgConf, WgConfError> {
+        const ERR_MSG: &'static str = "Couldn't read peers";
+
+        let peer_start_pos = self.peer_start_position(false)?;
+        let end_pos = self
+            .conf_file
+            .metadata()
+            .map_err(|err| WgConfError::Unexpected(format!("{ERR_MSG}: {err}")))?
+            .len();
+
+        let raw_peers = Zeroizing::new(fileworks::read_string(
             &mut self.conf_file,
-            &mut tmp_file,
-            start_peer_pos,
-            end_peer_pos,
-            "Couldn't copy config file to tmp",
-        )
-        .map_err(|err| {
-            let _ = fs::remove_file(&tmp_file_name);
+            peer_start_pos,
+            end_pos,
+            ERR_MSG,
+        )?);
+
+        let mut peers_document = WgDocument::parse(&raw_peers);
+        for peer in peers {
+            let peer_section = peers_document
+                .sections_mut()
+                .iter_mut()
+                .find(|section| {
+                    section.is(wg_peer::PEER_TAG)
+                        && section
+                            .get(wg_peer::PUBLIC_KEY)
+                            .and_then(|public_key| public_key.parse::<WgPublicKey>().ok())
+                            .is_some_and(|public_key| public_key == *peer.public_key())
+                })
+                .ok_or(WgConfError::NotFound(format!(
+                    "Peer with public key '{}'",
+                    peer.public_key().to_string()
+                )))?;
+            peer_section.sync_all_key_values(&peer.to_raw_key_values(), wg_peer::LIST_KEYS);
+            peer_section.sync_comment_key_values(&peer.to_raw_metadata(), wg_peer::is_metadata_key);
+        }
 
-            err
-        })?;
+        let raw_peers = Zeroizing::new(peers_document.to_string());
+        let mut updated_conf =
+            self.replace_bytes_in_file(peer_start_pos, end_pos, raw_peers.as_bytes())?;
 
-        let new_wg_conf_file = fileworks::replace_file(
-            tmp_file,
-            &tmp_file_name,
-            self.conf_file,
-            &self.conf_file_name,
-        )
-        .map_err(|err| {
-            // keep tmp file if main file was already deleted
-            if err.kind() != WgConfErrKind::CriticalKeepTmp {
-                let _ = fs::remove_file(&tmp_file_name);
-            }
+        let _ = fileworks::seek_to_start(&mut updated_conf.conf_file, "");
 
-            err
-        })?;
+        Ok(updated_conf)
+    }
 
-        self.conf_file = new_wg_conf_file;
+    /// Removes \[Peer\] with provided public key from WG config file
+    pub fn remove_peer_by_pub_key(
+        mut self,
+        public_key: &WgPublicKey,
+    ) -> Result<WgConf, WgConfError> {
+        // get target's peer start & end pos
+        let (start_peer_pos, end_peer_pos) = self.check_peer_exist(public_key, true)?.unwrap();
 
-        Ok(self)
+        self.replace_bytes_in_file(start_peer_pos, end_peer_pos, &[])
     }
 
     /// Generates client configuration from own settings and adds it as own peer
     ///
-    /// `aclient_ddress` client's virtual address
+    /// `client_addresses` client's virtual addresses, exactly one address per address family
+    /// of the server's \[Interface\] addresses must be provided (e.g. IPv4 and IPv6 ones for dual-stack server)
     ///
-    /// `server_endpoint` public endpoint which will be used by client to connect to server
+    /// `server_host` public IP address or domain name which will be used by client to connect to server,
+    /// the port of the client's endpoint is server's listen port
     ///
     /// `server_allowed_ips` depends on virtual network purposes.
     /// If WG is used for proxying, `0.0.0.0/0` may be used to send the whole traffic through the VPN.
     /// If WG is used for virtual network as is, and, e.g. network address is 10.*, `10.0.0.0/8` may be used to use VPN only
     /// for this network and other traffic ('open' internet) will not bee sent through the server
     ///
+    /// `dns` client's DNS resolvers and search domains, may be empty
+    ///
     /// `use_preshared_key` indicates if preshared key between server and client will be generated
-    #[cfg(feature = "wg_engine")]
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
     pub fn generate_peer(
         &mut self,
-        client_address: IpAddr,
-        server_endpoint: IpAddr,
+        client_addresses: Vec<IpAddr>,
+        server_host: WgHost,
         server_allowed_ips: Vec<IpNetwork>,
-        dns: Option<IpAddr>,
+        dns: Vec<WgDns>,
         use_preshared_key: bool,
         persistent_keepalive: Option<u16>,
     ) -> Result<WgClientConf, WgConfError> {
         use crate::WgPresharedKey;
 
-        let client_private_key = WgKey::generate_private_key()?;
-        let client_pub_key = WgKey::generate_public_key(&client_private_key)?;
+        let client_addresses = client_addresses_for(&self.interface()?, client_addresses)?;
+
+        let client_key_pair = WgKeyPair::generate()?;
         let server_pub_key = self.pub_key().to_owned()?;
         let preshared_key: Option<WgPresharedKey> = match use_preshared_key {
             true => Some(WgKey::generate_preshared_key()?),
             false => None,
         };
-        let client_address: IpNetwork =
-            (client_address.to_string() + "/32").parse().map_err(|_| {
-                WgConfError::Unexpected(
-                    "Couldn't create address with mask from provided value".to_string(),
-                )
-            })?;
 
         let server_peer_w_client = WgPeer::new(
-            client_pub_key,
-            vec![client_address.clone()],
+            client_key_pair.public_key,
+            client_addresses.clone(),
             None,
             preshared_key.clone(),
             persistent_keepalive,
         );
 
-        let client_interface =
-            WgInterface::new(client_private_key, client_address, None, dns, None, None)?;
+        let client_interface = WgInterface::new(
+            client_key_pair.private_key,
+            client_addresses,
+            None,
+            dns,
+            vec![],
+            vec![],
+        )?;
 
         let server_listen_port = self.interface()?.listen_port.unwrap(); // for server there is always listen port
-        let server_endpoint: SocketAddr =
-            format!("{}:{}", server_endpoint.to_string(), server_listen_port)
-                .parse()
-                .unwrap();
+        let server_endpoint = WgEndpoint::new(server_host, server_listen_port)?;
         let client_peer_w_server = WgPeer::new(
             server_pub_key,
             server_allowed_ips,
@@ -343,33 +483,64 @@ impl WgConf {
         // nothing happens, just moving the variable like in a drop func
     }
 
-    fn interface_key_values_from_file(&mut self) -> Result<HashMap<String, String>, WgConfError> {
+    /// Returns key-values of \[Interface\] and location of its tag
+    fn interface_key_values_from_file(
+        &mut self,
+    ) -> Result<(RawKeyValues, WgConfErrLocation), WgConfError> {
         fileworks::seek_to_start(&mut self.conf_file, "Couldn't get interface section")?;
 
-        let mut raw_key_values: HashMap<String, String> = HashMap::with_capacity(10);
+        let mut raw_key_values: Vec<(String, String)> = Vec::with_capacity(10);
+        let mut interface_location = WgConfErrLocation::new(
+            Some(&self.conf_file_name),
+            1,
+            wg_interface::INTERFACE_TAG,
+            section_name(wg_interface::INTERFACE_TAG),
+        );
 
-        let mut lines_iter = BufReader::new(&mut self.conf_file).lines();
+        let mut lines_iter = fileworks::raw_lines(BufReader::new(&mut self.conf_file));
 
         let mut cur_position: usize = 0;
-        while let Some(line) = lines_iter.next() {
-            match line {
-                Ok(line) => {
-                    cur_position += line.len() + 1; // +1 for EOL
-
-                    let line = line.trim().to_owned();
+        let mut cur_line: usize = 0;
+        while let Some(raw_line) = lines_iter.next() {
+            match raw_line {
+                Ok(raw_line) => {
+                    cur_position += raw_line.len();
+                    cur_line += 1;
+
+                    let line = raw_line.text.trim_start_matches(wg_document::BOM).trim();
                     // Skip comments and empty lines
-                    if line == "" || line.starts_with("#") || line == wg_interface::INTERFACE_TAG {
+                    if line.is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+
+                    if wg_parse::is_tag(line, wg_interface::INTERFACE_TAG) {
+                        interface_location = WgConfErrLocation::new(
+                            Some(&self.conf_file_name),
+                            cur_line,
+                            &raw_line.text,
+                            section_name(wg_interface::INTERFACE_TAG),
+                        );
                         continue;
                     }
 
                     // Stop when the first [Peer] will be reached
-                    if line == wg_peer::PEER_TAG {
-                        cur_position = cur_position - wg_peer::PEER_TAG.len() - 1; // -1 for EOL
+                    if wg_parse::is_tag(line, wg_peer::PEER_TAG) {
+                        cur_position -= raw_line.len();
                         break;
                     }
 
-                    let (k, v) = key_value_from_raw_string(&line)?;
-                    let _ = raw_key_values.insert(k, v);
+                    let location = WgConfErrLocation::new(
+                        Some(&self.conf_file_name),
+                        cur_line,
+                        &raw_line.text,
+                        section_name(wg_interface::INTERFACE_TAG),
+                    );
+                    raw_key_values.push(parse_key_value_line(
+                        &raw_line.text,
+                        location,
+                        wg_interface::KEYS,
+                        wg_interface::validate_raw_key_value,
+                    )?);
                 }
                 Err(err) => {
                     let _ = fileworks::seek_to_start(
@@ -387,31 +558,80 @@ impl WgConf {
 
         let _ = fileworks::seek_to_start(&mut self.conf_file, "");
 
-        Ok(raw_key_values)
+        Ok((raw_key_values, interface_location))
     }
 
-    fn update_interface_in_file(mut self, interface: WgInterface) -> Result<WgConf, WgConfError> {
-        // TODO: fix fileworks::open_file_w_all_permissions (see TODO there) and make overwriting without remove-add funcs
-        // if new interface len == old interface len
+    /// Returns the part of the config file before the first \[Peer\] as document
+    pub(crate) fn interface_document(&mut self) -> Result<WgDocument, WgConfError> {
+        let peer_start_pos = self.peer_start_position(false)?;
 
-        let (tmp_file_name, mut tmp_file) = fileworks::create_tmp_file(&self.conf_file_name)?;
+        let raw_interface = Zeroizing::new(fileworks::read_string(
+            &mut self.conf_file,
+            0,
+            peer_start_pos,
+            "Couldn't read interface",
+        )?);
 
-        // write new interface section into tmp
-        let updated_peer_start_pos =
-            write_interface_to_file(&mut tmp_file, &tmp_file_name, &interface).map_err(|err| {
-                let _ = fs::remove_file(&tmp_file_name);
+        Ok(WgDocument::parse(&raw_interface))
+    }
 
-                err
-            })?;
+    /// Updates \[Interface\] section in the file, `comment_key_values` are synced
+    /// with `# Key = value` comments of the section which keys satisfy `is_managed_comment`
+    pub(crate) fn update_interface_in_file(
+        mut self,
+        interface: WgInterface,
+        comment_key_values: &[(String, String)],
+        is_managed_comment: fn(&str) -> bool,
+    ) -> Result<WgConf, WgConfError> {
+        let peer_start_pos = self.peer_start_position(false)?;
+
+        // update only changed lines of [Interface] keeping comments and unchanged extra key-values
+        let mut interface_document = self.interface_document()?;
+        let interface_section = interface_document
+            .sections_mut()
+            .iter_mut()
+            .find(|section| section.is(wg_interface::INTERFACE_TAG))
+            .ok_or(WgConfError::NotWgConfig(
+                "couldn't find [Interface] section".to_string(),
+            ))?;
+        interface_section
+            .sync_all_key_values(&interface.to_raw_key_values(), wg_interface::LIST_KEYS);
+        interface_section.sync_comment_key_values(comment_key_values, is_managed_comment);
+
+        let raw_interface = interface_document.to_string();
+        let mut updated_conf =
+            self.replace_bytes_in_file(0, peer_start_pos, raw_interface.as_bytes())?;
+
+        updated_conf.cache.interface = Some(interface);
+        updated_conf.cache.peer_start_pos = Some(raw_interface.len() as u64);
+        updated_conf.cache.peer_start_line = Some(raw_interface.matches('\n').count() + 1);
 
-        // copy peers from current conf file to dst
-        self.copy_peers(&mut tmp_file).map_err(|err| {
+        Ok(updated_conf)
+    }
+
+    /// Replaces the interval `from` - `to` of the config file by `replacement` using tmp file
+    fn replace_bytes_in_file(
+        mut self,
+        from: u64,
+        to: u64,
+        replacement: &[u8],
+    ) -> Result<WgConf, WgConfError> {
+        let (tmp_file_name, mut tmp_file) = fileworks::create_tmp_file(&self.conf_file_name)?;
+
+        let _ = fileworks::copy_bytes_replacing(
+            &mut self.conf_file,
+            &mut tmp_file,
+            from,
+            to,
+            replacement,
+            "Couldn't copy config file to tmp",
+        )
+        .map_err(|err| {
             let _ = fs::remove_file(&tmp_file_name);
 
             err
         })?;
 
-        // replace conf by tmp
         let new_wg_conf_file = fileworks::replace_file(
             tmp_file,
             &tmp_file_name,
@@ -428,31 +648,10 @@ impl WgConf {
         })?;
 
         self.conf_file = new_wg_conf_file;
-        self.cache.interface = Some(interface);
-        self.cache.peer_start_pos = Some(updated_peer_start_pos);
 
         Ok(self)
     }
 
-    fn copy_peers(&mut self, mut dst_file: &File) -> Result<(), WgConfError> {
-        // define start position in src to copy
-        let src_peer_start_pos = self.peer_start_position(false)?;
-
-        // set position to copy only Peer section
-        self.conf_file
-            .seek(SeekFrom::Start(src_peer_start_pos))
-            .map_err(|err| {
-                WgConfError::Unexpected(format!("Couldn't copy peers to tmp: {}", err.to_string()))
-            })?;
-
-        // copy to dst
-        io::copy(&mut self.conf_file, &mut dst_file).map_err(|err| {
-            WgConfError::Unexpected(format!("Couldn't copy peers to tmp: {}", err.to_string()))
-        })?;
-
-        Ok(())
-    }
-
     fn peer_start_position(&mut self, ingore_cache: bool) -> Result<u64, WgConfError> {
         if let Some(start_pos) = self.cache.peer_start_pos {
             if !ingore_cache {
@@ -462,19 +661,21 @@ impl WgConf {
 
         fileworks::seek_to_start(&mut self.conf_file, "Couldn't get peer start position")?;
 
-        let mut lines_iter = BufReader::new(&mut self.conf_file).lines();
+        let mut lines_iter = fileworks::raw_lines(BufReader::new(&mut self.conf_file));
 
         let mut cur_position: usize = 0;
+        // line of the first [Peer] or the line after the last one if there are no peers
+        let mut peer_start_line: usize = 1;
         while let Some(line) = lines_iter.next() {
             match line {
                 Ok(line) => {
-                    cur_position += line.len() + 1; // +1 for EOL
-
                     // Stop when the first [Peer] will be reached
-                    if line == wg_peer::PEER_TAG {
-                        cur_position = cur_position - wg_peer::PEER_TAG.len() - 1; // -1 for EOL
+                    if wg_parse::is_tag(&line.text, wg_peer::PEER_TAG) {
                         break;
                     }
+
+                    cur_position += line.len();
+                    peer_start_line += 1;
                 }
                 Err(err) => {
                     let _ = fileworks::seek_to_start(
@@ -490,12 +691,26 @@ impl WgConf {
 
         let cur_position = cur_position as u64;
         self.cache.peer_start_pos = Some(cur_position);
+        self.cache.peer_start_line = Some(peer_start_line);
 
         let _ = fileworks::seek_to_start(&mut self.conf_file, "");
 
         Ok(cur_position)
     }
 
+    /// Returns 1-based line number of the first \[Peer\] tag
+    fn peer_start_line(&mut self) -> Result<usize, WgConfError> {
+        if let Some(start_line) = self.cache.peer_start_line {
+            return Ok(start_line);
+        }
+
+        let _ = self.peer_start_position(true)?;
+
+        self.cache.peer_start_line.ok_or(WgConfError::Unexpected(
+            "Couldn't define peer start line".to_string(),
+        ))
+    }
+
     /// returns peer start & end position if peer must exist and it exists
     fn check_peer_exist(
         &mut self,
@@ -541,19 +756,25 @@ impl WgConf {
 /// one should to invoke `self.check_err()` to ensure that iterations were successfull while using the iterator
 pub struct WgConfPeers<'a> {
     err: Option<WgConfError>,
-    lines: Lines<BufReader<&'a mut File>>,
+    conf_file_name: &'a str,
+    lines: RawLines<BufReader<&'a mut File>>,
     next_peer_exist: bool,
     first_iteration: bool,
     peer_start_position: u64,
     cur_position: u64,
     cur_peer_start_position: Option<u64>,
     cur_peer_end_position: Option<u64>,
+    cur_line: usize,
+    cur_peer_line: usize,
+    next_peer_line: usize,
 }
 
 impl Iterator for WgConfPeers<'_> {
     type Item = WgPeer;
 
-    /// Note all the not related to \[Peer\] key-values and duplications will be ignored (the last duplication value will be got) without errors
+    /// Note all the not related to \[Peer\] key-values are kept in order as [`WgPeer::extra()`].
+    /// Repeated AllowedIPs are merged in order, for other duplications the last value will be got.
+    /// Peer's name, description and metadata are read from `# Key = value` comments of the section
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(_) = &self.err {
             return None;
@@ -566,13 +787,26 @@ impl Iterator for WgConfPeers<'_> {
         }
 
         match self.next_peer_key_values() {
-            Ok(raw_key_values) => {
+            Ok((raw_key_values, raw_metadata)) => {
                 if raw_key_values.len() == 0 {
                     return None;
                 }
 
-                match WgPeer::from_raw_key_values(raw_key_values) {
-                    Ok(peer) => Some(peer),
+                let peer = WgPeer::from_raw_key_values(raw_key_values).map_err(|err| {
+                    err.with_location(WgConfErrLocation::new(
+                        Some(self.conf_file_name),
+                        self.cur_peer_line,
+                        wg_peer::PEER_TAG,
+                        section_name(wg_peer::PEER_TAG),
+                    ))
+                });
+
+                match peer {
+                    Ok(mut peer) => {
+                        peer.set_raw_metadata(raw_metadata);
+
+                        Some(peer)
+                    }
                     Err(err) => {
                         self.err = Some(err);
 
@@ -608,48 +842,69 @@ impl WgConfPeers<'_> {
         Ok(())
     }
 
-    fn next_peer_key_values(&mut self) -> Result<HashMap<String, String>, WgConfError> {
-        let mut raw_key_values: HashMap<String, String> = HashMap::with_capacity(10);
+    /// Returns key-values and metadata comments of the next peer
+    fn next_peer_key_values(&mut self) -> Result<(RawKeyValues, RawKeyValues), WgConfError> {
+        let mut raw_key_values: Vec<(String, String)> = Vec::with_capacity(10);
+        let mut raw_metadata: Vec<(String, String)> = Vec::new();
 
         self.next_peer_exist = false;
 
         if !self.first_iteration {
-            self.cur_peer_start_position =
-                Some(self.cur_position - wg_peer::PEER_TAG.len() as u64 - 1);
+            // the current peer starts where the previous one ended
+            self.cur_peer_start_position = self.cur_peer_end_position;
+            self.cur_peer_line = self.next_peer_line;
         }
 
-        while let Some(line) = self.lines.next() {
-            match line {
-                Ok(line) => {
-                    self.cur_position += line.len() as u64 + 1; // +1 for EOL
+        while let Some(raw_line) = self.lines.next() {
+            match raw_line {
+                Ok(raw_line) => {
+                    self.cur_position += raw_line.len() as u64;
+                    self.cur_line += 1;
+
+                    let line = raw_line.text.trim_start_matches(wg_document::BOM).trim();
+                    // Skip comments and empty lines, keep metadata comments
+                    if line.is_empty() || line.starts_with('#') {
+                        if let Some((k, v)) = wg_document::comment_key_value(line) {
+                            if wg_peer::is_metadata_key(k) {
+                                raw_metadata.push((k.to_owned(), v.to_owned()));
+                            }
+                        }
 
-                    let line = line.trim().to_owned();
-                    // Skip comments and empty lines
-                    if line == "" || line.starts_with("#") {
                         continue;
                     }
 
-                    if line == wg_peer::PEER_TAG {
+                    if wg_parse::is_tag(line, wg_peer::PEER_TAG) {
                         // current section's peer tag will be found only in the first iteration,
                         // in the next iteration the coursor position will be after it as it was read in the prev iteration,
                         // so, in all iterations except the frst one peer tag means the end of the current iteration
                         if self.first_iteration {
                             self.cur_peer_start_position = Some(self.peer_start_position);
+                            self.cur_peer_line = self.cur_line;
                             continue;
                         } else {
                             self.cur_peer_end_position =
-                                Some(self.cur_position - wg_peer::PEER_TAG.len() as u64 - 1); // -1 for EOL
+                                Some(self.cur_position - raw_line.len() as u64);
                             self.next_peer_exist = true;
+                            self.next_peer_line = self.cur_line;
                             break;
                         }
                     }
 
                     self.first_iteration = false;
 
-                    match key_value_from_raw_string(&line) {
-                        Ok((k, v)) => {
-                            let _ = raw_key_values.insert(k, v);
-                        }
+                    let location = WgConfErrLocation::new(
+                        Some(self.conf_file_name),
+                        self.cur_line,
+                        &raw_line.text,
+                        section_name(wg_peer::PEER_TAG),
+                    );
+                    match parse_key_value_line(
+                        &raw_line.text,
+                        location,
+                        wg_peer::KEYS,
+                        wg_peer::validate_raw_key_value,
+                    ) {
+                        Ok(key_value) => raw_key_values.push(key_value),
                         Err(err) => {
                             self.err = Some(err.clone());
 
@@ -671,28 +926,30 @@ impl WgConfPeers<'_> {
             self.err = Some(WgConfError::EOF);
         }
 
-        Ok(raw_key_values)
+        Ok((raw_key_values, raw_metadata))
     }
 }
 
-/// Checks if provided file is WG config
+/// Checks if provided file is WG config using [`WgParseMode::Lenient`](crate::WgParseMode::Lenient) parsing mode
 ///
 /// Returns [`WgConfError::NotWgConfig`] if checks failed
 pub fn check_if_wg_conf(file_name: &str, file: &mut File) -> Result<(), WgConfError> {
-    const ERR_MSG: &'static str = "Couldn't define if file is WG config";
+    let _ = check_structure(file_name, file, &WgParseOptions::default())?;
 
-    if Path::new(file_name).extension().unwrap_or(&OsStr::new("")) != CONF_EXTENSION {
-        return Err(WgConfError::NotWgConfig("invalid extension".to_string()));
-    }
+    Ok(())
+}
+
+/// Checks that the first line of the file is exact [Interface] tag
+fn check_starts_with_interface_tag(file: &mut File) -> Result<(), WgConfError> {
+    const ERR_MSG: &'static str = "Couldn't define if file is WG config";
 
     fileworks::seek_to_start(file, ERR_MSG)?;
 
     let mut lines_iter = BufReader::new(&mut *file).lines();
     let res = match lines_iter.next() {
         Some(first_line) => {
-            if first_line.map_err(|err| {
-                WgConfError::Unexpected(format!("{}: {}", ERR_MSG, err.to_string()))
-            })? == wg_interface::INTERFACE_TAG
+            if first_line.map_err(|err| WgConfError::Unexpected(format!("{ERR_MSG}: {err}")))?
+                == wg_interface::INTERFACE_TAG
             {
                 Ok(())
             } else {
@@ -709,6 +966,31 @@ pub fn check_if_wg_conf(file_name: &str, file: &mut File) -> Result<(), WgConfEr
     res
 }
 
+/// Checks extension and structure of the file according to `options` and returns tolerated deviations
+fn check_structure(
+    file_name: &str,
+    file: &mut File,
+    options: &WgParseOptions,
+) -> Result<Vec<WgParseWarning>, WgConfError> {
+    const ERR_MSG: &'static str = "Couldn't define if file is WG config";
+
+    if Path::new(file_name).extension().unwrap_or(&OsStr::new("")) != CONF_EXTENSION {
+        return Err(WgConfError::NotWgConfig("invalid extension".to_string()));
+    }
+
+    fileworks::seek_to_start(file, ERR_MSG)?;
+
+    let mut text = String::new();
+    let res = file
+        .read_to_string(&mut text)
+        .map_err(|err| WgConfError::Unexpected(format!("{ERR_MSG}: {err}")))
+        .and_then(|_| options.check_structure(Some(file_name), &text));
+
+    fileworks::seek_to_start(file, ERR_MSG)?;
+
+    res
+}
+
 fn key_value_from_raw_string(raw_string: &str) -> Result<(String, String), WgConfError> {
     if !raw_string.contains('=') {
         return Err(WgConfError::Unexpected(format!(
@@ -726,6 +1008,79 @@ fn key_value_from_raw_string(raw_string: &str) -> Result<(String, String), WgCon
     return Ok((key.to_owned(), value.to_owned()));
 }
 
+/// Parses key-value line of the section and validates its value with `validate`,
+/// errors are located at the line, the offending value or the whole line is highlighted
+///
+/// Known keys are case-insensitive, they are returned in the known spelling
+pub(crate) fn parse_key_value_line(
+    raw_line: &str,
+    location: WgConfErrLocation,
+    known_keys: &[&str],
+    validate: fn(&str, &str) -> Result<(), WgConfError>,
+) -> Result<(String, String), WgConfError> {
+    let line = raw_line.trim_start_matches(wg_document::BOM).trim();
+    let (key, value) = key_value_from_raw_string(line).map_err(|err| {
+        let line_span = wg_document::trimmed_span(raw_line, 0, raw_line.len());
+        err.with_location(
+            location
+                .clone()
+                .at_columns(char_columns(raw_line, line_span)),
+        )
+    })?;
+
+    let key = wg_parse::canonical_key(key, known_keys);
+
+    validate(&key, &value).map_err(|err| {
+        let value_span = match wg_document::line_kind(raw_line) {
+            WgDocumentLineKind::KeyValue { value, .. } => value,
+            _ => (0, raw_line.len()),
+        };
+        err.with_location(location.at_key(&key, char_columns(raw_line, value_span)))
+    })?;
+
+    Ok((key, value))
+}
+
+/// Validates client addresses against the server's \[Interface\] addresses
+/// and returns them as host networks (/32 for IPv4 and /128 for IPv6)
+#[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+fn client_addresses_for(
+    server_interface: &WgInterface,
+    client_addresses: Vec<IpAddr>,
+) -> Result<Vec<IpNetwork>, WgConfError> {
+    for server_address in server_interface.addresses() {
+        let same_family_count = client_addresses
+            .iter()
+            .filter(|address| address.is_ipv4() == server_address.is_ipv4())
+            .count();
+
+        if same_family_count != 1 {
+            return Err(WgConfError::ValidationFailed(format!(
+                "exactly one client address must be provided for server address {}",
+                server_address
+            )));
+        }
+    }
+
+    client_addresses
+        .into_iter()
+        .map(|address| {
+            if !server_interface
+                .addresses()
+                .iter()
+                .any(|server_address| server_address.contains(address))
+            {
+                return Err(WgConfError::ValidationFailed(format!(
+                    "client address {} is out of the server networks",
+                    address
+                )));
+            }
+
+            Ok(IpNetwork::from(address))
+        })
+        .collect()
+}
+
 /// Returns peer start position
 fn write_interface_to_file(
     file: &mut File,
@@ -779,11 +1134,15 @@ fn write_peers_to_file(
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::{error::WgConfErrKind, WgPresharedKey, WgPrivateKey, WgPublicKey};
 
     use super::*;
-    use std::{fs, io::Write, net::IpAddr, str::FromStr};
+    use ipnetwork::IpNetwork;
+    use std::{
+        fs,
+        io::{BufRead, Write},
+    };
 
     const INTERFACE_CONTENT: &'static str = "[Interface]
 PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
@@ -804,7 +1163,7 @@ PresharedKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
 PersistentKeepalive = 25
 ";
 
-    struct Deferred(pub Box<dyn Fn() -> ()>);
+    pub(crate) struct Deferred(pub Box<dyn Fn() -> ()>);
 
     impl Drop for Deferred {
         fn drop(&mut self) {
@@ -817,7 +1176,7 @@ PersistentKeepalive = 25
         // Arrange
         const TEST_CONF_FILE: &str = "wg1.conf";
 
-        #[cfg(feature = "wg_engine")]
+        #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
         let (private_key, psk, peer1_pubkey, peer2_pubkey): (
             WgPrivateKey,
             WgPresharedKey,
@@ -829,7 +1188,7 @@ PersistentKeepalive = 25
             WgKey::generate_public_key(&WgKey::generate_private_key().unwrap()).unwrap(),
             WgKey::generate_public_key(&WgKey::generate_private_key().unwrap()).unwrap(),
         );
-        #[cfg(not(feature = "wg_engine"))]
+        #[cfg(not(any(feature = "wg_engine", feature = "native_keys")))]
         let (private_key, psk, peer1_pubkey, peer2_pubkey): (
             WgPrivateKey,
             WgPresharedKey,
@@ -853,11 +1212,11 @@ PersistentKeepalive = 25
 
         let interface = WgInterface::new(
             private_key.clone(),
-            "10.0.0.1/24".parse().unwrap(),
+            vec!["10.0.0.1/24".parse().unwrap()],
             Some(8082),
-            None,
-            Some("some-script".to_string()),
-            Some("some-other-script".to_string()),
+            vec![],
+            vec!["some-script".to_string()],
+            vec!["some-other-script".to_string()],
         )
         .unwrap();
 
@@ -895,9 +1254,9 @@ PersistentKeepalive = 25
         assert!(fs::exists(TEST_CONF_FILE).unwrap());
         let mut lines =
             BufReader::new(fileworks::open_file_w_all_permissions(TEST_CONF_FILE).unwrap()).lines();
-        assert!(lines.any(|l| l.is_ok() && l.unwrap().contains(&private_key.to_string())));
+        assert!(lines.any(|l| l.is_ok() && l.unwrap().contains(&private_key.expose_secret())));
         assert!(lines.any(|l| l.is_ok() && l.unwrap().contains(&peer1_pubkey.to_string())));
-        assert!(lines.any(|l| l.is_ok() && l.unwrap().contains(&psk.to_string())));
+        assert!(lines.any(|l| l.is_ok() && l.unwrap().contains(&psk.expose_secret())));
         assert!(lines.any(|l| l.is_ok() && l.unwrap().contains(&peer2_pubkey.to_string())));
     }
 
@@ -911,11 +1270,11 @@ PersistentKeepalive = 25
                 .to_string()
                 .parse()
                 .unwrap(),
-            "192.168.130.131/25".parse().unwrap(),
+            vec!["192.168.130.131/25".parse().unwrap()],
             Some(8082),
-            None,
-            Some("some-script".to_string()),
-            Some("some-other-script".to_string()),
+            vec![],
+            vec!["some-script".to_string()],
+            vec!["some-other-script".to_string()],
         )
         .unwrap();
 
@@ -1005,12 +1364,13 @@ PersistentKeepalive = 25
         let interface = interface.unwrap();
         assert_eq!(
             "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
-            interface.private_key.to_string()
+            interface.private_key().unwrap().expose_secret()
         );
-        assert_eq!("10.0.0.1/24", interface.address.to_string());
+        assert_eq!(1, interface.addresses.len());
+        assert_eq!("10.0.0.1/24", interface.addresses[0].to_string());
         assert_eq!(8080, interface.listen_port.unwrap());
-        assert_eq!(Some("ufw allow 8080/udp"), interface.post_up());
-        assert_eq!(Some("ufw delete allow 8080/udp"), interface.post_down());
+        assert_eq!(["ufw allow 8080/udp"], interface.post_up());
+        assert_eq!(["ufw delete allow 8080/udp"], interface.post_down());
         assert!(wg_conf.cache.interface.is_some());
         assert!(wg_conf.cache.peer_start_pos.is_some());
     }
@@ -1042,12 +1402,41 @@ PostUp = ufw allow 8080/udp";
         let interface = interface.unwrap();
         assert_eq!(
             "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
-            interface.private_key.to_string()
+            interface.private_key().unwrap().expose_secret()
         );
-        assert_eq!("10.0.0.1/24", interface.address.to_string());
+        assert_eq!(1, interface.addresses.len());
+        assert_eq!("10.0.0.1/24", interface.addresses[0].to_string());
         assert_eq!(8080, interface.listen_port.unwrap());
-        assert_eq!(Some("ufw allow 8080/udp"), interface.post_up());
-        assert_eq!(None, interface.post_down());
+        assert_eq!(["ufw allow 8080/udp"], interface.post_up());
+        assert!(interface.post_down().is_empty());
+    }
+
+    #[test]
+    fn interface_0_multiple_addresses_0_merges_all_lines() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg22.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24,fd00::1/64
+ListenPort = 8080
+Address = 10.1.0.1/24
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let interface = wg_conf.interface();
+
+        // Assert
+        let interface = interface.unwrap();
+        assert_eq!(
+            vec![
+                "10.0.0.1/24".parse::<IpNetwork>().unwrap(),
+                "fd00::1/64".parse().unwrap(),
+                "10.1.0.1/24".parse().unwrap()
+            ],
+            interface.addresses()
+        );
     }
 
     #[test]
@@ -1069,57 +1458,311 @@ PrivateKey
         let err = interface.unwrap_err();
         assert!(err.kind() == WgConfErrKind::Unexpected);
         assert!(err.to_string().contains("not key-value"));
+        let location = err.location().unwrap();
+        assert_eq!(Some(TEST_CONF_FILE), location.file());
+        assert_eq!(Some(3), location.line());
+        assert_eq!(Some((1, 11)), location.columns());
+        assert_eq!(Some("Interface"), location.section());
     }
 
     #[test]
-    fn update_interface_0_common_scenario() {
+    fn peers_iter_0_invalid_value_0_returns_located_err() {
         // Arrange
-        const TEST_CONF_FILE: &str = "wg9.conf";
-        let content = INTERFACE_CONTENT.to_string() + "\n" + PEER_CONTENT;
-
-        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
-        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        const TEST_CONF_FILE: &str = "wg30.conf";
+        let _cleanup = prepare_test_conf(
+            TEST_CONF_FILE,
+            "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
 
-        // invoke to set peer start position cache
-        let _ = wg_conf.interface();
-        let old_peer_pos = wg_conf.cache.peer_start_pos.unwrap();
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
 
-        let new_interface = WgInterface::new(
-            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
-                .to_string()
-                .parse()
-                .unwrap(),
-            "192.168.130.131/25".parse().unwrap(),
-            Some(8082),
-            Some(IpAddr::from_str("8.8.8.8").unwrap()),
-            Some("some-script".to_string()),
-            Some("some-other-script".to_string()),
-        )
-        .unwrap();
+# second peer
+[Peer]
+PublicKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+AllowedIPs = 10.0.0.3/32,  10.0.0.300/32
+",
+        );
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
 
         // Act
-        let updated_conf = wg_conf.update_interface(new_interface.clone());
-        assert!(updated_conf.is_ok());
-        let mut updated_conf = updated_conf.unwrap();
-        let interface_by_method = updated_conf.interface();
+        let mut peers = wg_conf.peers().unwrap();
+        let peers_count = peers.by_ref().count();
+        let err = peers.check_err().unwrap_err();
 
         // Assert
-        let cur_peer_start_pos = updated_conf.cache.peer_start_pos.unwrap();
-        assert_ne!(old_peer_pos, cur_peer_start_pos);
-        assert!(interface_by_method.is_ok());
-        let interface_by_method = interface_by_method.unwrap();
-        assert_eq!(new_interface, interface_by_method);
-        assert_eq!(2, updated_conf.peers().unwrap().count());
+        assert_eq!(1, peers_count);
+        assert_eq!(WgConfErrKind::ValidationFailed, err.kind());
+        let location = err.location().unwrap();
+        assert_eq!(Some(13), location.line());
+        assert_eq!(Some((14, 41)), location.columns());
+        assert_eq!(Some("Peer"), location.section());
+        assert_eq!(Some("AllowedIPs"), location.key());
+        assert_eq!(
+            "error: WG validation failed: allowed IPs must be addresses with mask (e.g. 10.0.0.1/8)
+  --> wg30.conf:13:14
+   |
+13 | AllowedIPs = 10.0.0.3/32,  10.0.0.300/32
+   |              ^^^^^^^^^^^^^^^^^^^^^^^^^^^
+   = [Peer] section, key 'AllowedIPs'
+",
+            err.render()
+        );
     }
 
     #[test]
-    fn peers_iter_0_common_scenario() {
+    fn peers_iter_0_missing_public_key_0_returns_err_located_at_peer_tag() {
         // Arrange
-        const TEST_CONF_FILE: &str = "wg10.conf";
-        let content = INTERFACE_CONTENT.to_string() + "\n" + PEER_CONTENT;
+        const TEST_CONF_FILE: &str = "wg31.conf";
+        let _cleanup = prepare_test_conf(
+            TEST_CONF_FILE,
+            "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
 
-        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
-        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+
+[Peer]
+AllowedIPs = 10.0.0.3/32
+",
+        );
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let mut peers = wg_conf.peers().unwrap();
+        let _ = peers.by_ref().count();
+        let err = peers.check_err().unwrap_err();
+
+        // Assert
+        let location = err.location().unwrap();
+        assert_eq!(Some(10), location.line());
+        assert_eq!(Some("[Peer]"), location.line_text());
+        assert_eq!(Some("PublicKey"), location.key());
+        assert!(err
+            .to_string()
+            .starts_with("wg31.conf:10 ([Peer] section, key 'PublicKey'): "));
+    }
+
+    #[test]
+    fn open_with_options_0_strict_0_returns_located_err_on_deviation() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg32.conf";
+        let contents = [
+            ("# leading comment\n".to_string() + INTERFACE_CONTENT, 1),
+            (INTERFACE_CONTENT.to_string() + "Foo = bar\n", 7),
+            (INTERFACE_CONTENT.to_string() + "ListenPort = 8081\n", 7),
+            (INTERFACE_CONTENT.to_string() + "\n[ Peer ]\n", 8),
+            (INTERFACE_CONTENT.replace('\n', "\r\n"), 1),
+        ];
+
+        for (content, line) in contents {
+            let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+
+            // Act
+            let strict_res = WgConf::open_with_options(TEST_CONF_FILE, WgParseOptions::strict());
+            let lenient_res = WgConf::open_with_options(TEST_CONF_FILE, WgParseOptions::lenient());
+
+            // Assert
+            let err = strict_res.unwrap_err();
+            assert_eq!(WgConfErrKind::NotWgConfig, err.kind());
+            assert_eq!(Some(line), err.location().unwrap().line());
+            assert_eq!(1, lenient_res.unwrap().warnings().len());
+        }
+    }
+
+    #[test]
+    fn open_0_non_canonical_header_0_returns_not_wg_conf_err_and_lenient_reports_warnings() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg33.conf";
+        let content = "\u{feff}# wg0 server\r
+[Interface]\r
+privatekey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=\r
+Address = 10.0.0.1/24\r
+ListenPort = 8080\r
+\r
+[ Peer ]\r
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=\r
+allowedips = 10.0.0.2/32\r
+\r
+[Peer]\r
+PublicKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=\r
+AllowedIPs = 10.0.0.3/32\r
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, content);
+
+        // Act
+        let open_res = WgConf::open(TEST_CONF_FILE);
+        let mut wg_conf =
+            WgConf::open_with_options(TEST_CONF_FILE, WgParseOptions::lenient()).unwrap();
+        let interface = wg_conf.interface().unwrap();
+        let peers: Vec<WgPeer> = wg_conf.peers().unwrap().collect();
+
+        // Assert
+        assert!(matches!(open_res, Err(WgConfError::NotWgConfig(_))));
+        let messages: Vec<&str> = wg_conf
+            .warnings()
+            .iter()
+            .map(|warning| warning.message())
+            .collect();
+        assert_eq!(
+            vec![
+                "byte order mark at the start of the file",
+                "CRLF line endings",
+                "content before [Interface] section",
+                "key 'privatekey' must be written as 'PrivateKey'",
+                "section tag must be written as [Peer]",
+                "key 'allowedips' must be written as 'AllowedIPs'",
+            ],
+            messages
+        );
+        assert_eq!(Some(3), wg_conf.warnings()[3].location().line());
+        assert_eq!(
+            "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
+            interface.private_key().unwrap().expose_secret()
+        );
+        assert_eq!(2, peers.len());
+        assert_eq!(
+            vec!["10.0.0.2/32".parse::<IpNetwork>().unwrap()],
+            *peers[0].allowed_ips()
+        );
+    }
+
+    #[test]
+    fn update_peer_0_crlf_0_keeps_positions_and_line_endings() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg34.conf";
+        let content = (INTERFACE_CONTENT.to_string() + "\n" + PEER_CONTENT).replace('\n', "\r\n");
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let peer_to_update = WgPeer::new(
+            "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.5/32".parse().unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        // Act
+        let wg_conf = wg_conf.update_peer(&peer_to_update).unwrap();
+        let mut wg_conf = wg_conf
+            .remove_peer_by_pub_key(
+                &"Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4="
+                    .parse()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        // Assert
+        let peers: Vec<WgPeer> = wg_conf.peers().unwrap().collect();
+        assert_eq!(1, peers.len());
+        assert_eq!(
+            vec!["10.0.0.5/32".parse::<IpNetwork>().unwrap()],
+            *peers[0].allowed_ips()
+        );
+        let expected = (INTERFACE_CONTENT.to_string()
+            + "\n[Peer]\nPublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=\nAllowedIPs = 10.0.0.5/32\n\n")
+            .replace('\n', "\r\n");
+        assert_eq!(expected, fs::read_to_string(TEST_CONF_FILE).unwrap());
+    }
+
+    #[test]
+    fn update_interface_0_common_scenario() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg9.conf";
+        let content = INTERFACE_CONTENT.to_string() + "\n" + PEER_CONTENT;
+
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // invoke to set peer start position cache
+        let _ = wg_conf.interface();
+        let old_peer_pos = wg_conf.cache.peer_start_pos.unwrap();
+
+        let new_interface = WgInterface::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .to_string()
+                .parse()
+                .unwrap(),
+            vec!["192.168.130.131/25".parse().unwrap()],
+            Some(8082),
+            vec!["8.8.8.8".parse().unwrap()],
+            vec!["some-script".to_string()],
+            vec!["some-other-script".to_string()],
+        )
+        .unwrap();
+
+        // Act
+        let updated_conf = wg_conf.update_interface(new_interface.clone());
+        assert!(updated_conf.is_ok());
+        let mut updated_conf = updated_conf.unwrap();
+        let interface_by_method = updated_conf.interface();
+
+        // Assert
+        let cur_peer_start_pos = updated_conf.cache.peer_start_pos.unwrap();
+        assert_ne!(old_peer_pos, cur_peer_start_pos);
+        assert!(interface_by_method.is_ok());
+        let interface_by_method = interface_by_method.unwrap();
+        assert_eq!(new_interface, interface_by_method);
+        assert_eq!(2, updated_conf.peers().unwrap().count());
+    }
+
+    #[test]
+    fn update_interface_0_wg_quick_fields_0_keeps_all_fields() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg21.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+MTU = 1420
+Table = 51820
+FwMark = 0xca6c
+PreUp = pre-up-script
+PreDown = pre-down-script
+SaveConfig = true
+";
+        let content = CONTENT.to_string() + "\n" + PEER_CONTENT;
+
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let mut new_interface = wg_conf.interface().unwrap();
+        new_interface.listen_port = Some(8082);
+
+        // Act
+        let mut updated_conf = wg_conf.update_interface(new_interface.clone()).unwrap();
+        updated_conf.cache.interface = None;
+        let interface_from_file = updated_conf.interface().unwrap();
+
+        // Assert
+        assert_eq!(new_interface, interface_from_file);
+        assert_eq!(Some(1420), interface_from_file.mtu());
+        assert_eq!(
+            Some(&crate::WgTable::Id(51820)),
+            interface_from_file.table()
+        );
+        assert_eq!(Some(51820), interface_from_file.fw_mark());
+        assert_eq!(["pre-up-script"], interface_from_file.pre_up());
+        assert_eq!(["pre-down-script"], interface_from_file.pre_down());
+        assert!(interface_from_file.save_config());
+        assert_eq!(2, updated_conf.peers().unwrap().count());
+    }
+
+    #[test]
+    fn peers_iter_0_common_scenario() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg10.conf";
+        let content = INTERFACE_CONTENT.to_string() + "\n" + PEER_CONTENT;
+
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
         let mut peers_iter = wg_conf.peers().unwrap();
 
         // Act
@@ -1158,7 +1801,7 @@ PrivateKey
                 assert!(peer.preshared_key.is_some());
                 assert_eq!(
                     "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
-                    peer.preshared_key.unwrap().to_string()
+                    peer.preshared_key.unwrap().expose_secret()
                 );
                 assert!(peer.persistent_keepalive.is_some());
                 assert_eq!(25, peer.persistent_keepalive.unwrap());
@@ -1298,7 +1941,7 @@ DNS = 8.8.8.8
 
         let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
         let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
-        let target_key: WgKey = "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
+        let target_key: WgPublicKey = "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
             .parse()
             .unwrap();
 
@@ -1324,7 +1967,7 @@ DNS = 8.8.8.8
                 assert!(peer.preshared_key.is_some());
                 assert_eq!(
                     "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
-                    peer.preshared_key.unwrap().to_string()
+                    peer.preshared_key.unwrap().expose_secret()
                 );
                 assert!(peer.persistent_keepalive.is_some());
                 assert_eq!(25, peer.persistent_keepalive.unwrap());
@@ -1343,7 +1986,7 @@ DNS = 8.8.8.8
 
         let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
         let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
-        let target_key: WgKey = "Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4="
+        let target_key: WgPublicKey = "Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4="
             .parse()
             .unwrap();
 
@@ -1390,7 +2033,7 @@ DNS = 0.0.0.0
 
         let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
         let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
-        let target_key: WgKey = "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c="
+        let target_key: WgPublicKey = "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c="
             .parse()
             .unwrap();
 
@@ -1434,7 +2077,7 @@ DNS = 0.0.0.0
                 assert!(peer.preshared_key.is_some());
                 assert_eq!(
                     "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
-                    peer.preshared_key.unwrap().to_string()
+                    peer.preshared_key.unwrap().expose_secret()
                 );
                 assert!(peer.persistent_keepalive.is_some());
                 assert_eq!(25, peer.persistent_keepalive.unwrap());
@@ -1448,14 +2091,14 @@ DNS = 0.0.0.0
     }
 
     #[test]
-    fn update_peer_0_same_len_0_removes_and_adds_to_end() {
+    fn update_peer_0_updates_in_place() {
         // Arrange
         const TEST_CONF_FILE: &str = "wg18.conf";
         let content = INTERFACE_CONTENT.to_string() + "\n" + PEER_CONTENT + "\n";
 
         let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
         let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
-        let target_key: WgKey = "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
+        let target_key: WgPublicKey = "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
             .parse()
             .unwrap();
         let peer_to_update = WgPeer::new(
@@ -1470,17 +2113,292 @@ DNS = 0.0.0.0
         let update_res = wg_conf.update_peer(&peer_to_update);
         let mut wg_conf = update_res.unwrap();
         let mut peers = wg_conf.peers().unwrap();
-        let _ = peers.next();
         let updated_peer = peers.next().unwrap();
+        let _ = peers.next();
 
         // Assert
         assert_eq!(target_key, *updated_peer.public_key());
+        assert!(peers.next().is_none());
         assert_eq!(
             "10.0.0.4/32",
             updated_peer.allowed_ips().first().unwrap().to_string()
         );
     }
 
+    #[test]
+    fn update_peer_0_keeps_comments_and_unchanged_lines() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg24.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+
+[Peer]
+# laptop of the support team
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs   =   10.0.0.2/32
+PersistentKeepalive = 25
+
+# next peer
+[Peer]
+PublicKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+AllowedIPs = 10.0.0.3/32
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let mut peer = wg_conf.peers().unwrap().next().unwrap();
+        peer.persistent_keepalive = Some(10);
+
+        // Act
+        let wg_conf = wg_conf.update_peer(&peer).unwrap();
+
+        // Assert
+        wg_conf.close();
+        assert_eq!(
+            CONTENT.replace("PersistentKeepalive = 25", "PersistentKeepalive = 10"),
+            fs::read_to_string(TEST_CONF_FILE).unwrap()
+        );
+    }
+
+    #[test]
+    fn update_interface_0_keeps_comments_and_unknown_keys() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg25.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+# IPv4 and IPv6 networks
+Address = 10.0.0.1/24
+Address = fd00::1/64
+ListenPort = 8080
+CustomKey = custom value
+PostUp = ufw allow 8080/udp
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let mut interface = wg_conf.interface().unwrap();
+        interface.listen_port = Some(8082);
+        interface.set_mtu(Some(1420)).unwrap();
+
+        // Act
+        let mut wg_conf = wg_conf.update_interface(interface).unwrap();
+
+        // Assert
+        assert_eq!(1, wg_conf.peers().unwrap().count());
+        wg_conf.close();
+        assert_eq!(
+            CONTENT
+                .replace("ListenPort = 8080", "ListenPort = 8082")
+                .replace(
+                    "PostUp = ufw allow 8080/udp\n",
+                    "PostUp = ufw allow 8080/udp\nMTU = 1420\n"
+                ),
+            fs::read_to_string(TEST_CONF_FILE).unwrap()
+        );
+    }
+
+    #[test]
+    fn peers_iter_0_metadata_comments_0_reads_name_and_metadata() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg26.conf";
+        let _cleanup = prepare_test_conf(
+            TEST_CONF_FILE,
+            "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+
+[Peer]
+# Name = alice-laptop
+# Description = Alice from support
+# created by ops, see https://example.com/?a=b
+# PersistentKeepalive = 25
+#Meta.Ticket=SUP-42
+# Owner = ops
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+
+[Peer]
+PublicKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+AllowedIPs = 10.0.0.3/32
+",
+        );
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let peers: Vec<WgPeer> = wg_conf.peers().unwrap().collect();
+
+        // Assert
+        assert_eq!(2, peers.len());
+        assert_eq!(Some("alice-laptop"), peers[0].name());
+        assert_eq!(Some("Alice from support"), peers[0].description());
+        assert_eq!(
+            &[("Ticket".to_string(), "SUP-42".to_string())],
+            peers[0].metadata()
+        );
+        assert_eq!(None, peers[0].persistent_keepalive());
+        assert_eq!(None, peers[1].name());
+        assert!(peers[1].metadata().is_empty());
+    }
+
+    #[test]
+    fn update_peer_0_metadata_0_edits_comments_in_place() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg27.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+
+[Peer]
+# Name = alice-laptop
+# support notes
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let mut peer = wg_conf.peers().unwrap().next().unwrap();
+        peer.set_name(Some("alice-desktop".to_string())).unwrap();
+        peer.set_metadata("Ticket", "SUP-42").unwrap();
+
+        // Act
+        let mut wg_conf = wg_conf.update_peer(&peer).unwrap();
+
+        // Assert
+        assert_eq!(peer, wg_conf.peers().unwrap().next().unwrap());
+        wg_conf.close();
+        assert_eq!(
+            CONTENT.replace(
+                "# Name = alice-laptop\n",
+                "# Name = alice-desktop\n# Meta.Ticket = SUP-42\n"
+            ),
+            fs::read_to_string(TEST_CONF_FILE).unwrap()
+        );
+    }
+
+    #[test]
+    fn update_peer_0_new_peer_wo_metadata_0_keeps_operator_comments() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg35.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+
+[Peer]
+# Name = alice-laptop
+# Owner = alice
+# Ticket = 123
+# Meta.Location = Berlin
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let peer = WgPeer::new(
+            "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.2/32".parse().unwrap()],
+            None,
+            None,
+            Some(25),
+        );
+
+        // Act
+        let wg_conf = wg_conf.update_peer(&peer).unwrap();
+
+        // Assert
+        wg_conf.close();
+        assert_eq!(
+            CONTENT
+                .replace("# Name = alice-laptop\n", "")
+                .replace("# Meta.Location = Berlin\n", "")
+                .replace(
+                    "AllowedIPs = 10.0.0.2/32\n",
+                    "AllowedIPs = 10.0.0.2/32\nPersistentKeepalive = 25\n"
+                ),
+            fs::read_to_string(TEST_CONF_FILE).unwrap()
+        );
+    }
+
+    #[test]
+    fn update_interface_0_extra_0_edits_and_removes_unknown_keys() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg28.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+Jc = 4
+H1 = 1234
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let mut interface = wg_conf.interface().unwrap();
+        interface
+            .set_extra(vec![
+                ("Jc".to_string(), "5".to_string()),
+                ("S1".to_string(), "15".to_string()),
+            ])
+            .unwrap();
+
+        // Act
+        let mut wg_conf = wg_conf.update_interface(interface.clone()).unwrap();
+
+        // Assert
+        assert_eq!(interface, wg_conf.interface().unwrap());
+        wg_conf.close();
+        assert_eq!(
+            CONTENT.replace("Jc = 4\nH1 = 1234\n", "Jc = 5\nS1 = 15\n"),
+            fs::read_to_string(TEST_CONF_FILE).unwrap()
+        );
+    }
+
+    #[test]
+    fn update_peer_0_unknown_keys_0_kept() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg29.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+FutureKey = future value
+AllowedIPs = 10.0.0.2/32
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let mut peer = wg_conf.peers().unwrap().next().unwrap();
+        peer.persistent_keepalive = Some(25);
+
+        // Act
+        let mut wg_conf = wg_conf.update_peer(&peer).unwrap();
+
+        // Assert
+        let updated_peer = wg_conf.peers().unwrap().next().unwrap();
+        assert_eq!(
+            &[("FutureKey".to_string(), "future value".to_string())],
+            updated_peer.extra()
+        );
+        wg_conf.close();
+        assert_eq!(
+            CONTENT.to_string() + "PersistentKeepalive = 25\n",
+            fs::read_to_string(TEST_CONF_FILE).unwrap()
+        );
+    }
+
     #[test]
     fn remove_peer_by_pub_key_0_unexistent() {
         // Arrange
@@ -1489,7 +2407,7 @@ DNS = 0.0.0.0
 
         let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
         let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
-        let target_key: WgKey = "Rrr2pT8pOvcEKdp1KptvUi8OO/fYIWnkVcnXJ3dtUE4="
+        let target_key: WgPublicKey = "Rrr2pT8pOvcEKdp1KptvUi8OO/fYIWnkVcnXJ3dtUE4="
             .parse()
             .unwrap();
 
@@ -1500,7 +2418,7 @@ DNS = 0.0.0.0
         assert_eq!(WgConfErrKind::NotFound, res.unwrap_err().kind());
     }
 
-    #[cfg(feature = "wg_engine")]
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
     #[test]
     fn generate_peer_0_common_scenario() {
         // Arrange
@@ -1512,10 +2430,10 @@ DNS = 0.0.0.0
 
         // Act
         let res = wg_conf.generate_peer(
-            "10.0.0.2".parse().unwrap(),
+            vec!["10.0.0.2".parse().unwrap()],
             "127.0.0.2".parse().unwrap(),
             vec!["0.0.0.0/0".parse().unwrap()],
-            Some("8.8.8.8".parse().unwrap()),
+            vec!["8.8.8.8".parse().unwrap()],
             true,
             Some(10),
         );
@@ -1541,13 +2459,19 @@ DNS = 0.0.0.0
 
         let client_interface = client_conf.interface();
         let regenerated_client_pub_key =
-            WgKey::generate_public_key(&client_interface.private_key).unwrap();
+            WgKey::generate_public_key(client_interface.private_key().unwrap()).unwrap();
         assert_eq!(*&regenerated_client_pub_key, *last_peer.public_key());
-        assert_eq!("10.0.0.2/32", client_interface.address().to_string());
-        assert_eq!("8.8.8.8", client_interface.dns.unwrap().to_string());
+        assert_eq!(
+            vec!["10.0.0.2/32".parse::<IpNetwork>().unwrap()],
+            client_interface.addresses()
+        );
+        assert_eq!(
+            vec![WgDns::Ip("8.8.8.8".parse().unwrap())],
+            client_interface.dns
+        );
         assert!(client_interface.listen_port().is_none());
-        assert!(client_interface.post_up.is_none());
-        assert!(client_interface.post_down.is_none());
+        assert!(client_interface.post_up.is_empty());
+        assert!(client_interface.post_down.is_empty());
 
         let client_peer_w_server = client_conf.peers().first().unwrap();
         assert_eq!(wg_conf.pub_key().unwrap(), client_peer_w_server.public_key);
@@ -1561,7 +2485,7 @@ DNS = 0.0.0.0
         );
         assert_eq!(
             "127.0.0.2:8080",
-            client_peer_w_server.endpoint.unwrap().to_string()
+            client_peer_w_server.endpoint().unwrap().to_string()
         );
         assert_eq!(
             last_peer.preshared_key(),
@@ -1570,7 +2494,130 @@ DNS = 0.0.0.0
         assert_eq!(10, client_peer_w_server.persistent_keepalive.unwrap());
     }
 
-    fn prepare_test_conf(conf_name: &'static str, content: &str) -> Deferred {
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+    #[test]
+    fn generate_peer_0_dual_stack_0_requires_address_per_family() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg23.conf";
+        const CONTENT: &str = "[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24, fd00::1/64
+ListenPort = 8080
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let invalid_client_addresses: Vec<Vec<IpAddr>> = vec![
+            vec!["10.0.0.2".parse().unwrap()],
+            vec!["10.0.0.2".parse().unwrap(), "10.0.0.3".parse().unwrap()],
+            vec!["10.0.1.2".parse().unwrap(), "fd00::2".parse().unwrap()],
+        ];
+
+        for client_addresses in invalid_client_addresses {
+            // Act
+            let res = wg_conf.generate_peer(
+                client_addresses,
+                "127.0.0.2".parse().unwrap(),
+                vec!["0.0.0.0/0".parse().unwrap()],
+                vec![],
+                false,
+                None,
+            );
+
+            // Assert
+            assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+        }
+        assert_eq!(0, wg_conf.peers().unwrap().count());
+    }
+
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+    #[test]
+    fn pub_key_0_private_key_file_0_resolves_key_and_keeps_ref() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg24.conf";
+        const KEY_FILE: &str = "wg24.key";
+        const CONTENT: &str = "[Interface]
+Address = 10.0.0.1/24
+ListenPort = 8080
+PostUp = wg set %i private-key wg24.key
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let _key_cleanup =
+            prepare_test_conf(KEY_FILE, "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=\n");
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let pub_key = wg_conf.pub_key();
+        let client_conf = wg_conf.generate_peer(
+            vec!["10.0.0.2".parse().unwrap()],
+            "127.0.0.1".parse().unwrap(),
+            vec!["10.0.0.0/24".parse().unwrap()],
+            vec![],
+            false,
+            None,
+        );
+
+        // Assert
+        let expected_pub_key = WgKey::generate_public_key(
+            &"4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c="
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(expected_pub_key, pub_key.unwrap());
+        assert_eq!(
+            &expected_pub_key,
+            client_conf.unwrap().peers()[0].public_key()
+        );
+        let content = fs::read_to_string(TEST_CONF_FILE).unwrap();
+        assert!(content.starts_with(CONTENT));
+        assert!(!content.contains("4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c="));
+    }
+
+    #[test]
+    fn private_key_0_provider_ref_0_resolved_by_provider() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg25.conf";
+        const CONTENT: &str = "[Interface]
+Address = 10.0.0.1/24
+ListenPort = 8080
+PostUp = wg set %i private-key <(pass show wg/server)
+";
+
+        #[derive(Debug)]
+        struct TestProvider;
+
+        impl WgSecretProvider for TestProvider {
+            fn private_key(
+                &self,
+                command: &str,
+                reference: &str,
+            ) -> Result<WgPrivateKey, WgConfError> {
+                assert_eq!(("pass show", "wg/server"), (command, reference));
+
+                "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=".parse()
+            }
+        }
+
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let without_provider = wg_conf.private_key();
+        wg_conf.set_secret_provider(Box::new(TestProvider));
+        let with_provider = wg_conf.private_key();
+
+        // Assert
+        assert_eq!(
+            WgConfErrKind::NotFound,
+            without_provider.unwrap_err().kind()
+        );
+        assert_eq!(
+            "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
+            with_provider.unwrap().expose_secret()
+        );
+    }
+
+    pub(crate) fn prepare_test_conf(conf_name: &'static str, content: &str) -> Deferred {
         {
             let mut file = fs::File::create(conf_name).unwrap();
             file.write_all(content.as_bytes()).unwrap();
@@ -0,0 +1,893 @@
+use std::fmt::Display;
+
+use crate::{wg_interface, wg_peer, REDACTED};
+
+const COMMENT_PREFIX: char = '#';
+const SECTION_START: char = '[';
+const SECTION_END: char = ']';
+const KEY_VALUE_SEPARATOR: char = '=';
+const LF: &'static str = "\n";
+const CRLF: &'static str = "\r\n";
+/// Byte order mark which may start the text
+pub(crate) const BOM: char = '\u{feff}';
+
+/// Lossless representation of WG config text (concrete syntax tree).
+///
+/// Every line, comment, blank line, original key spelling and line ending is kept,
+/// so `WgDocument::parse(text).to_string() == text` for any text
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WgDocument {
+    preamble: Vec<WgDocumentLine>,
+    sections: Vec<WgDocumentSection>,
+}
+
+/// Section of [`WgDocument`] which starts with the section tag line (e.g. \[Peer\])
+/// and contains all the lines up to the next section tag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgDocumentSection {
+    header: WgDocumentLine,
+    lines: Vec<WgDocumentLine>,
+}
+
+/// Single line of [`WgDocument`] with its line ending
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgDocumentLine {
+    raw: String,
+    eol: &'static str,
+    kind: WgDocumentLineKind,
+}
+
+/// Kind of [`WgDocumentLine`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WgDocumentLineKind {
+    /// Empty or whitespace-only line
+    Blank,
+    /// Line starting with '#'
+    Comment,
+    /// Section tag line, e.g. \[Interface\]
+    Section,
+    /// `key = value` line, spans are byte ranges in the raw line
+    KeyValue {
+        key: (usize, usize),
+        value: (usize, usize),
+    },
+    /// Any other line, it's kept as is
+    Unknown,
+}
+
+impl Display for WgDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for line in &self.preamble {
+            write!(f, "{line}")?;
+        }
+
+        for section in &self.sections {
+            write!(f, "{section}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for WgDocumentSection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.header)?;
+
+        for line in &self.lines {
+            write!(f, "{line}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for WgDocumentLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.raw, self.eol)
+    }
+}
+
+impl WgDocument {
+    /// Parses WG config text into [`WgDocument`]
+    ///
+    /// Parsing never fails as unknown lines are kept as [`WgDocumentLineKind::Unknown`],
+    /// the semantic validation is up to [`crate::WgInterface`] and [`crate::WgPeer`]
+    pub fn parse(text: &str) -> WgDocument {
+        let mut document = WgDocument::default();
+
+        for line in split_lines(text) {
+            document.push_line(line);
+        }
+
+        document
+    }
+
+    /// Same as `to_string()`, but values of PrivateKey and PresharedKey lines are replaced by [`REDACTED`],
+    /// so the text is safe to log
+    pub fn to_string_redacted(&self) -> String {
+        let section_lines = self
+            .sections
+            .iter()
+            .flat_map(|section| std::iter::once(&section.header).chain(&section.lines));
+
+        self.preamble
+            .iter()
+            .chain(section_lines)
+            .map(WgDocumentLine::to_string_redacted)
+            .collect()
+    }
+
+    /// Lines before the first section
+    pub fn preamble(&self) -> &[WgDocumentLine] {
+        &self.preamble
+    }
+
+    pub fn sections(&self) -> &[WgDocumentSection] {
+        &self.sections
+    }
+
+    pub fn sections_mut(&mut self) -> &mut [WgDocumentSection] {
+        &mut self.sections
+    }
+
+    /// Returns sections with the provided name (e.g. "Peer")
+    pub fn sections_by_name<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a WgDocumentSection> + 'a {
+        self.sections.iter().filter(move |s| s.name() == name)
+    }
+
+    /// Appends `text` to the end of the document, appended lines get the line ending of the document
+    pub(crate) fn append(&mut self, text: &str) {
+        let eol = self.eol();
+        if let Some(last_line) = self.last_line_mut() {
+            if last_line.eol.is_empty() {
+                last_line.eol = eol;
+            }
+        }
+
+        for mut line in split_lines(text) {
+            if !line.eol.is_empty() {
+                line.eol = eol;
+            }
+            self.push_line(line);
+        }
+    }
+
+    /// Removes the section with provided index with all its lines
+    pub(crate) fn remove_section(&mut self, index: usize) -> WgDocumentSection {
+        self.sections.remove(index)
+    }
+
+    fn push_line(&mut self, line: WgDocumentLine) {
+        if line.kind == WgDocumentLineKind::Section {
+            self.sections.push(WgDocumentSection {
+                header: line,
+                lines: vec![],
+            });
+
+            return;
+        }
+
+        match self.sections.last_mut() {
+            Some(section) => section.lines.push(line),
+            None => self.preamble.push(line),
+        }
+    }
+
+    fn last_line_mut(&mut self) -> Option<&mut WgDocumentLine> {
+        match self.sections.last_mut() {
+            Some(section) => match section.lines.last_mut() {
+                Some(line) => Some(line),
+                None => Some(&mut section.header),
+            },
+            None => self.preamble.last_mut(),
+        }
+    }
+
+    /// Line ending used in the document
+    fn eol(&self) -> &'static str {
+        match self.sections.first() {
+            Some(section) => section.eol(),
+            None => LF,
+        }
+    }
+}
+
+impl WgDocumentSection {
+    /// Section name without brackets, e.g. "Interface"
+    pub fn name(&self) -> &str {
+        section_tag_name(&self.header.raw).unwrap_or_default()
+    }
+
+    /// Checks if the section has provided tag (e.g. \[Peer\])
+    pub fn is(&self, tag: &str) -> bool {
+        Some(self.name()) == section_tag_name(tag)
+    }
+
+    pub fn header(&self) -> &WgDocumentLine {
+        &self.header
+    }
+
+    /// Lines after the section tag
+    pub fn lines(&self) -> &[WgDocumentLine] {
+        &self.lines
+    }
+
+    /// Returns ordered key-values of the section, comments and other lines are skipped
+    pub fn key_values(&self) -> Vec<(String, String)> {
+        self.lines
+            .iter()
+            .filter_map(|line| {
+                line.key_value()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+            })
+            .collect()
+    }
+
+    /// Returns the last value of the provided key (key is case-insensitive)
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| line.key_value())
+            .filter(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+            .next_back()
+    }
+
+    /// Sets value of the provided key (key is case-insensitive).
+    ///
+    /// The first line with the key is edited in place keeping original key spelling and spacing,
+    /// the other lines with this key are removed. If there is no such key, the new line is added
+    /// after the last key-value line of the section
+    pub fn set(&mut self, key: &str, value: &str) {
+        let mut line_indexes = self.key_line_indexes(key);
+
+        if line_indexes.is_empty() {
+            self.insert_after_entries(key, value);
+            return;
+        }
+
+        let first_line_index = line_indexes.remove(0);
+        self.lines[first_line_index].set_value(value);
+
+        for i in line_indexes.into_iter().rev() {
+            self.lines.remove(i);
+        }
+    }
+
+    /// Removes all the lines with the provided key (key is case-insensitive)
+    pub fn remove(&mut self, key: &str) {
+        self.lines.retain(|line| match line.key_value() {
+            Some((k, _)) => !k.eq_ignore_ascii_case(key),
+            None => true,
+        });
+    }
+
+    /// Changes key-values of the section to match `raw_key_values` with minimal edits.
+    ///
+    /// Only `managed_keys` are touched: unchanged lines are kept byte-identical, changed values
+    /// are edited in place, absent keys are removed and new keys are added after the last key-value line.
+    /// Values of `list_keys` are compared as comma-separated lists, so, e.g. the same addresses
+    /// split into several lines are kept as is
+    pub(crate) fn sync_key_values(
+        &mut self,
+        raw_key_values: &[(&str, String)],
+        managed_keys: &[&str],
+        list_keys: &[&str],
+    ) {
+        // new keys are added in the provided order, then absent keys are removed
+        let mut keys: Vec<&str> = Vec::with_capacity(managed_keys.len());
+        for key in raw_key_values
+            .iter()
+            .map(|(k, _)| *k)
+            .chain(managed_keys.iter().copied())
+        {
+            let is_managed = managed_keys.iter().any(|m| m.eq_ignore_ascii_case(key));
+            if is_managed && !keys.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                keys.push(key);
+            }
+        }
+
+        for managed_key in keys {
+            let new_values: Vec<&str> = raw_key_values
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case(managed_key))
+                .map(|(_, v)| v.as_str())
+                .collect();
+
+            let line_indexes = self.key_line_indexes(managed_key);
+            let cur_values: Vec<String> = line_indexes
+                .iter()
+                .map(|i| self.lines[*i].value().unwrap_or_default().to_owned())
+                .collect();
+
+            if list_keys
+                .iter()
+                .any(|k| k.eq_ignore_ascii_case(managed_key))
+            {
+                let cur_values: Vec<&str> = cur_values.iter().map(|v| v.as_str()).collect();
+                if split_list(&cur_values) != split_list(&new_values) {
+                    match new_values.is_empty() {
+                        true => self.remove(managed_key),
+                        false => self.set(managed_key, &new_values.join(", ")),
+                    }
+                }
+
+                continue;
+            }
+
+            // pair existing lines with new values in order
+            for (i, line_index) in line_indexes.iter().enumerate() {
+                if let Some(new_value) = new_values.get(i) {
+                    if cur_values[i] != *new_value {
+                        self.lines[*line_index].set_value(new_value);
+                    }
+                }
+            }
+
+            for line_index in line_indexes.iter().skip(new_values.len()).rev() {
+                self.lines.remove(*line_index);
+            }
+
+            for new_value in new_values.iter().skip(line_indexes.len()) {
+                self.insert_after_key(managed_key, new_value);
+            }
+        }
+    }
+
+    /// Same as [`WgDocumentSection::sync_key_values`] but all the key-values of the section are managed,
+    /// so unknown keys which are absent in `raw_key_values` are removed too
+    pub(crate) fn sync_all_key_values(
+        &mut self,
+        raw_key_values: &[(&str, String)],
+        list_keys: &[&str],
+    ) {
+        let section_keys: Vec<String> = self.key_values().into_iter().map(|(k, _)| k).collect();
+        let managed_keys: Vec<&str> = raw_key_values
+            .iter()
+            .map(|(k, _)| *k)
+            .chain(section_keys.iter().map(|k| k.as_str()))
+            .collect();
+
+        self.sync_key_values(raw_key_values, &managed_keys, list_keys);
+    }
+
+    /// Changes `# Key = value` comments of the section to match `raw_key_values` with minimal edits.
+    ///
+    /// Only comments which keys satisfy `is_managed` are touched: changed values are edited in place,
+    /// absent keys are removed and new keys are added after the last such comment
+    /// (or right after the section tag). Other comments are kept as is
+    pub(crate) fn sync_comment_key_values(
+        &mut self,
+        raw_key_values: &[(String, String)],
+        is_managed: fn(&str) -> bool,
+    ) {
+        let mut synced_keys: Vec<&str> = Vec::with_capacity(raw_key_values.len());
+
+        let mut i = 0;
+        while i < self.lines.len() {
+            let key = match self.lines[i].comment_key_value() {
+                Some((key, _)) if is_managed(key) => key.to_owned(),
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            let already_synced = synced_keys.iter().any(|k| k.eq_ignore_ascii_case(&key));
+            let new_value = raw_key_values
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(&key))
+                .filter(|_| !already_synced);
+
+            match new_value {
+                Some((k, new_value)) => {
+                    if self.lines[i].comment_key_value().map(|(_, v)| v) != Some(new_value) {
+                        self.lines[i].set_comment_value(new_value);
+                    }
+                    synced_keys.push(k);
+                    i += 1;
+                }
+                None => {
+                    self.lines.remove(i);
+                }
+            }
+        }
+
+        let mut insert_pos = self
+            .lines
+            .iter()
+            .rposition(|line| line.comment_key_value().is_some_and(|(k, _)| is_managed(k)))
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        for (key, value) in raw_key_values {
+            if synced_keys.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                continue;
+            }
+
+            let eol = self.eol();
+            let prev_line = match insert_pos {
+                0 => &mut self.header,
+                _ => &mut self.lines[insert_pos - 1],
+            };
+            if prev_line.eol.is_empty() {
+                prev_line.eol = eol;
+            }
+
+            let line = WgDocumentLine::new_comment_key_value(key, value, eol);
+            self.lines.insert(insert_pos, line);
+            insert_pos += 1;
+            synced_keys.push(key);
+        }
+    }
+
+    fn key_line_indexes(&self, key: &str) -> Vec<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| match line.key_value() {
+                Some((k, _)) => k.eq_ignore_ascii_case(key),
+                None => false,
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Inserts new line after the last line with the same key or after the last key-value line
+    fn insert_after_key(&mut self, key: &str, value: &str) {
+        match self.key_line_indexes(key).last() {
+            Some(i) => {
+                let line = WgDocumentLine::new_key_value(key, value, self.eol());
+                self.lines.insert(i + 1, line);
+            }
+            None => self.insert_after_entries(key, value),
+        }
+    }
+
+    fn insert_after_entries(&mut self, key: &str, value: &str) {
+        let insert_pos = self
+            .lines
+            .iter()
+            .rposition(|line| line.key_value().is_some())
+            .map(|i| i + 1)
+            .unwrap_or(0);
+
+        // previous line may be the last line of the file without line ending
+        let eol = self.eol();
+        let prev_line = match insert_pos {
+            0 => &mut self.header,
+            _ => &mut self.lines[insert_pos - 1],
+        };
+        if prev_line.eol.is_empty() {
+            prev_line.eol = eol;
+        }
+
+        let line = WgDocumentLine::new_key_value(key, value, eol);
+        self.lines.insert(insert_pos, line);
+    }
+
+    /// Line ending used in the section
+    fn eol(&self) -> &'static str {
+        match self.header.eol {
+            CRLF => CRLF,
+            _ => LF,
+        }
+    }
+}
+
+impl WgDocumentLine {
+    fn parse(raw: &str, eol: &'static str) -> WgDocumentLine {
+        WgDocumentLine {
+            raw: raw.to_owned(),
+            eol,
+            kind: line_kind(raw),
+        }
+    }
+
+    fn new_key_value(key: &str, value: &str, eol: &'static str) -> WgDocumentLine {
+        WgDocumentLine::parse(&format!("{key} = {value}"), eol)
+    }
+
+    fn new_comment_key_value(key: &str, value: &str, eol: &'static str) -> WgDocumentLine {
+        WgDocumentLine::parse(&format!("{COMMENT_PREFIX} {key} = {value}"), eol)
+    }
+
+    /// Raw line without line ending
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// Line ending ("\n", "\r\n" or "" for the last line without line ending)
+    pub fn eol(&self) -> &str {
+        self.eol
+    }
+
+    pub fn kind(&self) -> &WgDocumentLineKind {
+        &self.kind
+    }
+
+    /// Returns trimmed key and value if the line is key-value line
+    pub fn key_value(&self) -> Option<(&str, &str)> {
+        match self.kind {
+            WgDocumentLineKind::KeyValue { key, value } => {
+                Some((&self.raw[key.0..key.1], &self.raw[value.0..value.1]))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key_value().map(|(k, _)| k)
+    }
+
+    pub fn value(&self) -> Option<&str> {
+        self.key_value().map(|(_, v)| v)
+    }
+
+    /// Returns trimmed key and value if the line is a `# Key = value` comment
+    ///
+    /// Such comments are used by wg-quick frontends to store peer names and other metadata
+    /// without breaking the config for wg-quick
+    pub fn comment_key_value(&self) -> Option<(&str, &str)> {
+        match self.kind {
+            WgDocumentLineKind::Comment => comment_key_value(&self.raw),
+            _ => None,
+        }
+    }
+
+    /// Replaces value of `# Key = value` comment keeping the rest of the line as is
+    fn set_comment_value(&mut self, new_value: &str) {
+        if let Some((_, value)) = comment_key_value_spans(&self.raw) {
+            let separator = match value.0 == self.raw.len() {
+                true => " ",
+                false => "",
+            };
+
+            self.raw = format!(
+                "{}{}{}{}",
+                &self.raw[..value.0],
+                separator,
+                new_value,
+                &self.raw[value.1..]
+            );
+        }
+    }
+
+    /// Returns the line with its line ending, the value of secret key line is replaced by [`REDACTED`]
+    fn to_string_redacted(&self) -> String {
+        match self.kind {
+            WgDocumentLineKind::KeyValue { key, value }
+                if value.0 < value.1 && is_secret_key(&self.raw[key.0..key.1]) =>
+            {
+                format!(
+                    "{}{}{}{}",
+                    &self.raw[..value.0],
+                    REDACTED,
+                    &self.raw[value.1..],
+                    self.eol
+                )
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// Replaces value of key-value line keeping the rest of the line as is
+    fn set_value(&mut self, new_value: &str) {
+        if let WgDocumentLineKind::KeyValue { value, .. } = self.kind {
+            let separator = match value.0 == self.raw.len() {
+                true => " ",
+                false => "",
+            };
+
+            self.raw = format!(
+                "{}{}{}{}",
+                &self.raw[..value.0],
+                separator,
+                new_value,
+                &self.raw[value.1..]
+            );
+            self.kind = line_kind(&self.raw);
+        }
+    }
+}
+
+/// Checks if the value of the key is a secret one (key is case-insensitive)
+fn is_secret_key(key: &str) -> bool {
+    key.eq_ignore_ascii_case(wg_interface::PRIVATE_KEY)
+        || key.eq_ignore_ascii_case(wg_peer::PRESHARED_KEY)
+}
+
+/// Splits text into lines keeping line endings
+fn split_lines(text: &str) -> Vec<WgDocumentLine> {
+    let mut lines = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let line = match rest.find('\n') {
+            Some(i) if rest[..i].ends_with('\r') => WgDocumentLine::parse(&rest[..i - 1], CRLF),
+            Some(i) => WgDocumentLine::parse(&rest[..i], LF),
+            None => WgDocumentLine::parse(rest, ""),
+        };
+
+        rest = &rest[line.raw.len() + line.eol.len()..];
+        lines.push(line);
+    }
+
+    lines
+}
+
+pub(crate) fn line_kind(raw: &str) -> WgDocumentLineKind {
+    let start = match raw.starts_with(BOM) {
+        true => BOM.len_utf8(),
+        false => 0,
+    };
+    let trimmed = raw[start..].trim();
+
+    if trimmed.is_empty() {
+        return WgDocumentLineKind::Blank;
+    }
+
+    if trimmed.starts_with(COMMENT_PREFIX) {
+        return WgDocumentLineKind::Comment;
+    }
+
+    if trimmed.starts_with(SECTION_START) && trimmed.ends_with(SECTION_END) {
+        return WgDocumentLineKind::Section;
+    }
+
+    match raw.find(KEY_VALUE_SEPARATOR) {
+        Some(separator_pos) => WgDocumentLineKind::KeyValue {
+            key: trimmed_span(raw, start, separator_pos),
+            value: trimmed_span(raw, separator_pos + 1, raw.len()),
+        },
+        None => WgDocumentLineKind::Unknown,
+    }
+}
+
+/// Returns trimmed key and value of `# Key = value` comment line.
+///
+/// Key must be a single word (letters, digits, '_', '-' or '.', e.g. `Meta.Owner`),
+/// so usual comments like `# see https://example.com/?a=b` are not taken as key-values
+pub(crate) fn comment_key_value(raw: &str) -> Option<(&str, &str)> {
+    comment_key_value_spans(raw).map(|(key, value)| (&raw[key.0..key.1], &raw[value.0..value.1]))
+}
+
+fn comment_key_value_spans(raw: &str) -> Option<((usize, usize), (usize, usize))> {
+    let comment_start = raw.find(COMMENT_PREFIX)?;
+    if !raw[..comment_start].trim().is_empty() {
+        return None;
+    }
+
+    let separator_pos = raw.find(KEY_VALUE_SEPARATOR)?;
+    if separator_pos < comment_start {
+        return None;
+    }
+
+    let key = trimmed_span(raw, comment_start + 1, separator_pos);
+    let key_str = &raw[key.0..key.1];
+    let is_word = key_str
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c));
+    if key_str.is_empty() || !is_word {
+        return None;
+    }
+
+    Some((key, trimmed_span(raw, separator_pos + 1, raw.len())))
+}
+
+/// Returns section name without brackets and whitespaces if the line is section tag,
+/// e.g. "Peer" for " \[ Peer \] "
+pub(crate) fn section_tag_name(raw: &str) -> Option<&str> {
+    raw.trim_start_matches(BOM)
+        .trim()
+        .strip_prefix(SECTION_START)?
+        .strip_suffix(SECTION_END)
+        .map(|name| name.trim())
+}
+
+/// Returns span of `raw[start..end]` without leading and trailing whitespaces
+pub(crate) fn trimmed_span(raw: &str, start: usize, end: usize) -> (usize, usize) {
+    let part = &raw[start..end];
+    let trimmed_start = start + (part.len() - part.trim_start().len());
+    let trimmed_end = end - (part.len() - part.trim_end().len());
+
+    match trimmed_start > trimmed_end {
+        true => (end, end),
+        false => (trimmed_start, trimmed_end),
+    }
+}
+
+fn split_list<'a>(values: &[&'a str]) -> Vec<&'a str> {
+    values
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY_CONTENT: &'static str = "# managed by ops\r
+[Interface]\r
+PrivateKey=4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=\r
+  Address = 10.0.0.1/24   \r
+# second address\r
+Address = fd00::1/64\r
+UnknownKey = keep me\r
+\r
+\r
+ [ Peer ] \r
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=\r
+not a key value line\r
+AllowedIPs = 10.0.0.2/32";
+
+    #[test]
+    fn parse_0_to_string_0_byte_identical() {
+        let contents = [MESSY_CONTENT, "", "\n\n", "[Interface]\nKey =\n", "text"];
+
+        for content in contents {
+            // Act
+            let document = WgDocument::parse(content);
+
+            // Assert
+            assert_eq!(content, document.to_string());
+        }
+    }
+
+    #[test]
+    fn to_string_redacted_0_secrets_replaced_keeping_formatting() {
+        // Arrange
+        let content = MESSY_CONTENT.replace(
+            "not a key value line",
+            " presharedkey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4= ",
+        );
+        let document = WgDocument::parse(&content);
+
+        // Act
+        let redacted = document.to_string_redacted();
+
+        // Assert
+        assert_eq!(
+            content
+                .replace("4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=", REDACTED)
+                .replace("Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=", REDACTED),
+            redacted
+        );
+        assert_eq!(content, document.to_string());
+    }
+
+    #[test]
+    fn parse_0_sections_and_lines() {
+        // Act
+        let document = WgDocument::parse(MESSY_CONTENT);
+
+        // Assert
+        assert_eq!(1, document.preamble().len());
+        assert_eq!(2, document.sections().len());
+        assert_eq!("Interface", document.sections()[0].name());
+        assert_eq!("Peer", document.sections()[1].name());
+        assert_eq!(
+            Some("10.0.0.1/24"),
+            document.sections()[0].lines()[1].value()
+        );
+        assert_eq!(
+            WgDocumentLineKind::Unknown,
+            *document.sections()[1].lines()[1].kind()
+        );
+        assert_eq!("", document.sections()[1].lines()[2].eol());
+    }
+
+    #[test]
+    fn sync_key_values_0_changes_only_edited_line() {
+        // Arrange
+        let mut document = WgDocument::parse(MESSY_CONTENT);
+        let interface = &mut document.sections_mut()[0];
+        let raw_key_values = vec![
+            (
+                "PrivateKey",
+                "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            ),
+            ("Address", "10.0.0.1/24, fd00::1/64".to_string()),
+            ("ListenPort", "8080".to_string()),
+        ];
+
+        // Act
+        interface.sync_key_values(
+            &raw_key_values,
+            &["PrivateKey", "Address", "ListenPort", "DNS"],
+            &["Address", "DNS"],
+        );
+
+        // Assert
+        assert_eq!(
+            MESSY_CONTENT
+                .replace(
+                    "PrivateKey=4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
+                    "PrivateKey=6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                )
+                .replace(
+                    "UnknownKey = keep me\r\n",
+                    "UnknownKey = keep me\r\nListenPort = 8080\r\n"
+                ),
+            document.to_string()
+        );
+    }
+
+    #[test]
+    fn sync_key_values_0_removes_absent_and_merges_changed_list() {
+        // Arrange
+        let mut document = WgDocument::parse(MESSY_CONTENT);
+        let interface = &mut document.sections_mut()[0];
+        let raw_key_values = vec![("Address", "10.0.0.1/24".to_string())];
+
+        // Act
+        interface.sync_key_values(&raw_key_values, &["PrivateKey", "Address"], &["Address"]);
+
+        // Assert
+        assert_eq!(
+            "[Interface]\r
+  Address = 10.0.0.1/24   \r
+# second address\r
+UnknownKey = keep me\r
+\r
+\r
+",
+            document.sections()[0].to_string()
+        );
+    }
+
+    #[test]
+    fn set_0_last_line_without_eol_0_adds_eol() {
+        // Arrange
+        let mut document = WgDocument::parse("[Peer]\nPublicKey = key");
+
+        // Act
+        document.sections_mut()[0].set("Endpoint", "127.0.0.1:8080");
+
+        // Assert
+        assert_eq!(
+            "[Peer]\nPublicKey = key\nEndpoint = 127.0.0.1:8080\n",
+            document.to_string()
+        );
+    }
+
+    #[test]
+    fn sync_comment_key_values_0_edits_managed_comments_only() {
+        // Arrange
+        let mut document = WgDocument::parse(
+            "[Peer]
+#Name=laptop
+# see https://example.com/?a=b
+# Owner = support
+# PersistentKeepalive = 25
+PublicKey = key
+",
+        );
+        let raw_key_values = vec![
+            ("Name".to_string(), "office laptop".to_string()),
+            ("Location".to_string(), "Berlin".to_string()),
+        ];
+
+        // Act
+        document.sections_mut()[0]
+            .sync_comment_key_values(&raw_key_values, |key| key != "PersistentKeepalive");
+
+        // Assert
+        assert_eq!(
+            "[Peer]
+#Name=office laptop
+# Location = Berlin
+# see https://example.com/?a=b
+# PersistentKeepalive = 25
+PublicKey = key
+",
+            document.to_string()
+        );
+    }
+}
@@ -0,0 +1,527 @@
+use std::{fmt::Debug, fs};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand_core::{OsRng, RngCore};
+use zeroize::Zeroizing;
+
+use crate::{
+    fileworks, WgClientConf, WgConf, WgConfError, WgKey, WgMemConf, WgPresharedKey, WgPrivateKey,
+    REDACTED,
+};
+
+/// Magic bytes which every envelope starts with
+const MAGIC: &[u8; 4] = b"WGCE";
+const VERSION: u8 = 1;
+/// Argon2id (version 0x13) KDF id
+const KDF_ARGON2ID: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+/// magic, version, KDF id, 3 KDF params, salt and nonce
+const HEADER_LEN: usize = MAGIC.len() + 2 + 3 * 4 + SALT_LEN + NONCE_LEN;
+/// Max memory cost which is accepted from the envelope (256 MiB, 4 times the default one),
+/// so a crafted envelope can't exhaust memory
+const MAX_M_COST_KIB: u32 = 256 * 1024;
+/// Max iterations count which is accepted from the envelope (4 times the default one)
+const MAX_T_COST: u32 = 12;
+/// Max parallelism which is accepted from the envelope
+const MAX_P_COST: u32 = 4;
+
+/// Argon2id parameters which the encryption key is derived from the passphrase with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WgKdfParams {
+    m_cost_kib: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+/// Passphrase which WG configs and keys are encrypted with
+///
+/// The data is sealed into the envelope (all numbers are big-endian):
+///
+/// | Offset | Size | Field                                                      |
+/// |--------|------|------------------------------------------------------------|
+/// | 0      | 4    | magic `WGCE`                                               |
+/// | 4      | 1    | format version, `1`                                        |
+/// | 5      | 1    | KDF id, `1` is Argon2id v0x13                              |
+/// | 6      | 4    | KDF memory cost in KiB                                     |
+/// | 10     | 4    | KDF iterations count                                       |
+/// | 14     | 4    | KDF parallelism                                            |
+/// | 18     | 16   | random KDF salt                                            |
+/// | 34     | 24   | random XChaCha20-Poly1305 nonce                            |
+/// | 58     | ..   | XChaCha20-Poly1305 ciphertext with 16 bytes tag            |
+///
+/// 32 bytes encryption key is derived with Argon2id from the passphrase and the salt,
+/// the first 58 bytes (header) are authenticated as associated data.
+/// Keys are encrypted into base64 text of the envelope, configs are encrypted into the envelope bytes
+pub struct WgPassphrase {
+    passphrase: Zeroizing<String>,
+    kdf_params: WgKdfParams,
+}
+
+impl Default for WgKdfParams {
+    /// 64 MiB of memory, 3 iterations, 1 lane
+    fn default() -> Self {
+        WgKdfParams {
+            m_cost_kib: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+impl WgKdfParams {
+    /// Creates new [`WgKdfParams`], `m_cost_kib` is memory cost in KiB,
+    /// `t_cost` is iterations count and `p_cost` is parallelism
+    pub fn new(m_cost_kib: u32, t_cost: u32, p_cost: u32) -> Result<WgKdfParams, WgConfError> {
+        let kdf_params = WgKdfParams {
+            m_cost_kib,
+            t_cost,
+            p_cost,
+        };
+        if m_cost_kib > MAX_M_COST_KIB || t_cost > MAX_T_COST || p_cost > MAX_P_COST {
+            return Err(WgConfError::ValidationFailed(format!(
+                "KDF params {kdf_params:?} exceed the max ones"
+            )));
+        }
+        kdf_params.argon2_params()?;
+
+        Ok(kdf_params)
+    }
+
+    // getters
+    pub fn m_cost_kib(&self) -> u32 {
+        self.m_cost_kib
+    }
+    pub fn t_cost(&self) -> u32 {
+        self.t_cost
+    }
+    pub fn p_cost(&self) -> u32 {
+        self.p_cost
+    }
+
+    fn argon2_params(&self) -> Result<Params, WgConfError> {
+        Params::new(self.m_cost_kib, self.t_cost, self.p_cost, Some(KEY_LEN))
+            .map_err(|err| WgConfError::ValidationFailed(format!("invalid KDF params: {err}")))
+    }
+}
+
+impl Debug for WgPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WgPassphrase")
+            .field("passphrase", &REDACTED)
+            .field("kdf_params", &self.kdf_params)
+            .finish()
+    }
+}
+
+impl WgPassphrase {
+    /// Creates new [`WgPassphrase`] with default KDF params, the passphrase can't be empty
+    pub fn new(passphrase: &str) -> Result<WgPassphrase, WgConfError> {
+        if passphrase.is_empty() {
+            return Err(WgConfError::ValidationFailed(
+                "passphrase can't be empty".to_string(),
+            ));
+        }
+
+        Ok(WgPassphrase {
+            passphrase: Zeroizing::new(passphrase.to_owned()),
+            kdf_params: WgKdfParams::default(),
+        })
+    }
+
+    /// Sets KDF params which are used for encryption, decryption always uses the params of the envelope
+    pub fn set_kdf_params(&mut self, kdf_params: WgKdfParams) {
+        self.kdf_params = kdf_params;
+    }
+
+    pub fn kdf_params(&self) -> &WgKdfParams {
+        &self.kdf_params
+    }
+
+    /// Seals `plaintext` into the envelope with random salt and nonce
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, WgConfError> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        for random in [&mut salt[..], &mut nonce[..]] {
+            OsRng.try_fill_bytes(random).map_err(|err| {
+                WgConfError::Unexpected(format!("Couldn't generate random bytes: {err}"))
+            })?;
+        }
+
+        let mut envelope = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+        envelope.extend_from_slice(MAGIC);
+        envelope.push(VERSION);
+        envelope.push(KDF_ARGON2ID);
+        for kdf_param in [
+            self.kdf_params.m_cost_kib,
+            self.kdf_params.t_cost,
+            self.kdf_params.p_cost,
+        ] {
+            envelope.extend_from_slice(&kdf_param.to_be_bytes());
+        }
+        envelope.extend_from_slice(&salt);
+        envelope.extend_from_slice(&nonce);
+
+        let cipher = self.cipher(&self.kdf_params, &salt)?;
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &envelope,
+                },
+            )
+            .map_err(|_| WgConfError::Unexpected("Couldn't encrypt data".to_string()))?;
+        envelope.extend_from_slice(&ciphertext);
+
+        Ok(envelope)
+    }
+
+    /// Opens the envelope which is sealed with [`WgPassphrase::encrypt`], the plaintext is zeroed on drop
+    ///
+    /// Returns [`WgConfError::DecryptionFailed`] if the data is not the envelope, the passphrase is wrong
+    /// or the envelope is corrupted
+    pub fn decrypt(&self, envelope: &[u8]) -> Result<Zeroizing<Vec<u8>>, WgConfError> {
+        if envelope.len() < HEADER_LEN + TAG_LEN || !envelope.starts_with(MAGIC) {
+            return Err(WgConfError::DecryptionFailed(
+                "data is not WG encrypted envelope".to_string(),
+            ));
+        }
+
+        let (header, ciphertext) = envelope.split_at(HEADER_LEN);
+        let (version, kdf_id) = (header[4], header[5]);
+        if version != VERSION || kdf_id != KDF_ARGON2ID {
+            return Err(WgConfError::DecryptionFailed(format!(
+                "unsupported envelope version {version} or KDF {kdf_id}"
+            )));
+        }
+
+        let kdf_param = |offset: usize| {
+            u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap()) // 4 bytes slice
+        };
+        let kdf_params = WgKdfParams::new(kdf_param(6), kdf_param(10), kdf_param(14))
+            .map_err(|err| WgConfError::DecryptionFailed(err.to_string()))?;
+        let salt = &header[18..18 + SALT_LEN];
+        let nonce = &header[18 + SALT_LEN..];
+
+        let cipher = self.cipher(&kdf_params, salt)?;
+        cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| {
+                WgConfError::DecryptionFailed("wrong passphrase or corrupted data".to_string())
+            })
+    }
+
+    fn cipher(
+        &self,
+        kdf_params: &WgKdfParams,
+        salt: &[u8],
+    ) -> Result<XChaCha20Poly1305, WgConfError> {
+        let argon2 = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            kdf_params.argon2_params()?,
+        );
+
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        argon2
+            .hash_password_into(self.passphrase.as_bytes(), salt, key.as_mut_slice())
+            .map_err(|err| WgConfError::Unexpected(format!("Couldn't derive key: {err}")))?;
+
+        Ok(XChaCha20Poly1305::new(Key::from_slice(key.as_slice())))
+    }
+
+    /// Decrypts the envelope into the text
+    fn decrypt_text(&self, envelope: &[u8]) -> Result<Zeroizing<String>, WgConfError> {
+        let plaintext = self.decrypt(envelope)?;
+
+        std::str::from_utf8(&plaintext)
+            .map(|text| Zeroizing::new(text.to_owned()))
+            .map_err(|_| WgConfError::DecryptionFailed("decrypted data is not text".to_string()))
+    }
+}
+
+/// Implements encryption of the key into base64 text of the envelope
+macro_rules! impl_key_encryption {
+    ($($t:ident),*) => {
+        $(
+            impl $t {
+                /// Encrypts the key with the passphrase into base64 text of the envelope, see [`WgPassphrase`]
+                pub fn encrypt(&self, passphrase: &WgPassphrase) -> Result<String, WgConfError> {
+                    Ok(base64::prelude::BASE64_STANDARD.encode(passphrase.encrypt(self.expose_secret().as_bytes())?))
+                }
+
+                /// Decrypts the key from base64 text of the envelope, see [`WgPassphrase::decrypt`]
+                pub fn decrypt(encrypted: &str, passphrase: &WgPassphrase) -> Result<$t, WgConfError> {
+                    let envelope = base64::prelude::BASE64_STANDARD
+                        .decode(encrypted.trim())
+                        .map_err(|_| WgConfError::DecryptionFailed("data is not base64".to_string()))?;
+
+                    passphrase.decrypt_text(&envelope)?.parse()
+                }
+            }
+        )*
+    };
+}
+
+impl_key_encryption!(WgKey, WgPrivateKey, WgPresharedKey);
+
+impl WgClientConf {
+    /// Encrypts the config with the passphrase into the envelope, see [`WgPassphrase`]
+    pub fn encrypt(&self, passphrase: &WgPassphrase) -> Result<Vec<u8>, WgConfError> {
+        passphrase.encrypt(Zeroizing::new(self.to_string()).as_bytes())
+    }
+
+    /// Decrypts and parses the config from the envelope, see [`WgClientConf::parse`]
+    pub fn decrypt(
+        envelope: &[u8],
+        passphrase: &WgPassphrase,
+    ) -> Result<WgClientConf, WgConfError> {
+        WgClientConf::parse(&passphrase.decrypt_text(envelope)?)
+    }
+
+    /// Encrypts the config and saves the envelope into the file which is readable and writable
+    /// by the owner only, see [`WgClientConf::save`]
+    pub fn save_encrypted(
+        &self,
+        file_name: &str,
+        passphrase: &WgPassphrase,
+    ) -> Result<(), WgConfError> {
+        fileworks::write_owner_only(file_name, &self.encrypt(passphrase)?)
+    }
+
+    /// Reads the encrypted file and decrypts the config in memory only
+    pub fn from_encrypted_file(
+        file_name: &str,
+        passphrase: &WgPassphrase,
+    ) -> Result<WgClientConf, WgConfError> {
+        WgClientConf::decrypt(&read_envelope(file_name)?, passphrase)
+    }
+}
+
+impl WgMemConf {
+    /// Encrypts the config with the passphrase into the envelope, see [`WgPassphrase`]
+    pub fn encrypt(&self, passphrase: &WgPassphrase) -> Result<Vec<u8>, WgConfError> {
+        passphrase.encrypt(Zeroizing::new(self.to_string()).as_bytes())
+    }
+
+    /// Decrypts and parses the config from the envelope, see [`WgMemConf::parse`]
+    pub fn decrypt(envelope: &[u8], passphrase: &WgPassphrase) -> Result<WgMemConf, WgConfError> {
+        WgMemConf::parse(&passphrase.decrypt_text(envelope)?)
+    }
+
+    /// Encrypts the config and saves the envelope into the file which is readable and writable by the owner only
+    pub fn save_encrypted(
+        &self,
+        file_name: &str,
+        passphrase: &WgPassphrase,
+    ) -> Result<(), WgConfError> {
+        fileworks::write_owner_only(file_name, &self.encrypt(passphrase)?)
+    }
+
+    /// Reads the encrypted file and decrypts the config in memory only,
+    /// e.g. server config which is saved with [`WgConf::save_encrypted`]
+    pub fn from_encrypted_file(
+        file_name: &str,
+        passphrase: &WgPassphrase,
+    ) -> Result<WgMemConf, WgConfError> {
+        WgMemConf::decrypt(&read_envelope(file_name)?, passphrase)
+    }
+}
+
+impl WgConf {
+    /// Encrypts the whole config file with the passphrase into the envelope, see [`WgPassphrase`]
+    pub fn encrypt(&mut self, passphrase: &WgPassphrase) -> Result<Vec<u8>, WgConfError> {
+        passphrase.encrypt(self.read_text()?.as_bytes())
+    }
+
+    /// Encrypts the whole config file and saves the envelope into another file which is readable
+    /// and writable by the owner only. The encrypted config is opened in memory with [`WgMemConf::from_encrypted_file`]
+    pub fn save_encrypted(
+        &mut self,
+        file_name: &str,
+        passphrase: &WgPassphrase,
+    ) -> Result<(), WgConfError> {
+        fileworks::write_owner_only(file_name, &self.encrypt(passphrase)?)
+    }
+}
+
+fn read_envelope(file_name: &str) -> Result<Vec<u8>, WgConfError> {
+    fs::read(file_name).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => WgConfError::NotFound(file_name.to_string()),
+        _ => WgConfError::Unexpected(format!("Couldn't read {file_name}: {err}")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        wg_conf::tests::{prepare_test_conf, Deferred},
+        WgConfErrKind,
+    };
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/32
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 0.0.0.0/0
+Endpoint = 127.0.0.2:8080
+";
+
+    /// Passphrase with cheap KDF params to keep tests fast
+    fn passphrase(passphrase: &str) -> WgPassphrase {
+        let mut passphrase = WgPassphrase::new(passphrase).unwrap();
+        passphrase.set_kdf_params(WgKdfParams::new(64, 1, 1).unwrap());
+
+        passphrase
+    }
+
+    #[test]
+    fn decrypt_0_encrypted_0_same_plaintext_and_header() {
+        // Arrange
+        let passphrase = passphrase("correct horse");
+
+        // Act
+        let envelope = passphrase.encrypt(b"secret").unwrap();
+        let decrypted = passphrase.decrypt(&envelope);
+
+        // Assert
+        assert_eq!(b"secret".to_vec(), *decrypted.unwrap());
+        assert_eq!(HEADER_LEN + 6 + TAG_LEN, envelope.len());
+        assert_eq!(
+            b"WGCE\x01\x01\0\0\0\x40\0\0\0\x01\0\0\0\x01",
+            &envelope[..18]
+        );
+        assert_ne!(envelope, passphrase.encrypt(b"secret").unwrap());
+    }
+
+    #[test]
+    fn decrypt_0_wrong_passphrase_or_corrupted_0_returns_decryption_err() {
+        // Arrange
+        let envelope = passphrase("correct horse").encrypt(b"secret").unwrap();
+        let mut corrupted_header = envelope.clone();
+        corrupted_header[20] ^= 1;
+        let mut corrupted_ciphertext = envelope.clone();
+        *corrupted_ciphertext.last_mut().unwrap() ^= 1;
+        let mut huge_kdf_params = envelope.clone();
+        huge_kdf_params[6] = 0xFF;
+
+        let invalid_cases = [
+            (passphrase("wrong horse"), envelope),
+            (passphrase("correct horse"), corrupted_header),
+            (passphrase("correct horse"), corrupted_ciphertext),
+            (passphrase("correct horse"), huge_kdf_params),
+            (passphrase("correct horse"), b"WGCE".to_vec()),
+            (
+                passphrase("correct horse"),
+                CLIENT_CONTENT.as_bytes().to_vec(),
+            ),
+        ];
+
+        for (passphrase, envelope) in invalid_cases {
+            // Act
+            let res = passphrase.decrypt(&envelope);
+
+            // Assert
+            assert_eq!(WgConfErrKind::DecryptionFailed, res.unwrap_err().kind());
+        }
+    }
+
+    #[test]
+    fn kdf_params_new_0_above_max_0_returns_validation_err() {
+        let invalid_params = [
+            (MAX_M_COST_KIB + 1, 3, 1),
+            (64 * 1024, MAX_T_COST + 1, 1),
+            (64 * 1024, 3, MAX_P_COST + 1),
+        ];
+
+        for (m_cost_kib, t_cost, p_cost) in invalid_params {
+            // Act
+            let res = WgKdfParams::new(m_cost_kib, t_cost, p_cost);
+
+            // Assert
+            assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+        }
+        assert!(WgKdfParams::new(MAX_M_COST_KIB, MAX_T_COST, MAX_P_COST).is_ok());
+    }
+
+    #[test]
+    fn decrypt_0_key_0_same_key() {
+        // Arrange
+        let passphrase = passphrase("correct horse");
+        let private_key: WgPrivateKey = "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+            .parse()
+            .unwrap();
+
+        // Act
+        let encrypted = private_key.encrypt(&passphrase).unwrap();
+        let decrypted = WgPrivateKey::decrypt(&encrypted, &passphrase);
+
+        // Assert
+        assert_eq!(private_key, decrypted.unwrap());
+        assert!(!encrypted.contains(private_key.expose_secret()));
+    }
+
+    #[test]
+    fn from_encrypted_file_0_saved_client_conf_0_same_conf() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_encrypted0.conf.enc";
+        let passphrase = passphrase("correct horse");
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+        let _cleanup = Deferred(Box::new(|| {
+            let _ = fs::remove_file(TEST_CONF_FILE);
+        }));
+
+        // Act
+        let save_res = client_conf.save_encrypted(TEST_CONF_FILE, &passphrase);
+        let raw = fs::read(TEST_CONF_FILE).unwrap();
+        let read_res = WgClientConf::from_encrypted_file(TEST_CONF_FILE, &passphrase);
+
+        // Assert
+        assert!(save_res.is_ok());
+        assert!(raw.starts_with(MAGIC));
+        assert_eq!(client_conf, read_res.unwrap());
+    }
+
+    #[test]
+    fn from_encrypted_file_0_saved_wg_conf_0_same_text() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_encrypted1.conf";
+        const ENCRYPTED_FILE: &str = "wg_encrypted1.conf.enc";
+        let content = CLIENT_CONTENT.replace(
+            "Address = 10.0.0.2/32",
+            "# server\nAddress = 10.0.0.1/24\nListenPort = 8080",
+        );
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let _encrypted_cleanup = Deferred(Box::new(|| {
+            let _ = fs::remove_file(ENCRYPTED_FILE);
+        }));
+        let passphrase = passphrase("correct horse");
+
+        // Act
+        let save_res = WgConf::open(TEST_CONF_FILE)
+            .and_then(|mut wg_conf| wg_conf.save_encrypted(ENCRYPTED_FILE, &passphrase));
+        let read_res = WgMemConf::from_encrypted_file(ENCRYPTED_FILE, &passphrase);
+
+        // Assert
+        assert!(save_res.is_ok());
+        assert_eq!(content, read_res.unwrap().to_string());
+    }
+}
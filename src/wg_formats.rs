@@ -0,0 +1,279 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{WgClientConf, WgConf, WgConfError, WgInterface, WgPeer};
+
+/// Structured document of WG config which is used for JSON, YAML and TOML formats.
+///
+/// The schema is the same for [`WgConf`] and [`WgClientConf`]:
+/// - `interface`: `private_key`, `addresses` (CIDR strings), `listen_port`, `dns`, `post_up`, `post_down`,
+///   `mtu`, `table`, `fw_mark`, `pre_up`, `pre_down`, `save_config`, `extra` (ordered `[key, value]` pairs)
+/// - `peers`: list of `public_key`, `allowed_ips` (CIDR strings), `endpoint`, `preshared_key`, `persistent_keepalive`,
+///   `name`, `description`, `metadata` and `extra` (ordered `[key, value]` pairs)
+///
+/// Keys are canonical base64 strings, referenced private key is a single-entry map (e.g. `{"file": "/etc/wireguard/wg0.key"}`),
+/// absent optional values are null (or omitted in TOML)
+#[derive(Serialize, Deserialize)]
+struct WgConfDocument {
+    interface: WgInterface,
+    #[serde(default)]
+    peers: Vec<WgPeer>,
+}
+
+impl WgConf {
+    /// Returns the whole config (interface and all the peers) as JSON document
+    #[cfg(feature = "json")]
+    pub fn to_json(&mut self) -> Result<String, WgConfError> {
+        to_json(&self.read_document()?)
+    }
+
+    /// Creates new [`WgConf`] file from JSON document, see [`WgConf::create`]
+    #[cfg(feature = "json")]
+    pub fn create_from_json(file_name: &str, json: &str) -> Result<WgConf, WgConfError> {
+        WgConf::create_from_document(file_name, from_json(json)?)
+    }
+
+    /// Returns the whole config (interface and all the peers) as YAML document
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&mut self) -> Result<String, WgConfError> {
+        to_yaml(&self.read_document()?)
+    }
+
+    /// Creates new [`WgConf`] file from YAML document, see [`WgConf::create`]
+    #[cfg(feature = "yaml")]
+    pub fn create_from_yaml(file_name: &str, yaml: &str) -> Result<WgConf, WgConfError> {
+        WgConf::create_from_document(file_name, from_yaml(yaml)?)
+    }
+
+    /// Returns the whole config (interface and all the peers) as TOML document
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&mut self) -> Result<String, WgConfError> {
+        to_toml(&self.read_document()?)
+    }
+
+    /// Creates new [`WgConf`] file from TOML document, see [`WgConf::create`]
+    #[cfg(feature = "toml")]
+    pub fn create_from_toml(file_name: &str, toml: &str) -> Result<WgConf, WgConfError> {
+        WgConf::create_from_document(file_name, from_toml(toml)?)
+    }
+
+    fn read_document(&mut self) -> Result<WgConfDocument, WgConfError> {
+        let interface = self.interface()?;
+
+        let mut peers_iter = self.peers()?;
+        let peers: Vec<WgPeer> = peers_iter.by_ref().collect();
+        peers_iter.check_err()?;
+
+        Ok(WgConfDocument { interface, peers })
+    }
+
+    fn create_from_document(
+        file_name: &str,
+        document: WgConfDocument,
+    ) -> Result<WgConf, WgConfError> {
+        WgConf::create(file_name, document.interface, Some(document.peers))
+    }
+}
+
+impl WgClientConf {
+    /// Returns client config as JSON document
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, WgConfError> {
+        to_json(&self.to_document())
+    }
+
+    /// Creates [`WgClientConf`] from JSON document
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<WgClientConf, WgConfError> {
+        from_json(json).map(WgClientConf::from_document)
+    }
+
+    /// Returns client config as YAML document
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String, WgConfError> {
+        to_yaml(&self.to_document())
+    }
+
+    /// Creates [`WgClientConf`] from YAML document
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(yaml: &str) -> Result<WgClientConf, WgConfError> {
+        from_yaml(yaml).map(WgClientConf::from_document)
+    }
+
+    /// Returns client config as TOML document
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String, WgConfError> {
+        to_toml(&self.to_document())
+    }
+
+    /// Creates [`WgClientConf`] from TOML document
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<WgClientConf, WgConfError> {
+        from_toml(toml).map(WgClientConf::from_document)
+    }
+
+    fn to_document(&self) -> WgConfDocument {
+        WgConfDocument {
+            interface: self.interface.clone(),
+            peers: self.peers.clone(),
+        }
+    }
+
+    fn from_document(document: WgConfDocument) -> WgClientConf {
+        WgClientConf::new(document.interface, document.peers)
+    }
+}
+
+#[cfg(feature = "json")]
+fn to_json<T: Serialize>(value: &T) -> Result<String, WgConfError> {
+    serde_json::to_string_pretty(value)
+        .map_err(|err| WgConfError::Unexpected(format!("Couldn't serialize JSON document: {err}")))
+}
+
+#[cfg(feature = "json")]
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, WgConfError> {
+    serde_json::from_str(json)
+        .map_err(|err| WgConfError::ValidationFailed(format!("invalid JSON document: {err}")))
+}
+
+#[cfg(feature = "yaml")]
+fn to_yaml<T: Serialize>(value: &T) -> Result<String, WgConfError> {
+    serde_yaml::to_string(value)
+        .map_err(|err| WgConfError::Unexpected(format!("Couldn't serialize YAML document: {err}")))
+}
+
+#[cfg(feature = "yaml")]
+fn from_yaml<T: DeserializeOwned>(yaml: &str) -> Result<T, WgConfError> {
+    serde_yaml::from_str(yaml)
+        .map_err(|err| WgConfError::ValidationFailed(format!("invalid YAML document: {err}")))
+}
+
+#[cfg(feature = "toml")]
+fn to_toml<T: Serialize>(value: &T) -> Result<String, WgConfError> {
+    toml::to_string(value)
+        .map_err(|err| WgConfError::Unexpected(format!("Couldn't serialize TOML document: {err}")))
+}
+
+#[cfg(feature = "toml")]
+fn from_toml<T: DeserializeOwned>(toml: &str) -> Result<T, WgConfError> {
+    toml::from_str(toml)
+        .map_err(|err| WgConfError::ValidationFailed(format!("invalid TOML document: {err}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "yaml")]
+    use crate::{
+        wg_conf::tests::{prepare_test_conf, Deferred},
+        WgConfErrKind,
+    };
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/32
+DNS = 8.8.8.8
+MTU = 1420
+FutureKey = value
+
+[Peer]
+# Name = office
+# Meta.Owner = ops
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 0.0.0.0/0
+Endpoint = vpn.example.com:51820
+PresharedKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+PersistentKeepalive = 25
+";
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn to_json_0_from_json_0_lossless() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let json = client_conf.to_json().unwrap();
+        let from_json = WgClientConf::from_json(&json).unwrap();
+
+        // Assert
+        assert_eq!(client_conf, from_json);
+        assert_eq!(CLIENT_CONTENT, from_json.to_string());
+        assert!(json.contains(r#""name": "office""#));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn to_yaml_0_from_yaml_0_lossless() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let yaml = client_conf.to_yaml().unwrap();
+        let from_yaml = WgClientConf::from_yaml(&yaml).unwrap();
+
+        // Assert
+        assert_eq!(client_conf, from_yaml);
+        assert_eq!(CLIENT_CONTENT, from_yaml.to_string());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn to_toml_0_from_toml_0_lossless() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let toml = client_conf.to_toml().unwrap();
+        let from_toml = WgClientConf::from_toml(&toml).unwrap();
+
+        // Assert
+        assert_eq!(client_conf, from_toml);
+        assert_eq!(CLIENT_CONTENT, from_toml.to_string());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn from_yaml_0_invalid_key_0_returns_validation_err() {
+        // Arrange
+        let yaml = "interface:
+  private_key: invalid
+  addresses: [10.0.0.2/32]
+";
+
+        // Act
+        let res = WgClientConf::from_yaml(yaml);
+
+        // Assert
+        assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn wg_conf_0_to_yaml_0_create_from_yaml_0_same_conf() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_formats0.conf";
+        const CREATED_CONF_FILE: &str = "wg_formats1.conf";
+        let content = CLIENT_CONTENT.replace("MTU = 1420", "ListenPort = 8080");
+        let _test_conf = prepare_test_conf(TEST_CONF_FILE, &content);
+        let _created_conf = Deferred(Box::new(|| {
+            let _ = std::fs::remove_file(CREATED_CONF_FILE);
+        }));
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let yaml = wg_conf.to_yaml().unwrap();
+        let created_conf = WgConf::create_from_yaml(CREATED_CONF_FILE, &yaml).map(|mut conf| {
+            let peers: Vec<WgPeer> = conf.peers().unwrap().collect();
+            (conf.interface().unwrap(), peers)
+        });
+
+        // Assert
+        let (created_interface, created_peers) = created_conf.unwrap();
+        assert_eq!(wg_conf.interface().unwrap(), created_interface);
+        assert_eq!(
+            wg_conf.peers().unwrap().collect::<Vec<WgPeer>>(),
+            created_peers
+        );
+        assert_eq!(Some("ops"), created_peers[0].metadata_value("Owner"));
+    }
+}
@@ -1,207 +1,818 @@
-use std::{collections::HashMap, fmt::Debug, net::IpAddr, str::FromStr};
+use std::{
+    fmt::{Debug, Display},
+    net::IpAddr,
+    str::FromStr,
+};
 
 use ipnetwork::IpNetwork;
 
-use crate::{WgConfError, WgKey};
+use crate::{
+    WgConfErrLocation, WgConfError, WgKey, WgPrivateKey, WgPrivateKeyRef, WgSecretProvider,
+    REDACTED,
+};
 
 /// Interface tag
 pub const INTERFACE_TAG: &'static str = "[Interface]";
 
 // Fields
-const PRIVATE_KEY: &'static str = "PrivateKey";
+pub(crate) const PRIVATE_KEY: &'static str = "PrivateKey";
 const ADDRESS: &'static str = "Address";
 const LISTEN_PORT: &'static str = "ListenPort";
 const DNS: &'static str = "DNS";
 const POST_UP: &'static str = "PostUp";
 const POST_DOWN: &'static str = "PostDown";
+const MTU: &'static str = "MTU";
+const TABLE: &'static str = "Table";
+const FW_MARK: &'static str = "FwMark";
+const PRE_UP: &'static str = "PreUp";
+const PRE_DOWN: &'static str = "PreDown";
+const SAVE_CONFIG: &'static str = "SaveConfig";
+
+/// \[Interface\] fields which are managed by [`WgInterface`]
+pub(crate) const KEYS: &'static [&'static str] = &[
+    PRIVATE_KEY,
+    ADDRESS,
+    LISTEN_PORT,
+    DNS,
+    POST_UP,
+    POST_DOWN,
+    MTU,
+    TABLE,
+    FW_MARK,
+    PRE_UP,
+    PRE_DOWN,
+    SAVE_CONFIG,
+];
+/// \[Interface\] fields which values are comma-separated lists
+pub(crate) const LIST_KEYS: &'static [&'static str] = &[ADDRESS, DNS];
+/// \[Interface\] fields which may be repeated, their values are accumulated
+pub(crate) const REPEATABLE_KEYS: &'static [&'static str] =
+    &[ADDRESS, DNS, PRE_UP, POST_UP, PRE_DOWN, POST_DOWN];
+
+// Values
+const OFF: &'static str = "off";
+const AUTO: &'static str = "auto";
+
+/// Minimal MTU accepted by the WG interface (IPv4 minimal MTU)
+const MIN_MTU: u16 = 68;
+
+/// WG \[Interface\] DNS entry which is resolver's address or search domain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WgDns {
+    /// DNS resolver address
+    Ip(IpAddr),
+    /// DNS search domain
+    Domain(String),
+}
+
+impl Display for WgDns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WgDns::Ip(ip) => write!(f, "{ip}"),
+            WgDns::Domain(domain) => write!(f, "{domain}"),
+        }
+    }
+}
+
+impl FromStr for WgDns {
+    type Err = WgConfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(ip) = IpAddr::from_str(s) {
+            return Ok(WgDns::Ip(ip));
+        }
+
+        if !is_domain(s) {
+            return Err(WgConfError::ValidationFailed(format!(
+                "dns '{s}' must be an ip address or a domain"
+            )));
+        }
+
+        Ok(WgDns::Domain(s.to_owned()))
+    }
+}
+
+impl From<IpAddr> for WgDns {
+    fn from(ip: IpAddr) -> Self {
+        WgDns::Ip(ip)
+    }
+}
+
+/// Routing table used by wg-quick for the interface routes (`Table` field)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WgTable {
+    /// Routes are not added at all
+    Off,
+    /// Routes are added to the default table (wg-quick default behaviour)
+    Auto,
+    /// Routes are added to the table with the provided id
+    Id(u32),
+    /// Routes are added to the table with the provided name (see /etc/iproute2/rt_tables)
+    Name(String),
+}
+
+impl Display for WgTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WgTable::Off => write!(f, "{OFF}"),
+            WgTable::Auto => write!(f, "{AUTO}"),
+            WgTable::Id(id) => write!(f, "{id}"),
+            WgTable::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl FromStr for WgTable {
+    type Err = WgConfError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            OFF => Ok(WgTable::Off),
+            AUTO => Ok(WgTable::Auto),
+            _ if s.chars().all(|c| c.is_ascii_digit()) && !s.is_empty() => s
+                .parse()
+                .map(WgTable::Id)
+                .map_err(|_| WgConfError::ValidationFailed("invalid table id".to_string())),
+            _ if s.is_empty() || s.chars().any(char::is_whitespace) => Err(
+                WgConfError::ValidationFailed("invalid table raw value".to_string()),
+            ),
+            _ => Ok(WgTable::Name(s.to_owned())),
+        }
+    }
+}
 
 /// Represents WG \[Interface\] section
 #[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "crate::wg_serde::WgInterfaceDe"))]
 pub struct WgInterface {
-    pub(crate) private_key: WgKey,
-    pub(crate) address: IpNetwork,
+    pub(crate) private_key: WgPrivateKeyRef,
+    pub(crate) addresses: Vec<IpNetwork>,
     pub(crate) listen_port: Option<u16>,
-    pub(crate) dns: Option<IpAddr>,
-    pub(crate) post_up: Option<String>,
-    pub(crate) post_down: Option<String>,
+    pub(crate) dns: Vec<WgDns>,
+    pub(crate) post_up: Vec<String>,
+    pub(crate) post_down: Vec<String>,
+    pub(crate) mtu: Option<u16>,
+    pub(crate) table: Option<WgTable>,
+    pub(crate) fw_mark: Option<u32>,
+    pub(crate) pre_up: Vec<String>,
+    pub(crate) pre_down: Vec<String>,
+    pub(crate) save_config: bool,
+    pub(crate) extra: Vec<(String, String)>,
 }
 
 impl Debug for WgInterface {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("WgInterface")
-            .field("private_key", &"***")
-            .field("address", &self.address)
+            .field("private_key", &self.private_key)
+            .field("addresses", &self.addresses)
             .field("listen_port", &self.listen_port)
             .field("dns", &self.dns)
             .field("post_up", &self.post_up)
             .field("post_down", &self.post_down)
+            .field("mtu", &self.mtu)
+            .field("table", &self.table)
+            .field("fw_mark", &self.fw_mark)
+            .field("pre_up", &self.pre_up)
+            .field("pre_down", &self.pre_down)
+            .field("save_config", &self.save_config)
+            .field("extra", &self.extra)
             .finish()
     }
 }
 
 impl ToString for WgInterface {
     fn to_string(&self) -> String {
-        let listen_port = match &self.listen_port {
-            Some(val) => format!("\n{} = {}", LISTEN_PORT, val.to_string()),
-            None => "".to_string(),
-        };
-
-        let dns = match &self.dns {
-            Some(val) => format!("\n{} = {}", DNS, val.to_string()),
-            None => "".to_string(),
-        };
-
-        let post_up = match self.post_up.as_deref() {
-            Some(val) => format!("\n{} = {}", POST_UP, val),
-            None => "".to_string(),
-        };
-
-        let post_down = match self.post_down.as_deref() {
-            Some(val) => format!("\n{} = {}", POST_DOWN, &val),
-            None => "".to_string(),
-        };
-
-        format!(
-            "{}
-{} = {}
-{} = {}{}{}{}{}
-",
-            INTERFACE_TAG,
-            PRIVATE_KEY,
-            self.private_key.to_string(),
-            ADDRESS,
-            self.address.to_string(),
-            listen_port,
-            dns,
-            post_up,
-            post_down
-        )
+        self.to_raw_string(false)
     }
 }
 
 impl WgInterface {
     /// Creates new [`WgInterface`]
     ///
+    /// `addresses` must contain at least one address, e.g. IPv4 and IPv6 ones for dual-stack interface
+    ///
     /// `listen_port` is required only for Server configuration,
     /// in case of Client, it may be None
+    ///
+    /// `dns` is ordered list of resolvers and search domains, it may be empty
+    ///
+    /// `post_up` and `post_down` are commands which will be executed by wg-quick in the provided order
     pub fn new(
-        private_key: WgKey,
-        address: IpNetwork,
+        private_key: WgPrivateKey,
+        addresses: Vec<IpNetwork>,
+        listen_port: Option<u16>,
+        dns: Vec<WgDns>,
+        post_up: Vec<String>,
+        post_down: Vec<String>,
+    ) -> Result<WgInterface, WgConfError> {
+        WgInterface::with_private_key_ref(
+            WgPrivateKeyRef::Inline(private_key),
+            addresses,
+            listen_port,
+            dns,
+            post_up,
+            post_down,
+        )
+    }
+
+    /// Creates new [`WgInterface`] which private key is referenced, e.g. by the key file path,
+    /// so the key itself is not written into the config, see [`WgInterface::new`]
+    ///
+    /// The reference is kept as `PostUp = wg set %i private-key ...` hook: it replaces the hook
+    /// already set in `post_up` or is added as the first one
+    pub fn with_private_key_ref(
+        private_key: WgPrivateKeyRef,
+        addresses: Vec<IpNetwork>,
         listen_port: Option<u16>,
-        dns: Option<IpAddr>,
-        post_up: Option<String>,
-        post_down: Option<String>,
+        dns: Vec<WgDns>,
+        post_up: Vec<String>,
+        post_down: Vec<String>,
     ) -> Result<WgInterface, WgConfError> {
+        private_key.validate()?;
+
+        if addresses.is_empty() {
+            return Err(WgConfError::ValidationFailed(
+                "at least one address must be set".to_string(),
+            ));
+        }
+
         if let Some(listen_port) = listen_port {
             if listen_port == 0 {
                 return Err(WgConfError::ValidationFailed("port can't be 0".to_string()));
             }
         }
 
-        Ok(WgInterface {
+        let mut interface = WgInterface {
             private_key,
-            address,
+            addresses,
             listen_port,
             dns,
             post_up,
             post_down,
-        })
+            mtu: None,
+            table: None,
+            fw_mark: None,
+            pre_up: vec![],
+            pre_down: vec![],
+            save_config: false,
+            extra: vec![],
+        };
+        interface.set_private_key_hook();
+
+        Ok(interface)
     }
 
     /// Creates new [`WgInterface`] from raw String values
     ///
-    /// Note, that WG address is address with mask (e.g. 10.0.0.1/8)
+    /// Note, that WG address is address with mask (e.g. 10.0.0.1/8),
+    /// several addresses may be set comma-separated (e.g. 10.0.0.1/8, fd00::1/64)
     pub fn from_raw_values(
         private_key: String,
         address: String,
         listen_port: Option<String>,
         dns: Option<String>,
-        post_up: Option<String>,
-        post_down: Option<String>,
+        post_up: Vec<String>,
+        post_down: Vec<String>,
     ) -> Result<WgInterface, WgConfError> {
-        let private_key: WgKey = private_key.parse()?;
-
-        let address: IpNetwork = address.parse().map_err(|_| {
-            WgConfError::ValidationFailed(
-                "address must be address with mask (e.g. 10.0.0.1/8)".to_string(),
-            )
-        })?;
+        let private_key: WgPrivateKey = private_key.parse().map_err(err_at_key(PRIVATE_KEY))?;
 
-        let listen_port: Option<u16> = listen_port
-            .map(|port| {
-                port.parse().map_err(|_| {
-                    WgConfError::ValidationFailed("invalid port raw value".to_string())
-                })
-            })
-            .transpose()?;
+        WgInterface::from_raw_values_with_key_ref(
+            WgPrivateKeyRef::Inline(private_key),
+            address,
+            listen_port,
+            dns,
+            post_up,
+            post_down,
+        )
+    }
 
-        if let Some(listen_port) = listen_port {
-            if listen_port == 0 {
-                return Err(WgConfError::ValidationFailed("port can't be 0".to_string()));
-            }
+    fn from_raw_values_with_key_ref(
+        private_key: WgPrivateKeyRef,
+        address: String,
+        listen_port: Option<String>,
+        dns: Option<String>,
+        post_up: Vec<String>,
+        post_down: Vec<String>,
+    ) -> Result<WgInterface, WgConfError> {
+        let addresses = parse_addresses(&address).map_err(err_at_key(ADDRESS))?;
+        if addresses.is_empty() {
+            let err = WgConfError::ValidationFailed("at least one address must be set".to_string());
+            return Err(err_at_key(ADDRESS)(err));
         }
 
-        let dns = dns
-            .map(|dns| {
-                IpAddr::from_str(&dns).map_err(|_| {
-                    WgConfError::ValidationFailed("dns must be an ip address".to_string())
-                })
-            })
-            .transpose()?;
+        let listen_port: Option<u16> = listen_port
+            .as_deref()
+            .map(parse_port)
+            .transpose()
+            .map_err(err_at_key(LISTEN_PORT))?;
+
+        let dns: Vec<WgDns> = match dns {
+            Some(dns) => parse_dns(&dns).map_err(err_at_key(DNS))?,
+            None => vec![],
+        };
 
-        Ok(WgInterface {
+        let mut interface = WgInterface {
             private_key,
-            address,
+            addresses,
             listen_port,
             dns,
             post_up,
             post_down,
-        })
+            mtu: None,
+            table: None,
+            fw_mark: None,
+            pre_up: vec![],
+            pre_down: vec![],
+            save_config: false,
+            extra: vec![],
+        };
+        interface.set_private_key_hook();
+
+        Ok(interface)
+    }
+
+    /// Sets where the private key is taken from, see [`WgPrivateKeyRef`]
+    ///
+    /// The key hook of the previous reference in `post_up` is replaced in place
+    /// (or removed if the key is written into the config)
+    pub fn set_private_key_ref(&mut self, private_key: WgPrivateKeyRef) -> Result<(), WgConfError> {
+        private_key.validate()?;
+        if let Some(old_hook) = self.private_key.to_post_up() {
+            if private_key.to_post_up().is_none() {
+                self.post_up.retain(|hook| *hook != old_hook);
+            }
+        }
+        self.private_key = private_key;
+        self.set_private_key_hook();
+
+        Ok(())
+    }
+
+    /// Keeps the hook of referenced private key in `post_up`:
+    /// the first hook setting the key is replaced, otherwise the hook is added as the first one
+    fn set_private_key_hook(&mut self) {
+        let Some(key_hook) = self.private_key.to_post_up() else {
+            return;
+        };
+
+        match self
+            .post_up
+            .iter()
+            .position(|hook| WgPrivateKeyRef::from_post_up(hook).is_some())
+        {
+            Some(i) => self.post_up[i] = key_hook,
+            None => self.post_up.insert(0, key_hook),
+        }
+    }
+
+    /// Returns the private key resolving its reference, see [`WgPrivateKeyRef::resolve`]
+    pub fn resolve_private_key(
+        &self,
+        provider: Option<&dyn WgSecretProvider>,
+    ) -> Result<WgPrivateKey, WgConfError> {
+        self.private_key.resolve(provider)
+    }
+
+    /// Sets interface MTU, `None` means that MTU will be defined by wg-quick automatically
+    pub fn set_mtu(&mut self, mtu: Option<u16>) -> Result<(), WgConfError> {
+        if let Some(mtu) = mtu {
+            validate_mtu(mtu)?;
+        }
+
+        self.mtu = mtu;
+
+        Ok(())
+    }
+
+    /// Sets routing table which wg-quick uses for the interface routes
+    pub fn set_table(&mut self, table: Option<WgTable>) {
+        self.table = table;
+    }
+
+    /// Sets firewall mark for outgoing packets, `Some(0)` is the same as `off`
+    pub fn set_fw_mark(&mut self, fw_mark: Option<u32>) {
+        self.fw_mark = fw_mark;
+    }
+
+    /// Sets commands which will be executed by wg-quick in the provided order before the interface is up
+    pub fn set_pre_up(&mut self, pre_up: Vec<String>) {
+        self.pre_up = pre_up;
+    }
+
+    /// Sets commands which will be executed by wg-quick in the provided order after the interface is up
+    ///
+    /// The hook of referenced private key is kept, see [`WgInterface::with_private_key_ref`]
+    pub fn set_post_up(&mut self, post_up: Vec<String>) {
+        self.post_up = post_up;
+        self.set_private_key_hook();
+    }
+
+    /// Sets commands which will be executed by wg-quick in the provided order before the interface is down
+    pub fn set_pre_down(&mut self, pre_down: Vec<String>) {
+        self.pre_down = pre_down;
+    }
+
+    /// Sets commands which will be executed by wg-quick in the provided order after the interface is down
+    pub fn set_post_down(&mut self, post_down: Vec<String>) {
+        self.post_down = post_down;
+    }
+
+    /// Sets if wg-quick should save the interface state into the config on shutdown
+    pub fn set_save_config(&mut self, save_config: bool) {
+        self.save_config = save_config;
+    }
+
+    /// Sets ordered key-values which are not recognized by [`WgInterface`].
+    ///
+    /// They are written as is after the known fields, so fields of newer WG tools are kept
+    pub fn set_extra(&mut self, extra: Vec<(String, String)>) -> Result<(), WgConfError> {
+        validate_extra(&extra, KEYS)?;
+        self.extra = extra;
+
+        Ok(())
     }
 
     // getters
-    pub fn private_key(&self) -> &WgKey {
+    /// Returns the private key if it's written into the config, see [`WgInterface::resolve_private_key`]
+    pub fn private_key(&self) -> Option<&WgPrivateKey> {
+        self.private_key.inline()
+    }
+    pub fn private_key_ref(&self) -> &WgPrivateKeyRef {
         &self.private_key
     }
-    pub fn address(&self) -> &IpNetwork {
-        &self.address
+    pub fn addresses(&self) -> &[IpNetwork] {
+        &self.addresses
     }
     pub fn listen_port(&self) -> Option<u16> {
         self.listen_port
     }
-    pub fn dns(&self) -> Option<&IpAddr> {
-        self.dns.as_ref()
+    pub fn dns(&self) -> &[WgDns] {
+        &self.dns
+    }
+    pub fn post_up(&self) -> &[String] {
+        &self.post_up
+    }
+    pub fn post_down(&self) -> &[String] {
+        &self.post_down
+    }
+    pub fn mtu(&self) -> Option<u16> {
+        self.mtu
     }
-    pub fn post_up(&self) -> Option<&str> {
-        self.post_up.as_deref()
+    pub fn table(&self) -> Option<&WgTable> {
+        self.table.as_ref()
     }
-    pub fn post_down(&self) -> Option<&str> {
-        self.post_down.as_deref()
+    pub fn fw_mark(&self) -> Option<u32> {
+        self.fw_mark
+    }
+    pub fn pre_up(&self) -> &[String] {
+        &self.pre_up
+    }
+    pub fn pre_down(&self) -> &[String] {
+        &self.pre_down
+    }
+    pub fn save_config(&self) -> bool {
+        self.save_config
+    }
+    /// Returns ordered key-values which are not recognized by [`WgInterface`]
+    pub fn extra(&self) -> &[(String, String)] {
+        &self.extra
+    }
+
+    /// Same as `to_string()`, but the private key is replaced by [`REDACTED`], so the section is safe to log
+    pub fn to_string_redacted(&self) -> String {
+        self.to_raw_string(true)
     }
 
+    fn to_raw_string(&self, redacted: bool) -> String {
+        let mut raw = INTERFACE_TAG.to_string() + "\n";
+        for (k, v) in self.to_raw_key_values() {
+            match redacted && k == PRIVATE_KEY {
+                true => raw += &format!("{} = {}\n", k, REDACTED),
+                false => raw += &format!("{} = {}\n", k, v),
+            }
+        }
+
+        raw
+    }
+
+    /// Returns ordered key-values as they are written into the \[Interface\] section,
+    /// extra key-values are the last ones
+    pub(crate) fn to_raw_key_values(&self) -> Vec<(&str, String)> {
+        let mut raw_key_values = vec![];
+        if let Some(private_key) = self.private_key.inline() {
+            raw_key_values.push((PRIVATE_KEY, private_key.expose_secret().to_string()));
+        }
+        raw_key_values.push((ADDRESS, join_list(&self.addresses)));
+
+        if let Some(listen_port) = self.listen_port {
+            raw_key_values.push((LISTEN_PORT, listen_port.to_string()));
+        }
+
+        match self.fw_mark {
+            Some(0) => raw_key_values.push((FW_MARK, OFF.to_string())),
+            Some(fw_mark) => raw_key_values.push((FW_MARK, fw_mark.to_string())),
+            None => {}
+        }
+
+        if !self.dns.is_empty() {
+            raw_key_values.push((DNS, join_list(&self.dns)));
+        }
+
+        if let Some(mtu) = self.mtu {
+            raw_key_values.push((MTU, mtu.to_string()));
+        }
+
+        if let Some(table) = &self.table {
+            raw_key_values.push((TABLE, table.to_string()));
+        }
+
+        for (key, hooks) in [
+            (PRE_UP, &self.pre_up),
+            (POST_UP, &self.post_up),
+            (PRE_DOWN, &self.pre_down),
+            (POST_DOWN, &self.post_down),
+        ] {
+            raw_key_values.extend(hooks.iter().map(|hook| (key, hook.clone())));
+        }
+
+        if self.save_config {
+            raw_key_values.push((SAVE_CONFIG, "true".to_string()));
+        }
+
+        raw_key_values.extend(self.extra.iter().map(|(k, v)| (k.as_str(), v.clone())));
+
+        raw_key_values
+    }
+
+    /// Creates [`WgInterface`] from ordered key-values of the section
+    ///
+    /// Repeated Address, DNS and hook keys are accumulated in order like wg-quick does,
+    /// for other repeated keys the last value is used.
+    /// If there is no PrivateKey, the key reference is taken from `PostUp = wg set %i private-key ...` hook.
+    /// Unknown key-values are kept in order as extra ones
     pub(crate) fn from_raw_key_values(
-        raw_key_values: HashMap<String, String>,
+        raw_key_values: Vec<(String, String)>,
     ) -> Result<WgInterface, WgConfError> {
-        let mut private_key = String::new();
+        let mut private_key: Option<String> = None;
         let mut address = String::new();
         let mut listen_port: Option<String> = None;
         let mut dns: Option<String> = None;
-        let mut post_up: Option<String> = None;
-        let mut post_down: Option<String> = None;
+        let mut post_up: Vec<String> = vec![];
+        let mut post_down: Vec<String> = vec![];
+        let mut mtu: Option<String> = None;
+        let mut table: Option<String> = None;
+        let mut fw_mark: Option<String> = None;
+        let mut pre_up: Vec<String> = vec![];
+        let mut pre_down: Vec<String> = vec![];
+        let mut save_config: Option<String> = None;
+        let mut extra: Vec<(String, String)> = vec![];
 
         for (k, v) in raw_key_values {
             match k {
-                _ if k == PRIVATE_KEY => private_key = v,
-                _ if k == ADDRESS => address = v,
+                _ if k == PRIVATE_KEY => private_key = Some(v),
+                _ if k == ADDRESS => append_list_value(&mut address, &v),
                 _ if k == LISTEN_PORT => listen_port = Some(v),
-                _ if k == DNS => dns = Some(v),
-                _ if k == POST_UP => post_up = Some(v),
-                _ if k == POST_DOWN => post_down = Some(v),
-                _ => continue,
+                _ if k == DNS => append_list_value(dns.get_or_insert_with(String::new), &v),
+                _ if k == POST_UP => post_up.push(v),
+                _ if k == POST_DOWN => post_down.push(v),
+                _ if k == MTU => mtu = Some(v),
+                _ if k == TABLE => table = Some(v),
+                _ if k == FW_MARK => fw_mark = Some(v),
+                _ if k == PRE_UP => pre_up.push(v),
+                _ if k == PRE_DOWN => pre_down.push(v),
+                _ if k == SAVE_CONFIG => save_config = Some(v),
+                _ => extra.push((k, v)),
             }
         }
 
-        WgInterface::from_raw_values(private_key, address, listen_port, dns, post_up, post_down)
+        let key_ref_hook = match private_key {
+            Some(_) => None,
+            None => post_up
+                .iter()
+                .position(|hook| WgPrivateKeyRef::from_post_up(hook).is_some()),
+        };
+
+        let mut interface = match key_ref_hook {
+            Some(i) => WgInterface::from_raw_values_with_key_ref(
+                WgPrivateKeyRef::from_post_up(&post_up[i]).unwrap(), // checked above
+                address,
+                listen_port,
+                dns,
+                post_up,
+                post_down,
+            )?,
+            None => WgInterface::from_raw_values(
+                private_key.unwrap_or_default(),
+                address,
+                listen_port,
+                dns,
+                post_up,
+                post_down,
+            )?,
+        };
+
+        interface
+            .set_mtu(
+                mtu.as_deref()
+                    .map(parse_mtu)
+                    .transpose()
+                    .map_err(err_at_key(MTU))?,
+            )
+            .map_err(err_at_key(MTU))?;
+        interface.set_table(
+            table
+                .map(|table| table.parse())
+                .transpose()
+                .map_err(err_at_key(TABLE))?,
+        );
+        interface.set_fw_mark(
+            fw_mark
+                .as_deref()
+                .map(parse_fw_mark)
+                .transpose()
+                .map_err(err_at_key(FW_MARK))?,
+        );
+        interface.set_pre_up(pre_up);
+        interface.set_pre_down(pre_down);
+        interface.set_save_config(
+            save_config
+                .as_deref()
+                .map(parse_save_config)
+                .transpose()
+                .map_err(err_at_key(SAVE_CONFIG))?
+                .unwrap_or(false),
+        );
+        interface.extra = extra;
+
+        Ok(interface)
+    }
+}
+
+/// Validates raw value of the single \[Interface\] key, values of unknown keys are always valid
+pub(crate) fn validate_raw_key_value(key: &str, value: &str) -> Result<(), WgConfError> {
+    match key {
+        _ if key == PRIVATE_KEY => value.parse::<WgKey>().map(|_| ()),
+        _ if key == ADDRESS => parse_addresses(value).map(|_| ()),
+        _ if key == LISTEN_PORT => parse_port(value).map(|_| ()),
+        _ if key == DNS => parse_dns(value).map(|_| ()),
+        _ if key == MTU => parse_mtu(value).and_then(validate_mtu),
+        _ if key == TABLE => value.parse::<WgTable>().map(|_| ()),
+        _ if key == FW_MARK => parse_fw_mark(value).map(|_| ()),
+        _ if key == SAVE_CONFIG => parse_save_config(value).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+/// Appends comma-separated `value` to the accumulated comma-separated list
+pub(crate) fn append_list_value(list: &mut String, value: &str) {
+    if !list.is_empty() {
+        list.push_str(", ");
+    }
+
+    list.push_str(value);
+}
+
+/// Joins list values into comma-separated string (e.g. 10.0.0.1/24, fd00::1/64)
+pub(crate) fn join_list<T: ToString>(values: &[T]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Checks if provided string is a valid domain name (e.g. corp.internal)
+pub(crate) fn is_domain(raw: &str) -> bool {
+    if raw.is_empty() || raw.len() > 253 {
+        return false;
+    }
+
+    raw.trim_end_matches('.').split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Validates extra key-values of a section which must not clash with `known_keys`
+/// and must be writable as `key = value` lines
+pub(crate) fn validate_extra(
+    extra: &[(String, String)],
+    known_keys: &[&str],
+) -> Result<(), WgConfError> {
+    for (k, v) in extra {
+        let is_known = known_keys.iter().any(|known| known.eq_ignore_ascii_case(k));
+        let is_valid_key = !k.trim().is_empty()
+            && k.trim() == k
+            && !k.starts_with(['#', '['])
+            && !k.contains(['=', '\n', '\r']);
+
+        if is_known || !is_valid_key {
+            return Err(WgConfError::ValidationFailed(format!(
+                "'{k}' can't be used as extra key"
+            )));
+        }
+
+        if v.contains(['\n', '\r']) {
+            return Err(WgConfError::ValidationFailed(format!(
+                "value of extra key '{k}' can't contain line breaks"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds \[Interface\] section and `key` to the error location
+fn err_at_key(key: &str) -> impl Fn(WgConfError) -> WgConfError + '_ {
+    move |err| err.with_location(WgConfErrLocation::for_key(INTERFACE_TAG, key))
+}
+
+/// Parses comma-separated addresses with masks, repeated addresses are skipped
+fn parse_addresses(raw: &str) -> Result<Vec<IpNetwork>, WgConfError> {
+    let mut addresses: Vec<IpNetwork> = Vec::new();
+    for raw_address in raw.split(',') {
+        let raw_address = raw_address.trim();
+        if raw_address.is_empty() {
+            continue;
+        }
+
+        let address: IpNetwork = raw_address.parse().map_err(|_| {
+            WgConfError::ValidationFailed(
+                "address must be address with mask (e.g. 10.0.0.1/8)".to_string(),
+            )
+        })?;
+
+        // the same address in repeated Address lines is ignored
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+
+    Ok(addresses)
+}
+
+fn parse_port(raw: &str) -> Result<u16, WgConfError> {
+    let port: u16 = raw
+        .parse()
+        .map_err(|_| WgConfError::ValidationFailed("invalid port raw value".to_string()))?;
+
+    if port == 0 {
+        return Err(WgConfError::ValidationFailed("port can't be 0".to_string()));
+    }
+
+    Ok(port)
+}
+
+/// Parses comma-separated DNS resolvers and search domains
+fn parse_dns(raw: &str) -> Result<Vec<WgDns>, WgConfError> {
+    raw.split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| entry.parse())
+        .collect()
+}
+
+fn validate_mtu(mtu: u16) -> Result<(), WgConfError> {
+    if mtu < MIN_MTU {
+        return Err(WgConfError::ValidationFailed(format!(
+            "MTU can't be less than {MIN_MTU}"
+        )));
+    }
+
+    Ok(())
+}
+
+fn parse_mtu(raw: &str) -> Result<u16, WgConfError> {
+    raw.parse()
+        .map_err(|_| WgConfError::ValidationFailed("invalid MTU raw value".to_string()))
+}
+
+/// Parses `FwMark` value which may be `off`, decimal or hex (0x-prefixed) number
+fn parse_fw_mark(raw: &str) -> Result<u32, WgConfError> {
+    let fw_mark = match raw {
+        OFF => Ok(0),
+        _ => match raw.strip_prefix("0x").or(raw.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => raw.parse(),
+        },
+    };
+
+    fw_mark.map_err(|_| WgConfError::ValidationFailed("invalid fwmark raw value".to_string()))
+}
+
+fn parse_save_config(raw: &str) -> Result<bool, WgConfError> {
+    match raw {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(WgConfError::ValidationFailed(
+            "save config must be 'true' or 'false'".to_string(),
+        )),
     }
 }
 
@@ -216,11 +827,11 @@ mod tests {
             "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
                 .parse()
                 .unwrap(),
-            "192.168.130.131/25".parse().unwrap(),
+            vec!["192.168.130.131/25".parse().unwrap()],
             Some(8082),
-            Some(IpAddr::from_str("8.8.8.8").unwrap()),
-            Some("some-script".to_string()),
-            Some("some-other-script".to_string()),
+            vec!["8.8.8.8".parse().unwrap()],
+            vec!["some-script".to_string()],
+            vec!["some-other-script".to_string()],
         )
         .unwrap();
 
@@ -248,13 +859,145 @@ PostDown = some-other-script
             "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
                 .parse()
                 .unwrap(),
-            "192.168.130.131/25".parse().unwrap(),
+            vec!["192.168.130.131/25".parse().unwrap()],
             None,
+            vec![],
+            vec![],
+            vec![],
+        )
+        .unwrap();
+
+        // Act
+        let interf_raw = interface.to_string();
+
+        // Assert
+        assert_eq!(
+            "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 192.168.130.131/25
+",
+            &interf_raw
+        )
+    }
+
+    #[test]
+    fn wg_interface_0_to_string_0_dual_stack() {
+        // Arrange
+        let interface = WgInterface::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .parse()
+                .unwrap(),
+            vec![
+                "10.0.0.1/24".parse().unwrap(),
+                "fd00::1/64".parse().unwrap(),
+            ],
+            None,
+            vec![],
+            vec![],
+            vec![],
+        )
+        .unwrap();
+
+        // Act
+        let interf_raw = interface.to_string();
+
+        // Assert
+        assert_eq!(
+            "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.1/24, fd00::1/64
+",
+            &interf_raw
+        )
+    }
+
+    #[test]
+    fn from_raw_values_0_dns_list_0_keeps_order() {
+        // Act
+        let interface = WgInterface::from_raw_values(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            "10.0.0.2/32".to_string(),
             None,
+            Some("10.0.0.1,fd00::1, corp.internal".to_string()),
+            vec![],
+            vec![],
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            &[
+                WgDns::Ip("10.0.0.1".parse().unwrap()),
+                WgDns::Ip("fd00::1".parse().unwrap()),
+                WgDns::Domain("corp.internal".to_string())
+            ],
+            interface.dns()
+        );
+        assert!(interface
+            .to_string()
+            .contains("\nDNS = 10.0.0.1, fd00::1, corp.internal\n"));
+    }
+
+    #[test]
+    fn from_raw_values_0_invalid_dns_0_returns_validation_err() {
+        // Act
+        let res = WgInterface::from_raw_values(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            "10.0.0.2/32".to_string(),
             None,
+            Some("10.0.0.1, corp internal".to_string()),
+            vec![],
+            vec![],
+        );
+
+        // Assert
+        assert_eq!(
+            crate::WgConfErrKind::ValidationFailed,
+            res.unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn new_0_no_addresses_0_returns_validation_err() {
+        // Act
+        let res = WgInterface::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .parse()
+                .unwrap(),
+            vec![],
             None,
+            vec![],
+            vec![],
+            vec![],
+        );
+
+        // Assert
+        assert_eq!(
+            crate::WgConfErrKind::ValidationFailed,
+            res.unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn wg_interface_0_to_string_0_wg_quick_fields() {
+        // Arrange
+        let mut interface = WgInterface::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .parse()
+                .unwrap(),
+            vec!["192.168.130.131/25".parse().unwrap()],
+            Some(8082),
+            vec![],
+            vec!["some-script".to_string()],
+            vec!["some-other-script".to_string()],
         )
         .unwrap();
+        interface.set_mtu(Some(1420)).unwrap();
+        interface.set_table(Some(WgTable::Id(1234)));
+        interface.set_fw_mark(Some(51820));
+        interface.set_pre_up(vec!["pre-up-script".to_string()]);
+        interface.set_pre_down(vec!["pre-down-script".to_string()]);
+        interface.set_save_config(true);
 
         // Act
         let interf_raw = interface.to_string();
@@ -264,8 +1007,377 @@ PostDown = some-other-script
             "[Interface]
 PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
 Address = 192.168.130.131/25
+ListenPort = 8082
+FwMark = 51820
+MTU = 1420
+Table = 1234
+PreUp = pre-up-script
+PostUp = some-script
+PreDown = pre-down-script
+PostDown = some-other-script
+SaveConfig = true
 ",
             &interf_raw
         )
     }
+
+    #[test]
+    fn from_raw_key_values_0_repeated_keys_0_accumulates_in_order() {
+        // Arrange
+        let raw_key_values = vec![
+            (
+                PRIVATE_KEY.to_string(),
+                "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            ),
+            (ADDRESS.to_string(), "10.0.0.1/24".to_string()),
+            (POST_UP.to_string(), "first-script".to_string()),
+            (DNS.to_string(), "10.0.0.1".to_string()),
+            (ADDRESS.to_string(), "fd00::1/64".to_string()),
+            (POST_UP.to_string(), "second-script".to_string()),
+            (DNS.to_string(), "corp.internal".to_string()),
+            (LISTEN_PORT.to_string(), "8080".to_string()),
+            (LISTEN_PORT.to_string(), "8082".to_string()),
+        ];
+
+        // Act
+        let interface = WgInterface::from_raw_key_values(raw_key_values).unwrap();
+
+        // Assert
+        assert_eq!(2, interface.addresses().len());
+        assert_eq!(["first-script", "second-script"], interface.post_up());
+        assert_eq!(
+            &[
+                WgDns::Ip("10.0.0.1".parse().unwrap()),
+                WgDns::Domain("corp.internal".to_string())
+            ],
+            interface.dns()
+        );
+        assert_eq!(Some(8082), interface.listen_port());
+        assert!(interface.to_string().contains(
+            "
+PostUp = first-script
+PostUp = second-script
+"
+        ));
+    }
+
+    #[test]
+    fn from_raw_key_values_0_wg_quick_fields() {
+        // Arrange
+        let raw_key_values = vec![
+            (
+                PRIVATE_KEY.to_string(),
+                "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            ),
+            (ADDRESS.to_string(), "10.0.0.1/24".to_string()),
+            (MTU.to_string(), "1420".to_string()),
+            (TABLE.to_string(), "off".to_string()),
+            (FW_MARK.to_string(), "0xca6c".to_string()),
+            (PRE_UP.to_string(), "pre-up-script".to_string()),
+            (PRE_DOWN.to_string(), "pre-down-script".to_string()),
+            (SAVE_CONFIG.to_string(), "true".to_string()),
+        ];
+
+        // Act
+        let interface = WgInterface::from_raw_key_values(raw_key_values).unwrap();
+
+        // Assert
+        assert_eq!(Some(1420), interface.mtu());
+        assert_eq!(Some(&WgTable::Off), interface.table());
+        assert_eq!(Some(51820), interface.fw_mark());
+        assert_eq!(["pre-up-script"], interface.pre_up());
+        assert_eq!(["pre-down-script"], interface.pre_down());
+        assert!(interface.save_config());
+    }
+
+    #[test]
+    fn from_raw_key_values_0_invalid_wg_quick_fields_0_returns_validation_err() {
+        let invalid_values = [
+            (MTU, "0"),
+            (MTU, "big"),
+            (TABLE, "main table"),
+            (FW_MARK, "0xzz"),
+            (SAVE_CONFIG, "yes"),
+        ];
+
+        for (key, value) in invalid_values {
+            // Arrange
+            let raw_key_values = vec![
+                (
+                    PRIVATE_KEY.to_string(),
+                    "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+                ),
+                (ADDRESS.to_string(), "10.0.0.1/24".to_string()),
+                (key.to_string(), value.to_string()),
+            ];
+
+            // Act
+            let res = WgInterface::from_raw_key_values(raw_key_values);
+
+            // Assert
+            assert_eq!(
+                crate::WgConfErrKind::ValidationFailed,
+                res.unwrap_err().kind(),
+                "{key} = {value}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_raw_key_values_0_unknown_keys_0_kept_as_extra() {
+        // Arrange
+        let raw_key_values = vec![
+            (
+                PRIVATE_KEY.to_string(),
+                "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            ),
+            ("Jc".to_string(), "4".to_string()),
+            (ADDRESS.to_string(), "10.0.0.1/24".to_string()),
+            ("H1".to_string(), "1234".to_string()),
+            ("Jc".to_string(), "5".to_string()),
+        ];
+
+        // Act
+        let interface = WgInterface::from_raw_key_values(raw_key_values).unwrap();
+
+        // Assert
+        assert_eq!(
+            &[
+                ("Jc".to_string(), "4".to_string()),
+                ("H1".to_string(), "1234".to_string()),
+                ("Jc".to_string(), "5".to_string()),
+            ],
+            interface.extra()
+        );
+        assert_eq!(
+            "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.1/24
+Jc = 4
+H1 = 1234
+Jc = 5
+",
+            interface.to_string()
+        );
+    }
+
+    #[test]
+    fn from_raw_key_values_0_private_key_hook_0_kept_as_key_ref() {
+        // Arrange
+        let raw_key_values = vec![
+            (ADDRESS.to_string(), "10.0.0.1/24".to_string()),
+            (POST_UP.to_string(), "ufw allow 8080/udp".to_string()),
+            (
+                POST_UP.to_string(),
+                "wg set %i private-key /etc/wireguard/wg0.key".to_string(),
+            ),
+        ];
+
+        // Act
+        let interface = WgInterface::from_raw_key_values(raw_key_values).unwrap();
+
+        // Assert
+        assert_eq!(None, interface.private_key());
+        assert_eq!(
+            &WgPrivateKeyRef::File("/etc/wireguard/wg0.key".to_string()),
+            interface.private_key_ref()
+        );
+        assert_eq!(
+            [
+                "ufw allow 8080/udp",
+                "wg set %i private-key /etc/wireguard/wg0.key"
+            ],
+            interface.post_up()
+        );
+        assert_eq!(
+            "[Interface]
+Address = 10.0.0.1/24
+PostUp = ufw allow 8080/udp
+PostUp = wg set %i private-key /etc/wireguard/wg0.key
+",
+            interface.to_string_redacted()
+        );
+        assert_eq!(
+            interface,
+            WgInterface::from_raw_key_values(
+                interface
+                    .to_raw_key_values()
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v))
+                    .collect()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn from_raw_key_values_0_invalid_optional_values_0_returns_err_located_at_key() {
+        let invalid_key_values = [
+            (MTU, "70000"),
+            (MTU, "10"),
+            (TABLE, "main table"),
+            (FW_MARK, "0xZZ"),
+            (SAVE_CONFIG, "yes"),
+        ];
+
+        for (key, value) in invalid_key_values {
+            // Arrange
+            let raw_key_values = vec![
+                (
+                    PRIVATE_KEY.to_string(),
+                    "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+                ),
+                (ADDRESS.to_string(), "10.0.0.1/24".to_string()),
+                (key.to_string(), value.to_string()),
+            ];
+
+            // Act
+            let err = WgInterface::from_raw_key_values(raw_key_values).unwrap_err();
+
+            // Assert
+            assert_eq!(
+                crate::WgConfErrKind::ValidationFailed,
+                err.kind(),
+                "{key} = {value}"
+            );
+            assert_eq!(Some(key), err.location().unwrap().key(), "{key} = {value}");
+        }
+    }
+
+    #[test]
+    fn set_private_key_ref_0_file_then_inline_0_hook_replaced_in_place_then_removed() {
+        // Arrange
+        let mut interface = WgInterface::with_private_key_ref(
+            WgPrivateKeyRef::File("/etc/wireguard/wg0.key".to_string()),
+            vec!["10.0.0.1/24".parse().unwrap()],
+            None,
+            vec![],
+            vec![
+                "ufw allow 8080/udp".to_string(),
+                "wg set %i private-key /etc/wireguard/wg0.key".to_string(),
+                "ip link set %i up".to_string(),
+            ],
+            vec![],
+        )
+        .unwrap();
+
+        // Act
+        interface
+            .set_private_key_ref(WgPrivateKeyRef::Env("WG_PRIVATE_KEY".to_string()))
+            .unwrap();
+        let env_post_up = interface.post_up().to_vec();
+        interface
+            .set_private_key_ref(WgPrivateKeyRef::Inline(
+                "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                    .parse()
+                    .unwrap(),
+            ))
+            .unwrap();
+
+        // Assert
+        assert_eq!(
+            [
+                "ufw allow 8080/udp",
+                "wg set %i private-key <(printenv WG_PRIVATE_KEY)",
+                "ip link set %i up"
+            ],
+            env_post_up.as_slice()
+        );
+        assert_eq!(
+            ["ufw allow 8080/udp", "ip link set %i up"],
+            interface.post_up()
+        );
+    }
+
+    #[test]
+    fn set_private_key_ref_0_invalid_0_returns_validation_err() {
+        // Arrange
+        let mut interface = WgInterface::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.1/24".parse().unwrap()],
+            None,
+            vec![],
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        let invalid_key_refs = [
+            WgPrivateKeyRef::File("".to_string()),
+            WgPrivateKeyRef::File("/etc/wire guard/wg0.key".to_string()),
+            WgPrivateKeyRef::Env("1WG_KEY".to_string()),
+            WgPrivateKeyRef::Env("WG-KEY".to_string()),
+            WgPrivateKeyRef::File("/etc/wireguard/$(id).key".to_string()),
+            WgPrivateKeyRef::File("wg0.key;id".to_string()),
+            WgPrivateKeyRef::Provider {
+                command: " ".to_string(),
+                reference: "wg/server".to_string(),
+            },
+            WgPrivateKeyRef::Provider {
+                command: "pass\nshow".to_string(),
+                reference: "wg/server".to_string(),
+            },
+            WgPrivateKeyRef::Provider {
+                command: "pass show".to_string(),
+                reference: "wg/server | tee key".to_string(),
+            },
+            WgPrivateKeyRef::Provider {
+                command: "pass show".to_string(),
+                reference: "`id`".to_string(),
+            },
+            WgPrivateKeyRef::Provider {
+                command: "pass show".to_string(),
+                reference: "$(id)".to_string(),
+            },
+        ];
+
+        for key_ref in invalid_key_refs {
+            // Act
+            let res = interface.set_private_key_ref(key_ref);
+
+            // Assert
+            assert_eq!(
+                crate::WgConfErrKind::ValidationFailed,
+                res.unwrap_err().kind()
+            );
+        }
+    }
+
+    #[test]
+    fn set_extra_0_invalid_0_returns_validation_err() {
+        // Arrange
+        let mut interface = WgInterface::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.1/24".parse().unwrap()],
+            None,
+            vec![],
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        let invalid_extras = [
+            ("listenport", "8080"),
+            ("", "value"),
+            ("# comment", "value"),
+            ("[Peer]", "value"),
+            ("Key=", "value"),
+            ("Key", "multi\nline"),
+        ];
+
+        for (key, value) in invalid_extras {
+            // Act
+            let res = interface.set_extra(vec![(key.to_string(), value.to_string())]);
+
+            // Assert
+            assert_eq!(
+                crate::WgConfErrKind::ValidationFailed,
+                res.unwrap_err().kind(),
+                "key: {key}"
+            );
+        }
+        assert!(interface.extra().is_empty());
+    }
 }
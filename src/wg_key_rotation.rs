@@ -0,0 +1,897 @@
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use ipnetwork::IpNetwork;
+
+use crate::{
+    fileworks, wg_interface, wg_secret, WgConf, WgConfError, WgEndpoint, WgHost, WgKey, WgKeyPair,
+    WgPeer, WgPresharedKey, WgPrivateKey, WgPrivateKeyRef, WgPublicKey,
+};
+
+/// `# PreviousPublicKey = <key>` comment of \[Interface\] which keeps the public key before the last rotation
+const PREVIOUS_PUBLIC_KEY: &'static str = "PreviousPublicKey";
+/// `# PendingPrivateKeyFile = <path>` comment of \[Interface\] which refers to the new key of the prepared rotation
+const PENDING_PRIVATE_KEY_FILE: &'static str = "PendingPrivateKeyFile";
+
+/// Rotation of \[Interface\] key which is prepared with [`WgConf::prepare_key_rotation`]
+///
+/// Keeps the new key pair and \[Peer\] sections with the new server public key for every client,
+/// the sections replace the server peer in the clients' configs.
+/// The prepared rotation is loaded back with [`WgConf::pending_key_rotation`]
+#[derive(Debug, Clone)]
+pub struct WgKeyRotation {
+    old_public_key: WgPublicKey,
+    new_key_pair: WgKeyPair,
+    client_peers: Vec<(WgPublicKey, WgPeer)>,
+    pending_key_file: Option<String>,
+}
+
+/// Preshared key rotation of the single \[Peer\] which is done with [`WgConf::rotate_preshared_keys`]
+///
+/// Keeps the new preshared key and \[Peer\] section with the server which replaces the server peer
+/// in the client's config
+#[derive(Debug, Clone)]
+pub struct WgPresharedKeyRotation {
+    public_key: WgPublicKey,
+    preshared_key: WgPresharedKey,
+    client_peer: WgPeer,
+}
+
+impl WgKeyRotation {
+    // getters
+    pub fn old_public_key(&self) -> &WgPublicKey {
+        &self.old_public_key
+    }
+    pub fn new_public_key(&self) -> &WgPublicKey {
+        &self.new_key_pair.public_key
+    }
+    pub fn new_private_key(&self) -> &WgPrivateKey {
+        &self.new_key_pair.private_key
+    }
+    /// Returns the file which keeps the new private key till the activation,
+    /// `None` if the rotation wasn't prepared (see [`WgConf::rotate_key`])
+    pub fn pending_key_file(&self) -> Option<&str> {
+        self.pending_key_file.as_deref()
+    }
+    /// Returns client public keys with their updated \[Peer\] sections with the server
+    pub fn client_peers(&self) -> &[(WgPublicKey, WgPeer)] {
+        &self.client_peers
+    }
+
+    /// Returns updated \[Peer\] section with the server for the client with provided public key
+    pub fn client_peer(&self, client_public_key: &WgPublicKey) -> Option<&WgPeer> {
+        self.client_peers
+            .iter()
+            .find(|(public_key, _)| public_key == client_public_key)
+            .map(|(_, peer)| peer)
+    }
+}
+
+impl WgPresharedKeyRotation {
+    // getters
+    /// Returns public key of the client which preshared key was rotated
+    pub fn public_key(&self) -> &WgPublicKey {
+        &self.public_key
+    }
+    pub fn preshared_key(&self) -> &WgPresharedKey {
+        &self.preshared_key
+    }
+    /// Returns updated \[Peer\] section with the server for the client
+    pub fn client_peer(&self) -> &WgPeer {
+        &self.client_peer
+    }
+}
+
+impl WgConf {
+    /// Prepares (stages) rotation of \[Interface\] key without changing the interface:
+    /// generates new key pair and \[Peer\] sections with the new server public key for every existing peer,
+    /// so the clients may be migrated before the new key is activated with [`WgConf::activate_key_rotation`]
+    ///
+    /// The new private key is written into `pending_key_file` (readable by the owner only) which is recorded
+    /// in `# PendingPrivateKeyFile = <path>` comment of \[Interface\], so the rotation may be loaded back
+    /// with [`WgConf::pending_key_rotation`]. The file of the previously prepared rotation is removed.
+    ///
+    /// `server_host` and `server_allowed_ips` are used for the clients' sections like in [`WgConf::generate_peer`],
+    /// preshared key and persistent keepalive are taken from the server's \[Peer\]
+    ///
+    /// Only inline and file private keys may be rotated, see [`WgPrivateKeyRef`].
+    /// Returns [`WgConfError::ValidationFailed`] without writing any file if there is no ListenPort
+    pub fn prepare_key_rotation(
+        mut self,
+        server_host: WgHost,
+        server_allowed_ips: Vec<IpNetwork>,
+        pending_key_file: &str,
+    ) -> Result<(WgConf, WgKeyRotation), WgConfError> {
+        WgPrivateKeyRef::File(pending_key_file.to_string()).validate()?;
+        let interface = self.interface()?;
+        if *interface.private_key_ref() == WgPrivateKeyRef::File(pending_key_file.to_string()) {
+            return Err(WgConfError::ValidationFailed(
+                "pending key file can't be the interface key file".to_string(),
+            ));
+        }
+
+        let mut rotation =
+            self.new_key_rotation(WgKeyPair::generate()?, server_host, server_allowed_ips)?;
+        let prev_pending_key_file = self.pending_key_file()?;
+
+        fileworks::write_owner_only(
+            pending_key_file,
+            format!("{}\n", rotation.new_private_key().expose_secret()).as_bytes(),
+        )?;
+        let updated_conf = self
+            .update_interface_in_file(
+                interface,
+                &[(
+                    PENDING_PRIVATE_KEY_FILE.to_string(),
+                    pending_key_file.to_string(),
+                )],
+                is_pending_private_key_file,
+            )
+            .map_err(|err| {
+                let _ = fs::remove_file(pending_key_file);
+
+                err
+            })?;
+
+        if let Some(prev_pending_key_file) = prev_pending_key_file {
+            if prev_pending_key_file != pending_key_file {
+                let _ = fs::remove_file(prev_pending_key_file);
+            }
+        }
+        rotation.pending_key_file = Some(pending_key_file.to_string());
+
+        Ok((updated_conf, rotation))
+    }
+
+    /// Loads the rotation prepared with [`WgConf::prepare_key_rotation`] from its pending key file,
+    /// `None` if there is no prepared rotation
+    ///
+    /// \[Peer\] sections are generated for the current peers with `server_host` and `server_allowed_ips`
+    pub fn pending_key_rotation(
+        &mut self,
+        server_host: WgHost,
+        server_allowed_ips: Vec<IpNetwork>,
+    ) -> Result<Option<WgKeyRotation>, WgConfError> {
+        let Some(pending_key_file) = self.pending_key_file()? else {
+            return Ok(None);
+        };
+
+        let new_key_pair =
+            WgKeyPair::from_private_key(wg_secret::read_key_file(&pending_key_file)?)?;
+        let mut rotation = self.new_key_rotation(new_key_pair, server_host, server_allowed_ips)?;
+        rotation.pending_key_file = Some(pending_key_file);
+
+        Ok(Some(rotation))
+    }
+
+    /// Activates prepared key rotation: sets the new \[Interface\] key and records the old public key
+    /// in `# PreviousPublicKey = <key>` comment of \[Interface\], see [`WgConf::previous_pub_key`].
+    /// The pending key file and its comment are removed
+    ///
+    /// Inline key is replaced in the config. For key file the new key is written into tmp file
+    /// which replaces the key file only after the config is updated
+    ///
+    /// Returns [`WgConfError::ValidationFailed`] if the config key was changed after the rotation was prepared
+    pub fn activate_key_rotation(
+        mut self,
+        rotation: &WgKeyRotation,
+    ) -> Result<WgConf, WgConfError> {
+        if self.pub_key()? != rotation.old_public_key {
+            return Err(WgConfError::ValidationFailed(
+                "interface key was changed after the key rotation was prepared".to_string(),
+            ));
+        }
+
+        let pending_key_file = self.pending_key_file()?;
+        let mut interface = self.interface()?;
+        let key_file_update = match interface.private_key_ref() {
+            WgPrivateKeyRef::File(path) => {
+                let tmp_key_file = format!("{path}.tmp");
+                fileworks::write_owner_only(
+                    &tmp_key_file,
+                    format!("{}\n", rotation.new_private_key().expose_secret()).as_bytes(),
+                )?;
+
+                Some((tmp_key_file, path.clone()))
+            }
+            WgPrivateKeyRef::Inline(_) => {
+                interface.set_private_key_ref(WgPrivateKeyRef::Inline(
+                    rotation.new_private_key().clone(),
+                ))?;
+
+                None
+            }
+            key_ref => {
+                validate_rotatable(key_ref)?;
+
+                None
+            }
+        };
+
+        let updated_conf = self
+            .update_interface_in_file(
+                interface,
+                &[(
+                    PREVIOUS_PUBLIC_KEY.to_string(),
+                    rotation.old_public_key.to_string(),
+                )],
+                is_key_rotation_comment,
+            )
+            .map_err(|err| {
+                if let Some((tmp_key_file, _)) = &key_file_update {
+                    let _ = fs::remove_file(tmp_key_file);
+                }
+
+                err
+            })?;
+
+        if let Some((tmp_key_file, key_file)) = key_file_update {
+            fs::rename(&tmp_key_file, &key_file).map_err(|err| {
+                WgConfError::CriticalKeepTmp(format!(
+                    "Couldn't rename {tmp_key_file} to {key_file}: {err}"
+                ))
+            })?;
+        }
+        if let Some(pending_key_file) = pending_key_file {
+            let _ = fs::remove_file(pending_key_file);
+        }
+
+        Ok(updated_conf)
+    }
+
+    /// Rotates \[Interface\] key at once without pending key file,
+    /// see [`WgConf::prepare_key_rotation`] and [`WgConf::activate_key_rotation`]
+    pub fn rotate_key(
+        mut self,
+        server_host: WgHost,
+        server_allowed_ips: Vec<IpNetwork>,
+    ) -> Result<(WgConf, WgKeyRotation), WgConfError> {
+        let rotation =
+            self.new_key_rotation(WgKeyPair::generate()?, server_host, server_allowed_ips)?;
+        let updated_conf = self.activate_key_rotation(&rotation)?;
+
+        Ok((updated_conf, rotation))
+    }
+
+    /// Returns the public key which \[Interface\] had before the last activated key rotation
+    pub fn previous_pub_key(&mut self) -> Result<Option<WgPublicKey>, WgConfError> {
+        self.interface_comment(is_previous_public_key)?
+            .map(|v| v.parse())
+            .transpose()
+    }
+
+    /// Regenerates preshared keys of the peers which satisfy `filter` (e.g. `|_| true` for all the peers)
+    /// rewriting the config file once, peers without preshared key get the new one
+    ///
+    /// The rotation time is recorded in `# PresharedKeyRotatedAt = <unix time>` comment of every rotated peer,
+    /// see [`WgPeer::preshared_key_rotated_at`] and [`WgConf::stale_preshared_keys`].
+    /// Returns the new key and \[Peer\] section with the server per rotated peer which must be distributed
+    /// to the clients, `server_host` and `server_allowed_ips` are used like in [`WgConf::generate_peer`]
+    pub fn rotate_preshared_keys(
+        mut self,
+        filter: impl Fn(&WgPeer) -> bool,
+        server_host: WgHost,
+        server_allowed_ips: Vec<IpNetwork>,
+    ) -> Result<(WgConf, Vec<WgPresharedKeyRotation>), WgConfError> {
+        let server_public_key = self.pub_key()?;
+        let server_endpoint = self.server_endpoint(server_host)?;
+        let rotated_at = SystemTime::now();
+
+        let mut peers = self.peers()?;
+        let mut rotated_peers: Vec<WgPeer> = peers.by_ref().filter(|peer| filter(peer)).collect();
+        peers.check_err()?;
+
+        if rotated_peers.is_empty() {
+            return Ok((self, vec![]));
+        }
+
+        let mut rotations = Vec::with_capacity(rotated_peers.len());
+        for peer in rotated_peers.iter_mut() {
+            let preshared_key = WgKey::generate_preshared_key()?;
+            peer.rotate_preshared_key(preshared_key.clone(), rotated_at)?;
+
+            let client_peer = WgPeer::new(
+                server_public_key.clone(),
+                server_allowed_ips.clone(),
+                Some(server_endpoint.clone()),
+                Some(preshared_key.clone()),
+                peer.persistent_keepalive(),
+            );
+            rotations.push(WgPresharedKeyRotation {
+                public_key: peer.public_key.clone(),
+                preshared_key,
+                client_peer,
+            });
+        }
+
+        let updated_conf = self.update_peers_in_file(&rotated_peers)?;
+
+        Ok((updated_conf, rotations))
+    }
+
+    /// Regenerates preshared key of the peer with provided public key, see [`WgConf::rotate_preshared_keys`]
+    pub fn rotate_preshared_key(
+        self,
+        public_key: &WgPublicKey,
+        server_host: WgHost,
+        server_allowed_ips: Vec<IpNetwork>,
+    ) -> Result<(WgConf, WgPresharedKeyRotation), WgConfError> {
+        let (updated_conf, mut rotations) = self.rotate_preshared_keys(
+            |peer| peer.public_key() == public_key,
+            server_host,
+            server_allowed_ips,
+        )?;
+
+        match rotations.pop() {
+            Some(rotation) => Ok((updated_conf, rotation)),
+            None => Err(WgConfError::NotFound(format!(
+                "Peer with public key '{}'",
+                public_key.to_string()
+            ))),
+        }
+    }
+
+    /// Returns peers which preshared keys were rotated more than `max_age` ago or were never rotated
+    /// (there is no rotation time), peers without preshared key are skipped
+    pub fn stale_preshared_keys(&mut self, max_age: Duration) -> Result<Vec<WgPeer>, WgConfError> {
+        let now = SystemTime::now();
+
+        let mut peers = self.peers()?;
+        let stale_peers = peers
+            .by_ref()
+            .filter(|peer| peer.preshared_key().is_some())
+            .filter(|peer| match peer.preshared_key_rotated_at() {
+                Some(rotated_at) => now
+                    .duration_since(rotated_at)
+                    .is_ok_and(|age| age > max_age),
+                None => true,
+            })
+            .collect();
+        peers.check_err()?;
+
+        Ok(stale_peers)
+    }
+
+    /// Generates \[Peer\] sections with the new server public key for every existing peer
+    fn new_key_rotation(
+        &mut self,
+        new_key_pair: WgKeyPair,
+        server_host: WgHost,
+        server_allowed_ips: Vec<IpNetwork>,
+    ) -> Result<WgKeyRotation, WgConfError> {
+        let interface = self.interface()?;
+        validate_rotatable(interface.private_key_ref())?;
+        let server_endpoint = self.server_endpoint(server_host)?;
+
+        let old_public_key = self.pub_key()?;
+
+        let mut peers = self.peers()?;
+        let client_peers = peers
+            .by_ref()
+            .map(|peer| {
+                let client_peer = WgPeer::new(
+                    new_key_pair.public_key.clone(),
+                    server_allowed_ips.clone(),
+                    Some(server_endpoint.clone()),
+                    peer.preshared_key().cloned(),
+                    peer.persistent_keepalive(),
+                );
+
+                (peer.public_key, client_peer)
+            })
+            .collect();
+        peers.check_err()?;
+
+        Ok(WgKeyRotation {
+            old_public_key,
+            new_key_pair,
+            client_peers,
+            pending_key_file: None,
+        })
+    }
+
+    fn pending_key_file(&mut self) -> Result<Option<String>, WgConfError> {
+        self.interface_comment(is_pending_private_key_file)
+    }
+
+    /// Returns the value of the last `# Key = value` comment of \[Interface\] which key satisfies `is_key`
+    fn interface_comment(
+        &mut self,
+        is_key: fn(&str) -> bool,
+    ) -> Result<Option<String>, WgConfError> {
+        let interface_document = self.interface_document()?;
+
+        let value = interface_document
+            .sections()
+            .iter()
+            .filter(|section| section.is(wg_interface::INTERFACE_TAG))
+            .flat_map(|section| section.lines())
+            .rev()
+            .filter_map(|line| line.comment_key_value())
+            .find(|(k, _)| is_key(k))
+            .map(|(_, v)| v.to_string());
+
+        Ok(value)
+    }
+
+    /// Returns server endpoint which clients connect to, the port is \[Interface\] listen port
+    ///
+    /// Returns [`WgConfError::ValidationFailed`] if there is no listen port
+    fn server_endpoint(&mut self, server_host: WgHost) -> Result<WgEndpoint, WgConfError> {
+        let server_listen_port = self.interface()?.listen_port().ok_or_else(|| {
+            WgConfError::ValidationFailed(
+                "ListenPort is required to build the server endpoint".to_string(),
+            )
+        })?;
+
+        WgEndpoint::new(server_host, server_listen_port)
+    }
+}
+
+fn is_previous_public_key(key: &str) -> bool {
+    key.eq_ignore_ascii_case(PREVIOUS_PUBLIC_KEY)
+}
+
+fn is_pending_private_key_file(key: &str) -> bool {
+    key.eq_ignore_ascii_case(PENDING_PRIVATE_KEY_FILE)
+}
+
+fn is_key_rotation_comment(key: &str) -> bool {
+    is_previous_public_key(key) || is_pending_private_key_file(key)
+}
+
+fn validate_rotatable(private_key: &WgPrivateKeyRef) -> Result<(), WgConfError> {
+    match private_key {
+        WgPrivateKeyRef::Inline(_) | WgPrivateKeyRef::File(_) => Ok(()),
+        _ => Err(WgConfError::ValidationFailed(format!(
+            "private key {private_key:?} can't be rotated, only inline and file keys can"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        wg_conf::tests::{prepare_test_conf, Deferred},
+        WgConfErrKind, WgKey, WgPrivateKeyRef,
+    };
+
+    const CONTENT: &'static str = "[Interface]
+# Server
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+
+[Peer]
+PublicKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+AllowedIPs = 10.0.0.3/32
+PresharedKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+PersistentKeepalive = 25
+";
+
+    fn server_allowed_ips() -> Vec<IpNetwork> {
+        vec!["10.0.0.0/24".parse().unwrap()]
+    }
+
+    #[test]
+    fn rotate_key_0_inline_key_0_replaces_key_and_records_old_one() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation0.conf";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let old_public_key = wg_conf.pub_key().unwrap();
+        let client_public_key: WgPublicKey = "Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4="
+            .parse()
+            .unwrap();
+
+        // Act
+        let res = wg_conf.rotate_key(
+            WgHost::Domain("vpn.example.com".to_string()),
+            server_allowed_ips(),
+        );
+
+        // Assert
+        let (mut wg_conf, rotation) = res.unwrap();
+        let text = fs::read_to_string(TEST_CONF_FILE).unwrap();
+
+        assert_eq!(old_public_key, *rotation.old_public_key());
+        assert_eq!(None, rotation.pending_key_file());
+        assert_eq!(*rotation.new_public_key(), wg_conf.pub_key().unwrap());
+        assert_eq!(
+            Some(old_public_key.clone()),
+            wg_conf.previous_pub_key().unwrap()
+        );
+        assert!(text.starts_with(&format!(
+            "[Interface]\n# PreviousPublicKey = {}\n# Server\nPrivateKey = {}\n",
+            old_public_key.to_string(),
+            rotation.new_private_key().expose_secret()
+        )));
+        assert!(text.ends_with(&CONTENT[CONTENT.find("[Peer]").unwrap()..]));
+
+        assert_eq!(2, rotation.client_peers().len());
+        let client_peer = rotation.client_peer(&client_public_key).unwrap();
+        assert_eq!(rotation.new_public_key(), client_peer.public_key());
+        assert_eq!(server_allowed_ips(), client_peer.allowed_ips());
+        assert_eq!(
+            "vpn.example.com:8080",
+            client_peer.endpoint().unwrap().to_string()
+        );
+        assert_eq!(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=",
+            client_peer.preshared_key().unwrap().expose_secret()
+        );
+        assert_eq!(Some(25), client_peer.persistent_keepalive());
+    }
+
+    #[test]
+    fn prepare_key_rotation_0_staged_0_key_is_pending_till_activation() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation1.conf";
+        const KEY_FILE: &str = "wg_key_rotation1.key";
+        const PENDING_KEY_FILE: &str = "wg_key_rotation1.pending.key";
+        let content = CONTENT.replace(
+            "PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
+            &format!("PostUp = wg set %i private-key {KEY_FILE}"),
+        );
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let _key_cleanup =
+            prepare_test_conf(KEY_FILE, "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=\n");
+        let _pending_key_cleanup = Deferred(Box::new(|| {
+            let _ = fs::remove_file(PENDING_KEY_FILE);
+        }));
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let (mut wg_conf, rotation) = wg_conf
+            .prepare_key_rotation(
+                WgHost::Ip("127.0.0.1".parse().unwrap()),
+                server_allowed_ips(),
+                PENDING_KEY_FILE,
+            )
+            .unwrap();
+        let staged_text = fs::read_to_string(TEST_CONF_FILE).unwrap();
+        let staged_pub_key = wg_conf.pub_key().unwrap();
+        let loaded_rotation = WgConf::open(TEST_CONF_FILE)
+            .unwrap()
+            .pending_key_rotation(
+                WgHost::Ip("127.0.0.1".parse().unwrap()),
+                server_allowed_ips(),
+            )
+            .unwrap()
+            .unwrap();
+        let mut wg_conf = wg_conf.activate_key_rotation(&loaded_rotation).unwrap();
+        let activated_pub_key = wg_conf.pub_key();
+
+        // Assert
+        let key_file_text = fs::read_to_string(KEY_FILE).unwrap();
+        let activated_text = fs::read_to_string(TEST_CONF_FILE).unwrap();
+
+        assert_eq!(
+            content.replace(
+                "[Interface]\n",
+                &format!("[Interface]\n# PendingPrivateKeyFile = {PENDING_KEY_FILE}\n")
+            ),
+            staged_text
+        );
+        assert_eq!(*rotation.old_public_key(), staged_pub_key);
+        assert_eq!(Some(PENDING_KEY_FILE), rotation.pending_key_file());
+
+        assert_eq!(
+            rotation.new_private_key(),
+            loaded_rotation.new_private_key()
+        );
+        assert_eq!(
+            rotation.client_peers().len(),
+            loaded_rotation.client_peers().len()
+        );
+
+        assert_eq!(
+            format!("{}\n", rotation.new_private_key().expose_secret()),
+            key_file_text
+        );
+        assert_eq!(*rotation.new_public_key(), activated_pub_key.unwrap());
+        assert_eq!(
+            WgPrivateKeyRef::File(KEY_FILE.to_string()),
+            *wg_conf.interface().unwrap().private_key_ref()
+        );
+        assert!(!activated_text.contains(PENDING_PRIVATE_KEY_FILE));
+        assert!(!fs::exists(PENDING_KEY_FILE).unwrap());
+        assert!(wg_conf
+            .pending_key_rotation(
+                WgHost::Ip("127.0.0.1".parse().unwrap()),
+                server_allowed_ips()
+            )
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn activate_key_rotation_0_config_update_failed_0_key_file_is_kept() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation6.conf";
+        const TMP_CONF_FILE: &str = "wg_key_rotation6.conf.tmp";
+        const KEY_FILE: &str = "wg_key_rotation6.key";
+        const KEY: &str = "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=\n";
+        let content = CONTENT.replace(
+            "PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
+            &format!("PostUp = wg set %i private-key {KEY_FILE}"),
+        );
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let _key_cleanup = prepare_test_conf(KEY_FILE, KEY);
+        // tmp config file can't be created in place of the directory
+        fs::create_dir(TMP_CONF_FILE).unwrap();
+        let _tmp_cleanup = Deferred(Box::new(|| {
+            let _ = fs::remove_dir(TMP_CONF_FILE);
+        }));
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let res = wg_conf.rotate_key(
+            WgHost::Ip("127.0.0.1".parse().unwrap()),
+            server_allowed_ips(),
+        );
+
+        // Assert
+        assert!(res.is_err());
+        assert_eq!(KEY, fs::read_to_string(KEY_FILE).unwrap());
+        assert!(!fs::exists(format!("{KEY_FILE}.tmp")).unwrap());
+    }
+
+    #[test]
+    fn activate_key_rotation_0_key_changed_after_preparing_0_returns_validation_err() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation2.conf";
+        const PENDING_KEY_FILE: &str = "wg_key_rotation2.pending.key";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let _pending_key_cleanup = Deferred(Box::new(|| {
+            let _ = fs::remove_file(PENDING_KEY_FILE);
+        }));
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let (mut wg_conf, rotation) = wg_conf
+            .prepare_key_rotation(
+                WgHost::Ip("127.0.0.1".parse().unwrap()),
+                server_allowed_ips(),
+                PENDING_KEY_FILE,
+            )
+            .unwrap();
+
+        let mut interface = wg_conf.interface().unwrap();
+        interface
+            .set_private_key_ref(WgPrivateKeyRef::Inline(
+                WgKey::generate_private_key().unwrap(),
+            ))
+            .unwrap();
+        let wg_conf = wg_conf.update_interface(interface).unwrap();
+
+        // Act
+        let res = wg_conf.activate_key_rotation(&rotation);
+
+        // Assert
+        assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+    }
+
+    #[test]
+    fn rotate_preshared_keys_0_filtered_peers_0_rewrites_only_them() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation3.conf";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let server_public_key = wg_conf.pub_key().unwrap();
+        let client_public_key: WgPublicKey = "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
+            .parse()
+            .unwrap();
+        let before = SystemTime::now() - Duration::from_secs(1);
+
+        // Act
+        let res = wg_conf.rotate_preshared_keys(
+            |peer| peer.preshared_key().is_none(),
+            WgHost::Domain("vpn.example.com".to_string()),
+            server_allowed_ips(),
+        );
+
+        // Assert
+        let (mut wg_conf, rotations) = res.unwrap();
+        let text = fs::read_to_string(TEST_CONF_FILE).unwrap();
+        let mut peers = wg_conf.peers().unwrap();
+        let rotated_peer = peers.next().unwrap();
+        let other_peer = peers.next().unwrap();
+        drop(peers);
+
+        assert_eq!(1, rotations.len());
+        let rotation = &rotations[0];
+        assert_eq!(client_public_key, *rotation.public_key());
+        assert_eq!(Some(rotation.preshared_key()), rotated_peer.preshared_key());
+        assert!(rotated_peer.preshared_key_rotated_at().unwrap() > before);
+        assert!(other_peer.preshared_key_rotated_at().is_none());
+        assert!(text.ends_with(&CONTENT[CONTENT.rfind("[Peer]").unwrap()..]));
+
+        let client_peer = rotation.client_peer();
+        assert_eq!(server_public_key, *client_peer.public_key());
+        assert_eq!(Some(rotation.preshared_key()), client_peer.preshared_key());
+        assert_eq!(
+            "vpn.example.com:8080",
+            client_peer.endpoint().unwrap().to_string()
+        );
+        assert_eq!(None, client_peer.persistent_keepalive());
+    }
+
+    #[test]
+    fn rotate_preshared_key_0_absent_peer_0_returns_not_found_err() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation4.conf";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, CONTENT);
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let absent_public_key: WgPublicKey = "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+            .parse()
+            .unwrap();
+
+        // Act
+        let res = wg_conf.rotate_preshared_key(
+            &absent_public_key,
+            WgHost::Ip("127.0.0.1".parse().unwrap()),
+            server_allowed_ips(),
+        );
+
+        // Assert
+        let text = fs::read_to_string(TEST_CONF_FILE).unwrap();
+        assert_eq!(WgConfErrKind::NotFound, res.unwrap_err().kind());
+        assert_eq!(CONTENT, text);
+    }
+
+    #[test]
+    fn prepare_key_rotation_0_no_listen_port_0_returns_validation_err_without_files() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation8.conf";
+        const KEY_FILE: &str = "wg_key_rotation8.key";
+        const PENDING_KEY_FILE: &str = "wg_key_rotation8.pending.key";
+        const KEY: &str = "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=\n";
+        let content = CONTENT.replace("ListenPort = 8080\n", "").replace(
+            "PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
+            &format!("PostUp = wg set %i private-key {KEY_FILE}"),
+        );
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let _key_cleanup = prepare_test_conf(KEY_FILE, KEY);
+        let _pending_key_cleanup = Deferred(Box::new(|| {
+            let _ = fs::remove_file(PENDING_KEY_FILE);
+        }));
+        let server_host = || WgHost::Ip("127.0.0.1".parse().unwrap());
+
+        // Act
+        let prepare_res = WgConf::open(TEST_CONF_FILE).unwrap().prepare_key_rotation(
+            server_host(),
+            server_allowed_ips(),
+            PENDING_KEY_FILE,
+        );
+        let rotate_res = WgConf::open(TEST_CONF_FILE)
+            .unwrap()
+            .rotate_key(server_host(), server_allowed_ips());
+
+        // Assert
+        assert_eq!(
+            WgConfErrKind::ValidationFailed,
+            prepare_res.unwrap_err().kind()
+        );
+        assert_eq!(
+            WgConfErrKind::ValidationFailed,
+            rotate_res.unwrap_err().kind()
+        );
+        assert_eq!(content, fs::read_to_string(TEST_CONF_FILE).unwrap());
+        assert_eq!(KEY, fs::read_to_string(KEY_FILE).unwrap());
+        assert!(!fs::exists(PENDING_KEY_FILE).unwrap());
+        assert!(!fs::exists(format!("{KEY_FILE}.tmp")).unwrap());
+    }
+
+    #[test]
+    fn pending_key_rotation_0_no_listen_port_0_returns_validation_err() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation9.conf";
+        const PENDING_KEY_FILE: &str = "wg_key_rotation9.pending.key";
+        let content = CONTENT.replace("ListenPort = 8080\n", "").replace(
+            "[Interface]\n",
+            &format!("[Interface]\n# PendingPrivateKeyFile = {PENDING_KEY_FILE}\n"),
+        );
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let _pending_key_cleanup = prepare_test_conf(
+            PENDING_KEY_FILE,
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=\n",
+        );
+        let mut wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let res = wg_conf.pending_key_rotation(
+            WgHost::Ip("127.0.0.1".parse().unwrap()),
+            server_allowed_ips(),
+        );
+
+        // Assert
+        assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+    }
+
+    #[test]
+    fn rotate_preshared_keys_0_no_listen_port_0_returns_validation_err() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation7.conf";
+        let content = CONTENT.replace("ListenPort = 8080\n", "");
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+
+        // Act
+        let res = wg_conf.rotate_preshared_keys(
+            |_| true,
+            WgHost::Ip("127.0.0.1".parse().unwrap()),
+            server_allowed_ips(),
+        );
+
+        // Assert
+        let text = fs::read_to_string(TEST_CONF_FILE).unwrap();
+        assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+        assert_eq!(content, text);
+    }
+
+    #[test]
+    fn stale_preshared_keys_0_old_and_unknown_rotation_0_returns_them() {
+        // Arrange
+        const TEST_CONF_FILE: &str = "wg_key_rotation5.conf";
+        let content = CONTENT.to_string()
+            + "
+[Peer]
+# PresharedKeyRotatedAt = 1700000000
+PublicKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+AllowedIPs = 10.0.0.4/32
+PresharedKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+";
+        let _cleanup = prepare_test_conf(TEST_CONF_FILE, &content);
+        let wg_conf = WgConf::open(TEST_CONF_FILE).unwrap();
+        let client_public_key: WgPublicKey = "Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4="
+            .parse()
+            .unwrap();
+        let max_age = Duration::from_secs(90 * 24 * 60 * 60);
+
+        // Act
+        let mut stale_peers = vec![];
+        let (mut wg_conf, _) = wg_conf
+            .rotate_preshared_key(
+                &client_public_key,
+                WgHost::Ip("127.0.0.1".parse().unwrap()),
+                server_allowed_ips(),
+            )
+            .unwrap();
+        stale_peers.push(wg_conf.stale_preshared_keys(max_age));
+        let (mut wg_conf, _) = wg_conf
+            .rotate_preshared_keys(
+                |_| true,
+                WgHost::Ip("127.0.0.1".parse().unwrap()),
+                server_allowed_ips(),
+            )
+            .unwrap();
+        stale_peers.push(wg_conf.stale_preshared_keys(max_age));
+
+        // Assert
+        let stale_public_keys: Vec<Vec<String>> = stale_peers
+            .into_iter()
+            .map(|peers| {
+                peers
+                    .unwrap()
+                    .iter()
+                    .map(|peer| peer.public_key().to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                vec!["6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string()],
+                vec![]
+            ],
+            stale_public_keys
+        );
+    }
+}
@@ -0,0 +1,485 @@
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+};
+
+use crate::{
+    wg_conf, wg_interface, wg_parse::section_name, wg_peer, WgConfErrLocation, WgConfError,
+    WgDocument, WgDocumentLineKind, WgDocumentSection, WgInterface, WgParseOptions, WgParseWarning,
+    WgPeer, WgPublicKey,
+};
+
+/// In-memory WG configuration which is parsed from a string or any reader
+///
+/// It's the file-free equivalent of [`crate::WgConf`]: sections are edited in place,
+/// so comments, unknown lines and line endings of the original text are kept on serialization
+#[derive(Debug, Clone)]
+pub struct WgMemConf {
+    document: WgDocument,
+    interface: WgInterface,
+    peers: Vec<WgPeer>,
+    options: WgParseOptions,
+    warnings: Vec<WgParseWarning>,
+}
+
+impl Display for WgMemConf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+impl WgMemConf {
+    /// Creates new [`WgMemConf`] from interface and peers
+    pub fn new(interface: WgInterface, peers: Vec<WgPeer>) -> Result<WgMemConf, WgConfError> {
+        if interface.listen_port.is_none() {
+            return Err(WgConfError::ValidationFailed(
+                "Listen port must be set for server config".to_string(),
+            ));
+        }
+
+        let mut text = interface.to_string() + "\n";
+        for peer in peers.iter() {
+            text.push_str(&(peer.to_string() + "\n"));
+        }
+
+        WgMemConf::parse(&text)
+    }
+
+    /// Parses [`WgMemConf`] from the text using [`WgParseMode::Lenient`](crate::WgParseMode::Lenient) parsing mode
+    ///
+    /// Returns [`WgConfError::NotWgConfig`] if the text is not WG config or located validation error
+    /// of the first invalid value
+    pub fn parse(text: &str) -> Result<WgMemConf, WgConfError> {
+        WgMemConf::parse_with_options(text, WgParseOptions::default())
+    }
+
+    /// Parses [`WgMemConf`] from the text using provided parse options
+    ///
+    /// In strict mode the first deviation from the canonical format is returned as [`WgConfError::NotWgConfig`],
+    /// in lenient mode the tolerated deviations are available with [`WgMemConf::warnings()`]
+    pub fn parse_with_options(
+        text: &str,
+        options: WgParseOptions,
+    ) -> Result<WgMemConf, WgConfError> {
+        let warnings = options.check_structure(None, text)?;
+        let document = WgDocument::parse(text);
+
+        let mut sections = document.sections().iter();
+        let mut line_no = document.preamble().len();
+
+        let interface_section = sections.next().ok_or(WgConfError::NotWgConfig(
+            "couldn't find [Interface] section".to_string(),
+        ))?;
+        line_no += 1;
+        let interface = interface_from_section(interface_section, line_no)?;
+        line_no += interface_section.lines().len();
+
+        let mut peers: Vec<WgPeer> = vec![];
+        for peer_section in sections {
+            line_no += 1;
+            if let Some(peer) = peer_from_section(peer_section, line_no)? {
+                peers.push(peer);
+            }
+            line_no += peer_section.lines().len();
+        }
+
+        Ok(WgMemConf {
+            document,
+            interface,
+            peers,
+            options,
+            warnings,
+        })
+    }
+
+    /// Reads the whole `reader` and parses [`WgMemConf`] from it using [`WgParseMode::Lenient`](crate::WgParseMode::Lenient) parsing mode
+    pub fn from_reader<R: Read>(reader: R) -> Result<WgMemConf, WgConfError> {
+        WgMemConf::from_reader_with_options(reader, WgParseOptions::default())
+    }
+
+    /// Reads the whole `reader` and parses [`WgMemConf`] from it using provided parse options
+    pub fn from_reader_with_options<R: Read>(
+        mut reader: R,
+        options: WgParseOptions,
+    ) -> Result<WgMemConf, WgConfError> {
+        let mut text = String::new();
+        reader
+            .read_to_string(&mut text)
+            .map_err(|err| WgConfError::Unexpected(format!("Couldn't read WG config: {err}")))?;
+
+        WgMemConf::parse_with_options(&text, options)
+    }
+
+    /// Writes the config into `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), WgConfError> {
+        writer
+            .write_all(self.to_string().as_bytes())
+            .map_err(|err| WgConfError::Unexpected(format!("Couldn't write WG config: {err}")))
+    }
+
+    /// Same as `to_string()`, but private and preshared keys are replaced by [`REDACTED`](crate::REDACTED),
+    /// so the config is safe to log
+    pub fn to_string_redacted(&self) -> String {
+        self.document.to_string_redacted()
+    }
+
+    // getters
+    pub fn interface(&self) -> &WgInterface {
+        &self.interface
+    }
+    pub fn peers(&self) -> &[WgPeer] {
+        &self.peers
+    }
+    pub fn options(&self) -> &WgParseOptions {
+        &self.options
+    }
+    /// Returns deviations from the canonical format which were tolerated when the config was parsed
+    pub fn warnings(&self) -> &[WgParseWarning] {
+        &self.warnings
+    }
+
+    /// Returns \[Peer\] with provided public key
+    pub fn peer(&self, public_key: &WgPublicKey) -> Option<&WgPeer> {
+        self.peers.iter().find(|p| *p.public_key() == *public_key)
+    }
+
+    /// Updates \[Interface\] section
+    ///
+    /// The section is updated in place: only the changed lines are rewritten,
+    /// comments and the other lines of the section are kept
+    pub fn update_interface(&mut self, new_interface: WgInterface) -> Result<(), WgConfError> {
+        if self.interface == new_interface {
+            return Ok(());
+        }
+
+        let interface_section =
+            self.document
+                .sections_mut()
+                .first_mut()
+                .ok_or(WgConfError::NotWgConfig(
+                    "couldn't find [Interface] section".to_string(),
+                ))?;
+        interface_section
+            .sync_all_key_values(&new_interface.to_raw_key_values(), wg_interface::LIST_KEYS);
+
+        self.interface = new_interface;
+
+        Ok(())
+    }
+
+    /// Adds \[Peer\] to the end of the config
+    pub fn add_peer(&mut self, peer: &WgPeer) -> Result<(), WgConfError> {
+        if self.peer(&peer.public_key).is_some() {
+            return Err(WgConfError::AlreadyExists(format!(
+                "Peer with public key '{}'",
+                peer.public_key.to_string()
+            )));
+        }
+
+        self.document.append(&(peer.to_string() + "\n"));
+        self.peers.push(peer.clone());
+
+        Ok(())
+    }
+
+    /// Updates \[Peer\] with the same public key
+    ///
+    /// The peer is updated in place: only the changed lines are rewritten,
+    /// comments and the other lines of the section are kept
+    pub fn update_peer(&mut self, peer: &WgPeer) -> Result<(), WgConfError> {
+        let peer_index = self.peer_index(&peer.public_key)?;
+        if self.peers[peer_index] == *peer {
+            return Ok(());
+        }
+
+        let section_index = self.peer_section_index(&peer.public_key)?;
+        let peer_section = &mut self.document.sections_mut()[section_index];
+        peer_section.sync_all_key_values(&peer.to_raw_key_values(), wg_peer::LIST_KEYS);
+        peer_section.sync_comment_key_values(&peer.to_raw_metadata(), wg_peer::is_metadata_key);
+
+        self.peers[peer_index] = peer.clone();
+
+        Ok(())
+    }
+
+    /// Removes \[Peer\] with provided public key
+    pub fn remove_peer_by_pub_key(&mut self, public_key: &WgPublicKey) -> Result<(), WgConfError> {
+        let peer_index = self.peer_index(public_key)?;
+        let section_index = self.peer_section_index(public_key)?;
+
+        let _ = self.document.remove_section(section_index);
+        self.peers.remove(peer_index);
+
+        Ok(())
+    }
+
+    fn peer_index(&self, public_key: &WgPublicKey) -> Result<usize, WgConfError> {
+        self.peers
+            .iter()
+            .position(|p| *p.public_key() == *public_key)
+            .ok_or(WgConfError::NotFound(format!(
+                "Peer with public key '{}'",
+                public_key.to_string()
+            )))
+    }
+
+    fn peer_section_index(&self, public_key: &WgPublicKey) -> Result<usize, WgConfError> {
+        self.document
+            .sections()
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find(|(_, section)| {
+                section.get(wg_peer::PUBLIC_KEY).is_some_and(|key| {
+                    key.parse::<WgPublicKey>()
+                        .is_ok_and(|key| key == *public_key)
+                })
+            })
+            .map(|(i, _)| i)
+            .ok_or(WgConfError::Unexpected(
+                "Couldn't find target peer section".to_string(),
+            ))
+    }
+}
+
+/// Parses \[Interface\] from the document section, `line_no` is the line of the section tag
+fn interface_from_section(
+    section: &WgDocumentSection,
+    line_no: usize,
+) -> Result<WgInterface, WgConfError> {
+    let raw_key_values = section_key_values(
+        section,
+        line_no,
+        wg_interface::INTERFACE_TAG,
+        wg_interface::KEYS,
+        wg_interface::validate_raw_key_value,
+    )?;
+
+    WgInterface::from_raw_key_values(raw_key_values).map_err(|err| {
+        err.with_location(tag_location(section, line_no, wg_interface::INTERFACE_TAG))
+    })
+}
+
+/// Parses \[Peer\] from the document section, `line_no` is the line of the section tag
+///
+/// Returns `None` for the section without key-values
+fn peer_from_section(
+    section: &WgDocumentSection,
+    line_no: usize,
+) -> Result<Option<WgPeer>, WgConfError> {
+    let raw_key_values = section_key_values(
+        section,
+        line_no,
+        wg_peer::PEER_TAG,
+        wg_peer::KEYS,
+        wg_peer::validate_raw_key_value,
+    )?;
+    if raw_key_values.is_empty() {
+        return Ok(None);
+    }
+
+    let mut peer = WgPeer::from_raw_key_values(raw_key_values)
+        .map_err(|err| err.with_location(tag_location(section, line_no, wg_peer::PEER_TAG)))?;
+
+    let raw_metadata = section
+        .lines()
+        .iter()
+        .filter_map(|line| line.comment_key_value())
+        .filter(|(k, _)| wg_peer::is_metadata_key(k))
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect();
+    peer.set_raw_metadata(raw_metadata);
+
+    Ok(Some(peer))
+}
+
+/// Returns validated key-values of the section, known keys are returned in the known spelling
+fn section_key_values(
+    section: &WgDocumentSection,
+    line_no: usize,
+    tag: &str,
+    known_keys: &[&str],
+    validate: fn(&str, &str) -> Result<(), WgConfError>,
+) -> Result<Vec<(String, String)>, WgConfError> {
+    let mut raw_key_values: Vec<(String, String)> = Vec::with_capacity(section.lines().len());
+
+    for (i, line) in section.lines().iter().enumerate() {
+        match line.kind() {
+            WgDocumentLineKind::Blank | WgDocumentLineKind::Comment => continue,
+            _ => {}
+        }
+
+        let location = WgConfErrLocation::new(None, line_no + i + 1, line.raw(), section_name(tag));
+        raw_key_values.push(wg_conf::parse_key_value_line(
+            line.raw(),
+            location,
+            known_keys,
+            validate,
+        )?);
+    }
+
+    Ok(raw_key_values)
+}
+
+/// Location of the section tag line
+fn tag_location(section: &WgDocumentSection, line_no: usize, tag: &str) -> WgConfErrLocation {
+    WgConfErrLocation::new(None, line_no, section.header().raw(), section_name(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WgConfErrKind;
+    use ipnetwork::IpNetwork;
+
+    const CONTENT: &'static str = "# managed by ops
+[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+# keep the table
+Table = off
+
+[Peer]
+# Name = laptop
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.2/32
+
+[Peer]
+PublicKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+AllowedIPs = 10.0.0.3/32
+PersistentKeepalive = 25
+";
+
+    #[test]
+    fn parse_0_common_scenario() {
+        // Act
+        let conf = WgMemConf::parse(CONTENT).unwrap();
+
+        // Assert
+        assert_eq!(Some(8080), conf.interface().listen_port());
+        assert_eq!(2, conf.peers().len());
+        assert_eq!(Some("laptop"), conf.peers()[0].name());
+        assert_eq!(
+            vec!["10.0.0.3/32".parse::<IpNetwork>().unwrap()],
+            conf.peers()[1].allowed_ips()
+        );
+        assert_eq!(CONTENT, conf.to_string());
+    }
+
+    #[test]
+    fn parse_0_invalid_value_0_returns_located_err() {
+        // Arrange
+        let content = CONTENT.replace("PersistentKeepalive = 25", "PersistentKeepalive = abc");
+
+        // Act
+        let err = WgMemConf::parse(&content).unwrap_err();
+
+        // Assert
+        assert_eq!(WgConfErrKind::ValidationFailed, err.kind());
+        let location = err.location().unwrap();
+        assert_eq!(None, location.file());
+        assert_eq!(Some(17), location.line());
+        assert_eq!(Some("PersistentKeepalive"), location.key());
+    }
+
+    #[test]
+    fn parse_0_invalid_mtu_0_returns_err_located_at_mtu_line() {
+        // Arrange
+        let content = CONTENT.replace("ListenPort = 8080\n", "ListenPort = 8080\nMTU = 70000\n");
+
+        // Act
+        let err = WgMemConf::parse(&content).unwrap_err();
+
+        // Assert
+        assert_eq!(WgConfErrKind::ValidationFailed, err.kind());
+        let location = err.location().unwrap();
+        assert_eq!(Some(6), location.line());
+        assert_eq!(Some("MTU"), location.key());
+    }
+
+    #[test]
+    fn edit_peers_0_keeps_comments_and_unchanged_lines() {
+        // Arrange
+        let mut conf = WgMemConf::from_reader(CONTENT.as_bytes()).unwrap();
+        let mut updated_peer = conf.peers()[0].clone();
+        updated_peer.allowed_ips = vec!["10.0.0.5/32".parse().unwrap()];
+        let new_peer = WgPeer::new(
+            "2VHsXWz1BrvPjOCk4DGm7SUoVq/nsq/g6Hov0Q5GG3o="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.6/32".parse().unwrap()],
+            None,
+            None,
+            None,
+        );
+
+        // Act
+        conf.update_peer(&updated_peer).unwrap();
+        conf.remove_peer_by_pub_key(
+            &"Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4="
+                .parse()
+                .unwrap(),
+        )
+        .unwrap();
+        conf.add_peer(&new_peer).unwrap();
+        let mut written: Vec<u8> = vec![];
+        conf.write_to(&mut written).unwrap();
+
+        // Assert
+        let expected = "# managed by ops
+[Interface]
+PrivateKey = 4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=
+Address = 10.0.0.1/24
+ListenPort = 8080
+# keep the table
+Table = off
+
+[Peer]
+# Name = laptop
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.5/32
+
+[Peer]
+PublicKey = 2VHsXWz1BrvPjOCk4DGm7SUoVq/nsq/g6Hov0Q5GG3o=
+AllowedIPs = 10.0.0.6/32
+
+";
+        assert_eq!(expected, String::from_utf8(written).unwrap());
+        assert_eq!(2, conf.peers().len());
+        assert_eq!(expected, WgMemConf::parse(expected).unwrap().to_string());
+    }
+
+    #[test]
+    fn add_peer_0_existing_0_returns_already_exists_err() {
+        // Arrange
+        let mut conf = WgMemConf::parse(CONTENT).unwrap();
+        let existing_peer = conf.peers()[1].clone();
+
+        // Act
+        let res = conf.add_peer(&existing_peer);
+
+        // Assert
+        assert_eq!(WgConfErrKind::AlreadyExists, res.unwrap_err().kind());
+        assert_eq!(CONTENT, conf.to_string());
+    }
+
+    #[test]
+    fn update_interface_0_crlf_0_keeps_line_endings() {
+        // Arrange
+        let content = CONTENT.replace('\n', "\r\n");
+        let mut conf = WgMemConf::parse(&content).unwrap();
+        let mut interface = conf.interface().clone();
+        interface.listen_port = Some(8081);
+
+        // Act
+        conf.update_interface(interface).unwrap();
+
+        // Assert
+        assert_eq!(
+            content.replace("ListenPort = 8080", "ListenPort = 8081"),
+            conf.to_string()
+        );
+        assert_eq!(Some(8081), conf.interface().listen_port());
+    }
+}
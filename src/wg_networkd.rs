@@ -0,0 +1,571 @@
+use ipnetwork::IpNetwork;
+
+use crate::{
+    wg_parse::{parse_number, validate_interface_name},
+    wg_secret::read_key_file,
+    WgClientConf, WgConf, WgConfError, WgDns, WgDocument, WgDocumentSection, WgEndpoint,
+    WgInterface, WgPeer, WgPrivateKeyRef, WgPublicKey, WgTable,
+};
+
+// Sections
+const NETDEV: &'static str = "NetDev";
+const WIREGUARD: &'static str = "WireGuard";
+const WIREGUARD_PEER: &'static str = "WireGuardPeer";
+const MATCH: &'static str = "Match";
+const NETWORK: &'static str = "Network";
+const ADDRESS_SECTION: &'static str = "Address";
+const ROUTE: &'static str = "Route";
+
+// Keys
+const NAME: &'static str = "Name";
+const KIND: &'static str = "Kind";
+const MTU_BYTES: &'static str = "MTUBytes";
+const PRIVATE_KEY: &'static str = "PrivateKey";
+const PRIVATE_KEY_FILE: &'static str = "PrivateKeyFile";
+const LISTEN_PORT: &'static str = "ListenPort";
+const FIREWALL_MARK: &'static str = "FirewallMark";
+const PUBLIC_KEY: &'static str = "PublicKey";
+const PRESHARED_KEY: &'static str = "PresharedKey";
+const PRESHARED_KEY_FILE: &'static str = "PresharedKeyFile";
+const ALLOWED_IPS: &'static str = "AllowedIPs";
+const ENDPOINT: &'static str = "Endpoint";
+const PERSISTENT_KEEPALIVE: &'static str = "PersistentKeepalive";
+const ADDRESS: &'static str = "Address";
+const DNS: &'static str = "DNS";
+const DOMAINS: &'static str = "Domains";
+const DESTINATION: &'static str = "Destination";
+const TABLE: &'static str = "Table";
+
+// Values
+const WIREGUARD_KIND: &'static str = "wireguard";
+const AUTO: &'static str = "auto";
+const OFF: &'static str = "off";
+/// Prefix of routing-only domain in `Domains=`
+const ROUTING_DOMAIN_PREFIX: char = '~';
+
+/// Where generated .netdev file takes the interface private key from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WgNetworkdPrivateKey {
+    /// The key is written into .netdev file (`PrivateKey=`), interface key file reference
+    /// ([`WgPrivateKeyRef::File`]) is written as `PrivateKeyFile=`
+    Inline,
+    /// The key is read by systemd-networkd from the provided file (`PrivateKeyFile=`)
+    File(String),
+}
+
+/// Pair of systemd-networkd units of WG interface: `.netdev` and `.network` files' content
+///
+/// **Note**, that .netdev file contains preshared keys and inline private key, so it should be readable
+/// by root and systemd-network group only (0640)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgNetworkdUnits {
+    pub(crate) netdev: String,
+    pub(crate) network: String,
+}
+
+impl WgNetworkdUnits {
+    /// Creates [`WgNetworkdUnits`] from existing `.netdev` and `.network` files' content
+    pub fn new(netdev: String, network: String) -> WgNetworkdUnits {
+        WgNetworkdUnits { netdev, network }
+    }
+
+    /// Converts the units back to [`WgInterface`]
+    ///
+    /// `PrivateKeyFile=` is kept as [`WgPrivateKeyRef::File`], routing-only domains (`~example.com`) are skipped, Table is taken from `[Route]` sections.
+    /// Returns [`WgConfError::NotWgConfig`] if .netdev file is not WireGuard one
+    pub fn interface(&self) -> Result<WgInterface, WgConfError> {
+        let netdev = WgDocument::parse(&self.netdev);
+        let network = WgDocument::parse(&self.network);
+
+        let netdev_section = netdev.sections_by_name(NETDEV).next();
+        if netdev_section.and_then(|section| section.get(KIND)) != Some(WIREGUARD_KIND) {
+            return Err(WgConfError::NotWgConfig(
+                "netdev is not WireGuard one".to_string(),
+            ));
+        }
+        let wireguard =
+            netdev
+                .sections_by_name(WIREGUARD)
+                .next()
+                .ok_or(WgConfError::NotWgConfig(
+                    "couldn't find [WireGuard] section".to_string(),
+                ))?;
+
+        let private_key = match (wireguard.get(PRIVATE_KEY), wireguard.get(PRIVATE_KEY_FILE)) {
+            (Some(private_key), _) => WgPrivateKeyRef::Inline(private_key.parse()?),
+            (None, Some(file_name)) => WgPrivateKeyRef::File(file_name.to_string()),
+            (None, None) => {
+                return Err(WgConfError::ValidationFailed(
+                    "netdev has neither PrivateKey nor PrivateKeyFile".to_string(),
+                ))
+            }
+        };
+
+        let listen_port = match wireguard.get(LISTEN_PORT) {
+            Some(AUTO) | None => None,
+            Some(port) => Some(parse_number(LISTEN_PORT, port)?),
+        };
+
+        let mut addresses: Vec<IpNetwork> = vec![];
+        let mut dns: Vec<WgDns> = vec![];
+        for section in network.sections() {
+            if section.name() == NETWORK {
+                addresses.extend(parse_ip_networks(&section_values(section, ADDRESS))?);
+                for resolver in section_values(section, DNS) {
+                    dns.push(resolver.parse()?);
+                }
+                for domain in section_values(section, DOMAINS) {
+                    if !domain.starts_with(ROUTING_DOMAIN_PREFIX) {
+                        dns.push(domain.parse()?);
+                    }
+                }
+            } else if section.name() == ADDRESS_SECTION {
+                addresses.extend(parse_ip_networks(&section_values(section, ADDRESS))?);
+            }
+        }
+
+        let mut interface = WgInterface::with_private_key_ref(
+            private_key,
+            addresses,
+            listen_port,
+            dns,
+            vec![],
+            vec![],
+        )?;
+        interface.set_mtu(
+            netdev_section
+                .and_then(|section| section.get(MTU_BYTES))
+                .map(|mtu| parse_number(MTU_BYTES, mtu))
+                .transpose()?,
+        )?;
+        interface.set_fw_mark(
+            wireguard
+                .get(FIREWALL_MARK)
+                .map(|fw_mark| parse_number(FIREWALL_MARK, fw_mark))
+                .transpose()?
+                .filter(|fw_mark| *fw_mark != 0),
+        );
+        interface.set_table(
+            network
+                .sections_by_name(ROUTE)
+                .find_map(|section| section.get(TABLE))
+                .map(|table| table.parse())
+                .transpose()?,
+        );
+
+        Ok(interface)
+    }
+
+    /// Converts `[WireGuardPeer]` sections of .netdev file back to [`WgPeer`]s, `PresharedKeyFile=` is read
+    pub fn peers(&self) -> Result<Vec<WgPeer>, WgConfError> {
+        WgDocument::parse(&self.netdev)
+            .sections_by_name(WIREGUARD_PEER)
+            .map(peer_from_section)
+            .collect()
+    }
+
+    // getters
+    pub fn netdev(&self) -> &str {
+        &self.netdev
+    }
+    pub fn network(&self) -> &str {
+        &self.network
+    }
+}
+
+impl WgConf {
+    /// Renders systemd-networkd units of the interface with all the peers,
+    /// see [`WgClientConf::to_networkd`]
+    pub fn to_networkd(
+        &mut self,
+        interface_name: &str,
+        private_key: WgNetworkdPrivateKey,
+    ) -> Result<WgNetworkdUnits, WgConfError> {
+        let interface = self.interface()?;
+
+        let mut peers_iter = self.peers()?;
+        let peers: Vec<WgPeer> = peers_iter.by_ref().collect();
+        peers_iter.check_err()?;
+
+        to_networkd(interface_name, &private_key, &interface, &peers)
+    }
+}
+
+impl WgClientConf {
+    /// Renders systemd-networkd units of the interface: `.netdev` file with `[WireGuard]` and
+    /// `[WireGuardPeer]` sections and `.network` file with addresses, DNS and routes to the peers' allowed IPs
+    ///
+    /// Routes are not rendered if Table is off and for allowed IPs which are inside the interface addresses
+    /// (they are reachable without extra routes), Table id or name is set to every route. Hook commands,
+    /// SaveConfig and unknown keys can't be represented in networkd units, so they are not rendered.
+    ///
+    /// **Note**, that a default route (e.g. `0.0.0.0/0`) to the peer is rendered as is. Unlike wg-quick,
+    /// networkd doesn't add fwmark-based policy rules, so the route to the peer endpoint must be kept outside the tunnel
+    pub fn to_networkd(
+        &self,
+        interface_name: &str,
+        private_key: WgNetworkdPrivateKey,
+    ) -> Result<WgNetworkdUnits, WgConfError> {
+        to_networkd(interface_name, &private_key, self.interface(), self.peers())
+    }
+
+    /// Creates [`WgClientConf`] from systemd-networkd units, see [`WgNetworkdUnits::interface`]
+    pub fn from_networkd(units: &WgNetworkdUnits) -> Result<WgClientConf, WgConfError> {
+        Ok(WgClientConf::new(units.interface()?, units.peers()?))
+    }
+}
+
+fn to_networkd(
+    interface_name: &str,
+    private_key: &WgNetworkdPrivateKey,
+    interface: &WgInterface,
+    peers: &[WgPeer],
+) -> Result<WgNetworkdUnits, WgConfError> {
+    validate_interface_name(interface_name)?;
+
+    Ok(WgNetworkdUnits {
+        netdev: netdev(interface_name, private_key, interface, peers)?,
+        network: network(interface_name, interface, peers),
+    })
+}
+
+fn netdev(
+    interface_name: &str,
+    private_key: &WgNetworkdPrivateKey,
+    interface: &WgInterface,
+    peers: &[WgPeer],
+) -> Result<String, WgConfError> {
+    let mut raw = section(NETDEV);
+    raw += &key_value(NAME, interface_name);
+    raw += &key_value(KIND, WIREGUARD_KIND);
+    if let Some(mtu) = interface.mtu() {
+        raw += &key_value(MTU_BYTES, &mtu.to_string());
+    }
+
+    raw += "\n";
+    raw += &section(WIREGUARD);
+    match (private_key, interface.private_key_ref()) {
+        (WgNetworkdPrivateKey::File(file_name), _)
+        | (WgNetworkdPrivateKey::Inline, WgPrivateKeyRef::File(file_name)) => {
+            raw += &key_value(PRIVATE_KEY_FILE, file_name)
+        }
+        (WgNetworkdPrivateKey::Inline, WgPrivateKeyRef::Inline(private_key)) => {
+            raw += &key_value(PRIVATE_KEY, private_key.expose_secret())
+        }
+        (WgNetworkdPrivateKey::Inline, key_ref) => {
+            return Err(WgConfError::ValidationFailed(format!(
+                "private key {key_ref:?} can't be written into .netdev, use key file instead"
+            )))
+        }
+    }
+    if let Some(listen_port) = interface.listen_port() {
+        raw += &key_value(LISTEN_PORT, &listen_port.to_string());
+    }
+    if let Some(fw_mark) = interface.fw_mark() {
+        raw += &key_value(FIREWALL_MARK, &fw_mark.to_string());
+    }
+
+    for peer in peers {
+        raw += "\n";
+        raw += &section(WIREGUARD_PEER);
+        raw += &key_value(PUBLIC_KEY, &peer.public_key().to_string());
+        if let Some(preshared_key) = peer.preshared_key() {
+            raw += &key_value(PRESHARED_KEY, preshared_key.expose_secret());
+        }
+        if !peer.allowed_ips().is_empty() {
+            raw += &key_value(ALLOWED_IPS, &join(peer.allowed_ips(), ","));
+        }
+        if let Some(endpoint) = peer.endpoint() {
+            raw += &key_value(ENDPOINT, &endpoint.to_string());
+        }
+        if let Some(persistent_keepalive) = peer.persistent_keepalive() {
+            raw += &key_value(PERSISTENT_KEEPALIVE, &persistent_keepalive.to_string());
+        }
+    }
+
+    Ok(raw)
+}
+
+fn network(interface_name: &str, interface: &WgInterface, peers: &[WgPeer]) -> String {
+    let mut raw = section(MATCH);
+    raw += &key_value(NAME, interface_name);
+
+    raw += "\n";
+    raw += &section(NETWORK);
+    for address in interface.addresses() {
+        raw += &key_value(ADDRESS, &address.to_string());
+    }
+    let resolvers: Vec<&WgDns> = interface
+        .dns()
+        .iter()
+        .filter(|dns| matches!(dns, WgDns::Ip(_)))
+        .collect();
+    if !resolvers.is_empty() {
+        raw += &key_value(DNS, &join(&resolvers, " "));
+    }
+    let search_domains: Vec<&WgDns> = interface
+        .dns()
+        .iter()
+        .filter(|dns| matches!(dns, WgDns::Domain(_)))
+        .collect();
+    if !search_domains.is_empty() {
+        raw += &key_value(DOMAINS, &join(&search_domains, " "));
+    }
+
+    let table = match interface.table() {
+        Some(WgTable::Off) => return raw,
+        Some(WgTable::Id(id)) => Some(id.to_string()),
+        Some(WgTable::Name(name)) => Some(name.to_owned()),
+        Some(WgTable::Auto) | None => None,
+    };
+
+    for destination in route_destinations(interface, peers) {
+        raw += "\n";
+        raw += &section(ROUTE);
+        raw += &key_value(DESTINATION, &destination.to_string());
+        if let Some(table) = &table {
+            raw += &key_value(TABLE, table);
+        }
+    }
+
+    raw
+}
+
+/// Returns unique peers' allowed IPs which are outside the interface addresses, so they need routes
+pub(crate) fn route_destinations<'a>(
+    interface: &WgInterface,
+    peers: &'a [WgPeer],
+) -> Vec<&'a IpNetwork> {
+    let mut destinations: Vec<&IpNetwork> = vec![];
+    for allowed_ip in peers.iter().flat_map(|peer| peer.allowed_ips()) {
+        let is_connected = interface.addresses().iter().any(|address| {
+            address.prefix() <= allowed_ip.prefix() && address.contains(allowed_ip.ip())
+        });
+        if !is_connected && !destinations.contains(&allowed_ip) {
+            destinations.push(allowed_ip);
+        }
+    }
+
+    destinations
+}
+
+fn peer_from_section(section: &WgDocumentSection) -> Result<WgPeer, WgConfError> {
+    let public_key: WgPublicKey = section
+        .get(PUBLIC_KEY)
+        .ok_or(WgConfError::ValidationFailed(
+            "[WireGuardPeer] has no PublicKey".to_string(),
+        ))?
+        .parse()?;
+
+    let preshared_key = match (section.get(PRESHARED_KEY), section.get(PRESHARED_KEY_FILE)) {
+        (Some(preshared_key), _) => Some(preshared_key.parse()?),
+        (None, Some(file_name)) => Some(read_key_file(file_name)?),
+        (None, None) => None,
+    };
+    let endpoint: Option<WgEndpoint> = section
+        .get(ENDPOINT)
+        .map(|endpoint| endpoint.parse())
+        .transpose()?;
+    let persistent_keepalive = match section.get(PERSISTENT_KEEPALIVE) {
+        Some(OFF) | None => None,
+        Some(keepalive) => {
+            Some(parse_number(PERSISTENT_KEEPALIVE, keepalive)?).filter(|keepalive| *keepalive != 0)
+        }
+    };
+
+    Ok(WgPeer::new(
+        public_key,
+        parse_ip_networks(&section_values(section, ALLOWED_IPS))?,
+        endpoint,
+        preshared_key,
+        persistent_keepalive,
+    ))
+}
+
+/// Returns values of all the lines with the key, networkd lists are separated with whitespaces or commas
+fn section_values(section: &WgDocumentSection, key: &str) -> Vec<String> {
+    section
+        .key_values()
+        .into_iter()
+        .filter(|(k, _)| k == key)
+        .flat_map(|(_, v)| {
+            v.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|item| !item.is_empty())
+                .map(|item| item.to_owned())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+fn parse_ip_networks(raw: &[String]) -> Result<Vec<IpNetwork>, WgConfError> {
+    raw.iter()
+        .map(|ip| {
+            ip.parse()
+                .map_err(|_| WgConfError::ValidationFailed(format!("invalid ip network '{ip}'")))
+        })
+        .collect()
+}
+
+/// Reads base64 key from the file like systemd-networkd does, surrounding whitespaces are trimmed
+fn section(name: &str) -> String {
+    format!("[{name}]\n")
+}
+
+fn key_value(key: &str, value: &str) -> String {
+    format!("{key}={value}\n")
+}
+
+fn join<T: ToString>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{wg_conf::tests::prepare_test_conf, WgConfErrKind};
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/24, fd00::2/64
+DNS = 10.0.0.1, example.com
+MTU = 1420
+Table = 1000
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.0/24, 192.168.1.0/24, fd00::/64
+Endpoint = vpn.example.com:51820
+PresharedKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+PersistentKeepalive = 25
+";
+
+    const NETDEV_CONTENT: &'static str = "[NetDev]
+Name=wg0
+Kind=wireguard
+MTUBytes=1420
+
+[WireGuard]
+PrivateKey=6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+
+[WireGuardPeer]
+PublicKey=LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+PresharedKey=Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+AllowedIPs=10.0.0.0/24,192.168.1.0/24,fd00::/64
+Endpoint=vpn.example.com:51820
+PersistentKeepalive=25
+";
+
+    const NETWORK_CONTENT: &'static str = "[Match]
+Name=wg0
+
+[Network]
+Address=10.0.0.2/24
+Address=fd00::2/64
+DNS=10.0.0.1
+Domains=example.com
+
+[Route]
+Destination=192.168.1.0/24
+Table=1000
+";
+
+    #[test]
+    fn to_networkd_0_client_conf_0_netdev_and_network_with_routes() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let units = client_conf
+            .to_networkd("wg0", WgNetworkdPrivateKey::Inline)
+            .unwrap();
+
+        // Assert
+        assert_eq!(NETDEV_CONTENT, units.netdev());
+        assert_eq!(NETWORK_CONTENT, units.network());
+    }
+
+    #[test]
+    fn from_networkd_0_rendered_units_0_same_conf() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+        let units = client_conf
+            .to_networkd("wg0", WgNetworkdPrivateKey::Inline)
+            .unwrap();
+
+        // Act
+        let from_networkd = WgClientConf::from_networkd(&units);
+
+        // Assert
+        assert_eq!(client_conf, from_networkd.unwrap());
+    }
+
+    #[test]
+    fn interface_0_private_key_file_and_networkd_lists_0_keeps_key_file_and_reads_values() {
+        // Arrange
+        const KEY_FILE: &str = "wg_networkd0.key";
+        let _key_file =
+            prepare_test_conf(KEY_FILE, "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=\n");
+        let netdev = NETDEV_CONTENT.replace(
+            "PrivateKey=6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=",
+            &format!("PrivateKeyFile={KEY_FILE}\nListenPort=auto"),
+        );
+        let network = "[Match]
+Name=wg0
+
+[Network]
+DNS=10.0.0.1 1.1.1.1
+Domains=example.com ~corp.example.com
+
+[Address]
+Address=10.0.0.2/24
+";
+        let units = WgNetworkdUnits::new(netdev, network.to_string());
+
+        // Act
+        let interface = units.interface();
+
+        // Assert
+        let resolved_key = interface.as_ref().unwrap().resolve_private_key(None);
+        let interface = interface.unwrap();
+        assert_eq!(
+            &WgPrivateKeyRef::File(KEY_FILE.to_string()),
+            interface.private_key_ref()
+        );
+        assert_eq!(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=",
+            resolved_key.unwrap().expose_secret()
+        );
+        assert_eq!(None, interface.listen_port());
+        assert_eq!(
+            &vec!["10.0.0.2/24".parse::<IpNetwork>().unwrap()],
+            interface.addresses()
+        );
+        assert_eq!(
+            &vec![
+                "10.0.0.1".parse::<WgDns>().unwrap(),
+                "1.1.1.1".parse().unwrap(),
+                "example.com".parse().unwrap()
+            ],
+            interface.dns()
+        );
+    }
+
+    #[test]
+    fn interface_0_not_wireguard_netdev_0_returns_not_wg_config_err() {
+        // Arrange
+        let units = WgNetworkdUnits::new(
+            NETDEV_CONTENT.replace("Kind=wireguard", "Kind=bridge"),
+            NETWORK_CONTENT.to_string(),
+        );
+
+        // Act
+        let res = units.interface();
+
+        // Assert
+        assert_eq!(WgConfErrKind::NotWgConfig, res.unwrap_err().kind());
+    }
+}
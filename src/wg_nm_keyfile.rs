@@ -0,0 +1,448 @@
+use std::net::IpAddr;
+
+use ipnetwork::IpNetwork;
+
+use crate::{
+    wg_parse::{parse_number, validate_interface_name},
+    WgClientConf, WgConfError, WgDns, WgDocument, WgDocumentSection, WgEndpoint, WgInterface,
+    WgPeer, WgPresharedKey, WgPrivateKey, WgPublicKey, WgTable,
+};
+
+// Sections
+const CONNECTION: &'static str = "connection";
+const WIREGUARD: &'static str = "wireguard";
+const WIREGUARD_PEER_PREFIX: &'static str = "wireguard-peer.";
+const IPV4: &'static str = "ipv4";
+const IPV6: &'static str = "ipv6";
+
+// Keys
+const ID: &'static str = "id";
+const TYPE: &'static str = "type";
+const INTERFACE_NAME: &'static str = "interface-name";
+const PRIVATE_KEY: &'static str = "private-key";
+const LISTEN_PORT: &'static str = "listen-port";
+const FW_MARK: &'static str = "fwmark";
+const MTU: &'static str = "mtu";
+const PEER_ROUTES: &'static str = "peer-routes";
+const ENDPOINT: &'static str = "endpoint";
+const PRESHARED_KEY: &'static str = "preshared-key";
+const PRESHARED_KEY_FLAGS: &'static str = "preshared-key-flags";
+const PERSISTENT_KEEPALIVE: &'static str = "persistent-keepalive";
+const ALLOWED_IPS: &'static str = "allowed-ips";
+const METHOD: &'static str = "method";
+const ADDRESS_PREFIX: &'static str = "address";
+const ADDRESSES: &'static str = "addresses";
+const DNS: &'static str = "dns";
+const DNS_SEARCH: &'static str = "dns-search";
+const DNS_PRIORITY: &'static str = "dns-priority";
+
+// Values
+const WIREGUARD_TYPE: &'static str = "wireguard";
+const MANUAL_METHOD: &'static str = "manual";
+const DISABLED_METHOD: &'static str = "disabled";
+const LIST_SEPARATOR: char = ';';
+/// Negative priority makes VPN DNS exclusive for full tunnel like wg-quick does
+const FULL_TUNNEL_DNS_PRIORITY: &'static str = "-50";
+
+impl WgClientConf {
+    /// Exports client config as NetworkManager `.nmconnection` keyfile,
+    /// `interface_name` is used as connection id and interface name
+    ///
+    /// Address, DNS and MTU are mapped to `[ipv4]`, `[ipv6]` and `[wireguard]` sections,
+    /// every peer is mapped to `[wireguard-peer.<public key>]` section. Routes to the peers' allowed IPs
+    /// are added by NetworkManager (`peer-routes`) unless Table is off. Hook commands and unknown keys
+    /// can't be represented in keyfile, so they are not exported.
+    ///
+    /// Referenced private key is resolved and exported inline, see [`WgInterface::resolve_private_key`](crate::WgInterface::resolve_private_key)
+    ///
+    /// **Note**, that NetworkManager accepts only keyfiles which are readable by the owner only (0600)
+    pub fn to_nm_keyfile(&self, interface_name: &str) -> Result<String, WgConfError> {
+        validate_interface_name(interface_name)?;
+
+        let interface = self.interface();
+        let mut raw = section(CONNECTION);
+        raw += &key_value(ID, interface_name);
+        raw += &key_value(TYPE, WIREGUARD_TYPE);
+        raw += &key_value(INTERFACE_NAME, interface_name);
+
+        raw += "\n";
+        raw += &section(WIREGUARD);
+        raw += &key_value(
+            PRIVATE_KEY,
+            interface.resolve_private_key(None)?.expose_secret(),
+        );
+        if let Some(listen_port) = interface.listen_port() {
+            raw += &key_value(LISTEN_PORT, &listen_port.to_string());
+        }
+        if let Some(fw_mark) = interface.fw_mark() {
+            raw += &key_value(FW_MARK, &fw_mark.to_string());
+        }
+        if let Some(mtu) = interface.mtu() {
+            raw += &key_value(MTU, &mtu.to_string());
+        }
+        if interface.table() == Some(&WgTable::Off) {
+            raw += &key_value(PEER_ROUTES, "false");
+        }
+
+        for peer in self.peers() {
+            raw += "\n";
+            raw += &section(&format!(
+                "{WIREGUARD_PEER_PREFIX}{}",
+                peer.public_key().to_string()
+            ));
+            if let Some(endpoint) = peer.endpoint() {
+                raw += &key_value(ENDPOINT, &endpoint.to_string());
+            }
+            if let Some(preshared_key) = peer.preshared_key() {
+                raw += &key_value(PRESHARED_KEY, preshared_key.expose_secret());
+                // 0 means that the key is stored in the keyfile rather than in a secret agent
+                raw += &key_value(PRESHARED_KEY_FLAGS, "0");
+            }
+            if let Some(persistent_keepalive) = peer.persistent_keepalive() {
+                raw += &key_value(PERSISTENT_KEEPALIVE, &persistent_keepalive.to_string());
+            }
+            raw += &key_value(ALLOWED_IPS, &list(peer.allowed_ips()));
+        }
+
+        raw += "\n";
+        raw += &ip_section(self, IPV4, |ip| ip.is_ipv4());
+        raw += "\n";
+        raw += &ip_section(self, IPV6, |ip| ip.is_ipv6());
+
+        Ok(raw)
+    }
+
+    /// Imports client config from NetworkManager `.nmconnection` keyfile of WireGuard connection
+    ///
+    /// Returns [`WgConfError::NotWgConfig`] if the keyfile is not WireGuard connection
+    /// and [`WgConfError::ValidationFailed`] if private key isn't stored in the keyfile or any value is invalid
+    pub fn from_nm_keyfile(keyfile: &str) -> Result<WgClientConf, WgConfError> {
+        let document = WgDocument::parse(keyfile);
+
+        let connection_type = document
+            .sections_by_name(CONNECTION)
+            .next()
+            .and_then(|section| section.get(TYPE));
+        if connection_type != Some(WIREGUARD_TYPE) {
+            return Err(WgConfError::NotWgConfig(
+                "keyfile is not WireGuard connection".to_string(),
+            ));
+        }
+
+        let wireguard =
+            document
+                .sections_by_name(WIREGUARD)
+                .next()
+                .ok_or(WgConfError::NotWgConfig(
+                    "couldn't find [wireguard] section".to_string(),
+                ))?;
+
+        let private_key: WgPrivateKey = wireguard
+            .get(PRIVATE_KEY)
+            .ok_or(WgConfError::ValidationFailed(
+                "private key isn't stored in the keyfile".to_string(),
+            ))?
+            .parse()?;
+
+        let mut addresses: Vec<IpNetwork> = vec![];
+        let mut dns: Vec<WgDns> = vec![];
+        for ip_section_name in [IPV4, IPV6] {
+            if let Some(ip_section) = document.sections_by_name(ip_section_name).next() {
+                addresses.extend(section_addresses(ip_section)?);
+                dns.extend(section_dns(ip_section)?);
+            }
+        }
+
+        let listen_port = wireguard
+            .get(LISTEN_PORT)
+            .map(|port| parse_number(LISTEN_PORT, port))
+            .transpose()?
+            .filter(|port| *port != 0);
+
+        let mut interface =
+            WgInterface::new(private_key, addresses, listen_port, dns, vec![], vec![])?;
+        interface.set_mtu(
+            wireguard
+                .get(MTU)
+                .map(|mtu| parse_number(MTU, mtu))
+                .transpose()?
+                .filter(|mtu| *mtu != 0),
+        )?;
+        interface.set_fw_mark(
+            wireguard
+                .get(FW_MARK)
+                .map(|fw_mark| parse_number(FW_MARK, fw_mark))
+                .transpose()?
+                .filter(|fw_mark| *fw_mark != 0),
+        );
+        if wireguard.get(PEER_ROUTES) == Some("false") {
+            interface.set_table(Some(WgTable::Off));
+        }
+
+        let peers = document
+            .sections()
+            .iter()
+            .filter(|section| section.name().starts_with(WIREGUARD_PEER_PREFIX))
+            .map(peer_from_section)
+            .collect::<Result<Vec<WgPeer>, WgConfError>>()?;
+
+        Ok(WgClientConf::new(interface, peers))
+    }
+}
+
+fn peer_from_section(section: &WgDocumentSection) -> Result<WgPeer, WgConfError> {
+    let public_key: WgPublicKey = section.name()[WIREGUARD_PEER_PREFIX.len()..].parse()?;
+
+    let endpoint: Option<WgEndpoint> = section
+        .get(ENDPOINT)
+        .map(|endpoint| endpoint.parse())
+        .transpose()?;
+    let preshared_key: Option<WgPresharedKey> = section
+        .get(PRESHARED_KEY)
+        .map(|preshared_key| preshared_key.parse())
+        .transpose()?;
+    let persistent_keepalive: Option<u16> = section
+        .get(PERSISTENT_KEEPALIVE)
+        .map(|keepalive| parse_number(PERSISTENT_KEEPALIVE, keepalive))
+        .transpose()?
+        .filter(|keepalive| *keepalive != 0);
+    let allowed_ips: Vec<IpNetwork> = split_list(section.get(ALLOWED_IPS).unwrap_or_default())
+        .map(|ip| {
+            ip.parse()
+                .map_err(|_| WgConfError::ValidationFailed(format!("invalid allowed ip '{ip}'")))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(WgPeer::new(
+        public_key,
+        allowed_ips,
+        endpoint,
+        preshared_key,
+        persistent_keepalive,
+    ))
+}
+
+/// Returns `addressN` (and legacy `addresses`) values of ip section, gateway after ',' is ignored
+fn section_addresses(section: &WgDocumentSection) -> Result<Vec<IpNetwork>, WgConfError> {
+    section
+        .key_values()
+        .into_iter()
+        .filter(|(k, _)| {
+            k == ADDRESSES
+                || k.strip_prefix(ADDRESS_PREFIX)
+                    .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .flat_map(|(_, v)| {
+            split_list(&v)
+                .map(|address| address.split(',').next().unwrap_or_default().to_owned())
+                .collect::<Vec<String>>()
+        })
+        .map(|address| {
+            address
+                .parse()
+                .map_err(|_| WgConfError::ValidationFailed(format!("invalid address '{address}'")))
+        })
+        .collect()
+}
+
+fn section_dns(section: &WgDocumentSection) -> Result<Vec<WgDns>, WgConfError> {
+    let resolvers = split_list(section.get(DNS).unwrap_or_default());
+    let search_domains = split_list(section.get(DNS_SEARCH).unwrap_or_default());
+
+    resolvers
+        .chain(search_domains)
+        .map(|dns| dns.parse())
+        .collect()
+}
+
+/// Returns `[ipv4]` or `[ipv6]` section with the addresses and DNS of the family
+fn ip_section(client_conf: &WgClientConf, name: &str, is_family: fn(&IpAddr) -> bool) -> String {
+    let interface = client_conf.interface();
+    let addresses: Vec<&IpNetwork> = interface
+        .addresses()
+        .iter()
+        .filter(|address| is_family(&address.ip()))
+        .collect();
+
+    let mut raw = section(name);
+    if addresses.is_empty() {
+        raw += &key_value(METHOD, DISABLED_METHOD);
+
+        return raw;
+    }
+
+    for (i, address) in addresses.iter().enumerate() {
+        raw += &key_value(&format!("{ADDRESS_PREFIX}{}", i + 1), &address.to_string());
+    }
+
+    let resolvers: Vec<String> = interface
+        .dns()
+        .iter()
+        .filter_map(|dns| match dns {
+            WgDns::Ip(ip) if is_family(ip) => Some(ip.to_string()),
+            _ => None,
+        })
+        .collect();
+    if !resolvers.is_empty() {
+        raw += &key_value(DNS, &list(&resolvers));
+    }
+
+    // search domains aren't related to address family, they are written once into [ipv4] or [ipv6] one
+    let is_first_family = interface
+        .addresses()
+        .first()
+        .is_some_and(|address| is_family(&address.ip()));
+    let search_domains: Vec<&WgDns> = interface
+        .dns()
+        .iter()
+        .filter(|dns| matches!(dns, WgDns::Domain(_)))
+        .collect();
+    if is_first_family && !search_domains.is_empty() {
+        raw += &key_value(DNS_SEARCH, &list(&search_domains));
+    }
+
+    let is_full_tunnel = client_conf.peers().iter().any(|peer| {
+        peer.allowed_ips()
+            .iter()
+            .any(|ip| ip.prefix() == 0 && is_family(&ip.ip()))
+    });
+    if is_full_tunnel && !resolvers.is_empty() {
+        raw += &key_value(DNS_PRIORITY, FULL_TUNNEL_DNS_PRIORITY);
+    }
+
+    raw += &key_value(METHOD, MANUAL_METHOD);
+
+    raw
+}
+
+fn section(name: &str) -> String {
+    format!("[{name}]\n")
+}
+
+fn key_value(key: &str, value: &str) -> String {
+    format!("{key}={value}\n")
+}
+
+/// Keyfile list which items are terminated with ';'
+fn list<T: ToString>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string() + ";")
+        .collect::<String>()
+}
+
+fn split_list(raw: &str) -> impl Iterator<Item = &str> {
+    raw.split(LIST_SEPARATOR)
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WgConfErrKind;
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/32, fd00::2/128
+DNS = 10.0.0.1, example.com
+MTU = 1420
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 0.0.0.0/0, fd00::/64
+Endpoint = vpn.example.com:51820
+PresharedKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+PersistentKeepalive = 25
+";
+
+    const KEYFILE: &'static str = "[connection]
+id=wg0
+type=wireguard
+interface-name=wg0
+
+[wireguard]
+private-key=6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+mtu=1420
+
+[wireguard-peer.LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=]
+endpoint=vpn.example.com:51820
+preshared-key=Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+preshared-key-flags=0
+persistent-keepalive=25
+allowed-ips=0.0.0.0/0;fd00::/64;
+
+[ipv4]
+address1=10.0.0.2/32
+dns=10.0.0.1;
+dns-search=example.com;
+dns-priority=-50
+method=manual
+
+[ipv6]
+address1=fd00::2/128
+method=manual
+";
+
+    #[test]
+    fn to_nm_keyfile_0_common_scenario() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let keyfile = client_conf.to_nm_keyfile("wg0");
+
+        // Assert
+        assert_eq!(KEYFILE, keyfile.unwrap());
+    }
+
+    #[test]
+    fn to_nm_keyfile_0_invalid_interface_name_0_returns_validation_err() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        for interface_name in ["", "wg 0", "wg/0", "very-long-wg-interface"] {
+            // Act
+            let res = client_conf.to_nm_keyfile(interface_name);
+
+            // Assert
+            assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+        }
+    }
+
+    #[test]
+    fn from_nm_keyfile_0_exported_keyfile_0_same_conf() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let imported = WgClientConf::from_nm_keyfile(KEYFILE);
+
+        // Assert
+        assert_eq!(client_conf, imported.unwrap());
+    }
+
+    #[test]
+    fn from_nm_keyfile_0_not_wireguard_or_no_private_key_0_returns_err() {
+        // Arrange
+        let not_wireguard = KEYFILE.replace("type=wireguard", "type=vpn");
+        let no_private_key = KEYFILE.replace(
+            "private-key=6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=\n",
+            "private-key-flags=1\n",
+        );
+
+        // Act
+        let not_wireguard_res = WgClientConf::from_nm_keyfile(&not_wireguard);
+        let no_private_key_res = WgClientConf::from_nm_keyfile(&no_private_key);
+
+        // Assert
+        assert_eq!(
+            WgConfErrKind::NotWgConfig,
+            not_wireguard_res.unwrap_err().kind()
+        );
+        assert_eq!(
+            WgConfErrKind::ValidationFailed,
+            no_private_key_res.unwrap_err().kind()
+        );
+    }
+}
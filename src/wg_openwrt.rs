@@ -0,0 +1,217 @@
+use std::net::IpAddr;
+
+use crate::{
+    wg_parse::validate_interface_name, WgClientConf, WgConfError, WgDns, WgHost, WgInterface,
+    WgPeer, WgTable,
+};
+
+// Section types
+const INTERFACE: &'static str = "interface";
+const PEER_PREFIX: &'static str = "wireguard_";
+
+// Options
+const PROTO: &'static str = "proto";
+const PRIVATE_KEY: &'static str = "private_key";
+const LISTEN_PORT: &'static str = "listen_port";
+const ADDRESSES: &'static str = "addresses";
+const DNS: &'static str = "dns";
+const DNS_SEARCH: &'static str = "dns_search";
+const MTU: &'static str = "mtu";
+const FW_MARK: &'static str = "fwmark";
+const DESCRIPTION: &'static str = "description";
+const PUBLIC_KEY: &'static str = "public_key";
+const PRESHARED_KEY: &'static str = "preshared_key";
+const ALLOWED_IPS: &'static str = "allowed_ips";
+const ENDPOINT_HOST: &'static str = "endpoint_host";
+const ENDPOINT_PORT: &'static str = "endpoint_port";
+const PERSISTENT_KEEPALIVE: &'static str = "persistent_keepalive";
+const ROUTE_ALLOWED_IPS: &'static str = "route_allowed_ips";
+
+// Values
+const WIREGUARD_PROTO: &'static str = "wireguard";
+
+impl WgClientConf {
+    /// Exports the config as OpenWrt UCI snippet for `/etc/config/network`:
+    /// `config interface` section with WireGuard protocol and `config wireguard_<interface_name>` section per peer
+    ///
+    /// Routes to the peers' allowed IPs are added by netifd (`route_allowed_ips`) unless Table is off.
+    /// Peer name is exported as its description. Hook commands and unknown keys can't be represented in UCI,
+    /// so they are not exported. To export a single peer with the interface use
+    /// `WgClientConf::new(interface, vec![peer])`
+    ///
+    /// Referenced private key is resolved and exported inline, see [`WgInterface::resolve_private_key`](crate::WgInterface::resolve_private_key)
+    pub fn to_openwrt_uci(&self, interface_name: &str) -> Result<String, WgConfError> {
+        validate_interface_name(interface_name)?;
+        if !interface_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            // UCI section names may contain only alphanumeric chars and '_'
+            return Err(WgConfError::ValidationFailed(format!(
+                "invalid OpenWrt interface name '{interface_name}'"
+            )));
+        }
+
+        let mut raw = interface_section(interface_name, self.interface())?;
+        for peer in self.peers() {
+            raw += "\n";
+            raw += &peer_section(interface_name, self.interface(), peer);
+        }
+
+        Ok(raw)
+    }
+}
+
+fn interface_section(interface_name: &str, interface: &WgInterface) -> Result<String, WgConfError> {
+    let mut raw = section(INTERFACE, Some(interface_name));
+    raw += &option(PROTO, WIREGUARD_PROTO);
+    raw += &option(
+        PRIVATE_KEY,
+        interface.resolve_private_key(None)?.expose_secret(),
+    );
+    if let Some(listen_port) = interface.listen_port() {
+        raw += &option(LISTEN_PORT, &listen_port.to_string());
+    }
+    for address in interface.addresses() {
+        raw += &list(ADDRESSES, &address.to_string());
+    }
+    for dns in interface.dns() {
+        match dns {
+            WgDns::Ip(ip) => raw += &list(DNS, &ip.to_string()),
+            WgDns::Domain(domain) => raw += &list(DNS_SEARCH, domain),
+        }
+    }
+    if let Some(mtu) = interface.mtu() {
+        raw += &option(MTU, &mtu.to_string());
+    }
+    if let Some(fw_mark) = interface.fw_mark() {
+        raw += &option(FW_MARK, &fw_mark.to_string());
+    }
+
+    Ok(raw)
+}
+
+fn peer_section(interface_name: &str, interface: &WgInterface, peer: &WgPeer) -> String {
+    let mut raw = section(&format!("{PEER_PREFIX}{interface_name}"), None);
+    if let Some(name) = peer.name() {
+        raw += &option(DESCRIPTION, name);
+    }
+    raw += &option(PUBLIC_KEY, &peer.public_key().to_string());
+    if let Some(preshared_key) = peer.preshared_key() {
+        raw += &option(PRESHARED_KEY, preshared_key.expose_secret());
+    }
+    for allowed_ip in peer.allowed_ips() {
+        raw += &list(ALLOWED_IPS, &allowed_ip.to_string());
+    }
+    if let Some(endpoint) = peer.endpoint() {
+        // IPv6 host is written without brackets
+        let host = match endpoint.host() {
+            WgHost::Ip(IpAddr::V6(ip)) => ip.to_string(),
+            host => host.to_string(),
+        };
+        raw += &option(ENDPOINT_HOST, &host);
+        raw += &option(ENDPOINT_PORT, &endpoint.port().to_string());
+    }
+    if let Some(persistent_keepalive) = peer.persistent_keepalive() {
+        raw += &option(PERSISTENT_KEEPALIVE, &persistent_keepalive.to_string());
+    }
+    let route_allowed_ips = match interface.table() {
+        Some(WgTable::Off) => "0",
+        _ => "1",
+    };
+    raw += &option(ROUTE_ALLOWED_IPS, route_allowed_ips);
+
+    raw
+}
+
+fn section(section_type: &str, name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("config {section_type} {}\n", quote(name)),
+        None => format!("config {section_type}\n"),
+    }
+}
+
+fn option(name: &str, value: &str) -> String {
+    format!("\toption {name} {}\n", quote(value))
+}
+
+fn list(name: &str, value: &str) -> String {
+    format!("\tlist {name} {}\n", quote(value))
+}
+
+/// Single-quotes UCI value, single quotes inside are closed, escaped and reopened like in shell
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WgConfErrKind;
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/32, fd00::2/128
+DNS = 10.0.0.1, example.com
+MTU = 1420
+
+[Peer]
+# Name = Bob's office
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.0/24, fd00::/64
+Endpoint = [2001:db8::1]:51820
+PresharedKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+PersistentKeepalive = 25
+";
+
+    const UCI_CONTENT: &'static str = "config interface 'wg0'
+\toption proto 'wireguard'
+\toption private_key '6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8='
+\tlist addresses '10.0.0.2/32'
+\tlist addresses 'fd00::2/128'
+\tlist dns '10.0.0.1'
+\tlist dns_search 'example.com'
+\toption mtu '1420'
+
+config wireguard_wg0
+\toption description 'Bob'\\''s office'
+\toption public_key 'LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE='
+\toption preshared_key 'Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4='
+\tlist allowed_ips '10.0.0.0/24'
+\tlist allowed_ips 'fd00::/64'
+\toption endpoint_host '2001:db8::1'
+\toption endpoint_port '51820'
+\toption persistent_keepalive '25'
+\toption route_allowed_ips '1'
+";
+
+    #[test]
+    fn to_openwrt_uci_0_common_scenario() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let uci = client_conf.to_openwrt_uci("wg0");
+
+        // Assert
+        assert_eq!(UCI_CONTENT, uci.unwrap());
+    }
+
+    #[test]
+    fn to_openwrt_uci_0_invalid_section_name_0_returns_validation_err() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        for interface_name in ["wg-office", "wg.0", "wg 0", "wg\u{e9}"] {
+            // Act
+            let res = client_conf.to_openwrt_uci(interface_name);
+
+            // Assert
+            assert_eq!(
+                WgConfErrKind::ValidationFailed,
+                res.unwrap_err().kind(),
+                "{interface_name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use std::fmt::Display;
+
+use crate::{
+    wg_document, wg_interface, wg_peer, WgConfErrLocation, WgConfError, WgDocument, WgDocumentLine,
+    WgDocumentLineKind,
+};
+
+/// Max length of Linux interface name
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/// Mode of WG config parsing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WgParseMode {
+    /// Only canonical config is accepted: it starts with exact \[Interface\] tag,
+    /// section tags and keys are written exactly, there are no unknown or duplicate keys,
+    /// lines which are not key-values and CRLF line endings
+    Strict,
+    /// Leading comments, BOM, whitespaces around section tags, case-insensitive keys,
+    /// CRLF line endings, unknown and duplicate keys are accepted,
+    /// every tolerated deviation is reported as [`WgParseWarning`]
+    #[default]
+    Lenient,
+}
+
+/// Options of WG config parsing, lenient mode is used by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WgParseOptions {
+    pub(crate) mode: WgParseMode,
+}
+
+/// Deviation from the canonical WG config format which was tolerated in [`WgParseMode::Lenient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgParseWarning {
+    pub(crate) message: String,
+    pub(crate) location: WgConfErrLocation,
+}
+
+impl Display for WgParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl WgParseOptions {
+    /// Creates new [`WgParseOptions`]
+    pub fn new(mode: WgParseMode) -> WgParseOptions {
+        WgParseOptions { mode }
+    }
+
+    /// Options of [`WgParseMode::Strict`] mode
+    pub fn strict() -> WgParseOptions {
+        WgParseOptions::new(WgParseMode::Strict)
+    }
+
+    /// Options of [`WgParseMode::Lenient`] mode
+    pub fn lenient() -> WgParseOptions {
+        WgParseOptions::new(WgParseMode::Lenient)
+    }
+
+    // getters
+    pub fn mode(&self) -> WgParseMode {
+        self.mode
+    }
+
+    /// Checks structure of WG config `text` which is read from `file_name` (if any).
+    ///
+    /// In lenient mode returns all the tolerated deviations, in strict mode the first deviation
+    /// is returned as [`WgConfError::NotWgConfig`]. Values of known keys are not validated here,
+    /// they are validated when the sections are parsed
+    pub(crate) fn check_structure(
+        &self,
+        file_name: Option<&str>,
+        text: &str,
+    ) -> Result<Vec<WgParseWarning>, WgConfError> {
+        if text.trim_start_matches(wg_document::BOM).trim().is_empty() {
+            return Err(WgConfError::NotWgConfig("file is empty".to_string()));
+        }
+
+        let mut checker = StructureChecker {
+            options: self,
+            file_name,
+            warnings: vec![],
+        };
+        checker.check(&WgDocument::parse(text))?;
+
+        Ok(checker.warnings)
+    }
+}
+
+impl WgParseWarning {
+    // getters
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    pub fn location(&self) -> &WgConfErrLocation {
+        &self.location
+    }
+}
+
+struct StructureChecker<'a> {
+    options: &'a WgParseOptions,
+    file_name: Option<&'a str>,
+    warnings: Vec<WgParseWarning>,
+}
+
+impl StructureChecker<'_> {
+    fn check(&mut self, document: &WgDocument) -> Result<(), WgConfError> {
+        let interface_name = section_name(wg_interface::INTERFACE_TAG);
+        let peer_name = section_name(wg_peer::PEER_TAG);
+
+        let first_line = match document.preamble().first() {
+            Some(line) => Some(line),
+            None => document.sections().first().map(|section| section.header()),
+        };
+        if let Some(first_line) = first_line {
+            if first_line.raw().starts_with(wg_document::BOM) {
+                let location = self.location(first_line, 1, None);
+                self.deviation("byte order mark at the start of the file", location)?;
+            }
+        }
+
+        let mut line_no = 0;
+        let mut crlf_reported = false;
+
+        for line in document.preamble() {
+            line_no += 1;
+            self.check_line_ending(line, line_no, &mut crlf_reported)?;
+
+            match line.kind() {
+                WgDocumentLineKind::Blank | WgDocumentLineKind::Comment => {
+                    if line_no == 1 {
+                        let location = self.location(line, line_no, None);
+                        self.deviation("content before [Interface] section", location)?;
+                    }
+                }
+                _ => {
+                    let location = self.location(line, line_no, None);
+                    return Err(WgConfError::NotWgConfig(
+                        "couldn't find [Interface] section".to_string(),
+                    )
+                    .with_location(location));
+                }
+            }
+        }
+
+        if document.sections().is_empty() {
+            return Err(WgConfError::NotWgConfig(
+                "couldn't find [Interface] section".to_string(),
+            ));
+        }
+
+        for (i, section) in document.sections().iter().enumerate() {
+            line_no += 1;
+            let header = section.header();
+            let location = self.location(header, line_no, Some(section.name()));
+            self.check_line_ending(header, line_no, &mut crlf_reported)?;
+
+            let (known_keys, repeatable_keys) = match section.name() {
+                name if i == 0 && name == interface_name => {
+                    (wg_interface::KEYS, wg_interface::REPEATABLE_KEYS)
+                }
+                name if i > 0 && name == peer_name => (wg_peer::KEYS, wg_peer::REPEATABLE_KEYS),
+                _ if i == 0 => {
+                    return Err(WgConfError::NotWgConfig(
+                        "couldn't find [Interface] section".to_string(),
+                    )
+                    .with_location(location));
+                }
+                name => {
+                    return Err(
+                        WgConfError::NotWgConfig(format!("unexpected section [{name}]"))
+                            .with_location(location),
+                    );
+                }
+            };
+
+            let tag = match i {
+                0 => wg_interface::INTERFACE_TAG,
+                _ => wg_peer::PEER_TAG,
+            };
+            if header.raw().trim_start_matches(wg_document::BOM) != tag {
+                self.deviation(&format!("section tag must be written as {tag}"), location)?;
+            }
+
+            let mut seen_keys: Vec<&str> = vec![];
+            for line in section.lines() {
+                line_no += 1;
+                self.check_line_ending(line, line_no, &mut crlf_reported)?;
+
+                let key = match line.kind() {
+                    WgDocumentLineKind::KeyValue { key, .. } => *key,
+                    WgDocumentLineKind::Unknown => {
+                        // in lenient mode the error is returned when the section is parsed
+                        if self.options.mode == WgParseMode::Strict {
+                            let location = self.location(line, line_no, Some(section.name()));
+                            return Err(WgConfError::NotWgConfig(format!(
+                                "'{}' is not key-value string",
+                                line.raw().trim()
+                            ))
+                            .with_location(location));
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+
+                let raw_key = line.key().unwrap_or_default();
+                let location = self
+                    .location(line, line_no, Some(section.name()))
+                    .at_key(raw_key, char_columns(line.raw(), key));
+
+                let known_key = known_keys
+                    .iter()
+                    .find(|known_key| known_key.eq_ignore_ascii_case(raw_key));
+                let known_key = match known_key {
+                    Some(known_key) => known_key,
+                    None => {
+                        self.deviation(&format!("unknown key '{raw_key}'"), location)?;
+                        continue;
+                    }
+                };
+
+                if raw_key != *known_key {
+                    self.deviation(
+                        &format!("key '{raw_key}' must be written as '{known_key}'"),
+                        location.clone(),
+                    )?;
+                }
+
+                if seen_keys.contains(known_key) && !repeatable_keys.contains(known_key) {
+                    self.deviation(
+                        &format!("duplicate key '{raw_key}', the last value is used"),
+                        location,
+                    )?;
+                }
+                seen_keys.push(known_key);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_line_ending(
+        &mut self,
+        line: &WgDocumentLine,
+        line_no: usize,
+        crlf_reported: &mut bool,
+    ) -> Result<(), WgConfError> {
+        if line.eol() == "\r\n" && !*crlf_reported {
+            *crlf_reported = true;
+            let location = self.location(line, line_no, None);
+            self.deviation("CRLF line endings", location)?;
+        }
+
+        Ok(())
+    }
+
+    fn location(
+        &self,
+        line: &WgDocumentLine,
+        line_no: usize,
+        section: Option<&str>,
+    ) -> WgConfErrLocation {
+        let location = WgConfErrLocation {
+            file: self.file_name.map(|file_name| file_name.to_owned()),
+            line: Some(line_no),
+            columns: None,
+            section: section.map(|section| section.to_owned()),
+            key: None,
+            line_text: Some(line.raw().to_owned()),
+        };
+
+        let line_span = wg_document::trimmed_span(line.raw(), 0, line.raw().len());
+        location.at_columns(char_columns(line.raw(), line_span))
+    }
+
+    /// Reports tolerated deviation in lenient mode or returns error in strict mode
+    fn deviation(&mut self, message: &str, location: WgConfErrLocation) -> Result<(), WgConfError> {
+        match self.options.mode {
+            WgParseMode::Strict => {
+                Err(WgConfError::NotWgConfig(message.to_owned()).with_location(location))
+            }
+            WgParseMode::Lenient => {
+                self.warnings.push(WgParseWarning {
+                    message: message.to_owned(),
+                    location,
+                });
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Checks if the line is the provided section tag, whitespaces and BOM are ignored
+pub(crate) fn is_tag(raw_line: &str, tag: &str) -> bool {
+    match wg_document::section_tag_name(raw_line) {
+        Some(name) => name == section_name(tag),
+        None => false,
+    }
+}
+
+/// Returns known key spelling if the key is known (key is case-insensitive)
+pub(crate) fn canonical_key(key: String, known_keys: &[&str]) -> String {
+    match known_keys
+        .iter()
+        .find(|known_key| known_key.eq_ignore_ascii_case(&key))
+    {
+        Some(known_key) => known_key.to_string(),
+        None => key,
+    }
+}
+
+/// Section name without brackets, e.g. "Peer" for \[Peer\]
+pub(crate) fn section_name(tag: &str) -> &str {
+    wg_document::section_tag_name(tag).unwrap_or_default()
+}
+
+/// Converts byte span of the line to 1-based char columns
+pub(crate) fn char_columns(raw_line: &str, span: (usize, usize)) -> (usize, usize) {
+    (
+        raw_line[..span.0].chars().count() + 1,
+        raw_line[..span.1].chars().count() + 1,
+    )
+}
+
+/// Checks if the name is valid Linux network interface name
+pub(crate) fn validate_interface_name(interface_name: &str) -> Result<(), WgConfError> {
+    if interface_name.is_empty()
+        || interface_name.len() > MAX_INTERFACE_NAME_LEN
+        || interface_name
+            .chars()
+            .any(|c| c.is_whitespace() || c == '/' || c == '[' || c == ']')
+    {
+        return Err(WgConfError::ValidationFailed(format!(
+            "invalid interface name '{interface_name}'"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Parses numeric value of the key
+pub(crate) fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, WgConfError> {
+    value
+        .parse()
+        .map_err(|_| WgConfError::ValidationFailed(format!("invalid {key} '{value}'")))
+}
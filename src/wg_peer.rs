@@ -1,27 +1,186 @@
-use std::{collections::HashMap, fmt::Debug, net::SocketAddr};
+use std::{
+    fmt::{Debug, Display},
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use ipnetwork::IpNetwork;
 
-use crate::{WgConfError, WgKey};
+use crate::{
+    wg_interface, WgConfErrLocation, WgConfError, WgKey, WgPresharedKey, WgPublicKey, REDACTED,
+};
 
 /// Peer tag
 pub const PEER_TAG: &'static str = "[Peer]";
 
 // Fields
-const PUBLIC_KEY: &'static str = "PublicKey";
+pub(crate) const PUBLIC_KEY: &'static str = "PublicKey";
 const ALLOWED_IPS: &'static str = "AllowedIPs";
 const ENDPOINT: &'static str = "Endpoint";
-const PRESHARED_KEY: &'static str = "PresharedKey";
+pub(crate) const PRESHARED_KEY: &'static str = "PresharedKey";
 const PERSISTENT_KEEPALIVE: &'static str = "PersistentKeepalive";
 
+/// \[Peer\] fields which are managed by [`WgPeer`]
+pub(crate) const KEYS: &'static [&'static str] = &[
+    PUBLIC_KEY,
+    ALLOWED_IPS,
+    ENDPOINT,
+    PRESHARED_KEY,
+    PERSISTENT_KEEPALIVE,
+];
+/// \[Peer\] fields which values are comma-separated lists
+pub(crate) const LIST_KEYS: &'static [&'static str] = &[ALLOWED_IPS];
+/// \[Peer\] fields which may be repeated, their values are accumulated
+pub(crate) const REPEATABLE_KEYS: &'static [&'static str] = LIST_KEYS;
+
+// Metadata stored in `# Key = value` comments of the section
+const NAME: &'static str = "Name";
+const DESCRIPTION: &'static str = "Description";
+/// Last preshared key rotation time, Unix time in seconds
+const PRESHARED_KEY_ROTATED_AT: &'static str = "PresharedKeyRotatedAt";
+/// Prefix of `# Meta.<key> = value` comments which keep arbitrary metadata,
+/// other `# Key = value` comments belong to the operator and are never changed
+const METADATA_PREFIX: &'static str = "Meta.";
+
+/// Host of WG peer endpoint which is IP address or domain name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WgHost {
+    Ip(IpAddr),
+    Domain(String),
+}
+
+impl Display for WgHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WgHost::Ip(ip) => write!(f, "{ip}"),
+            WgHost::Domain(domain) => write!(f, "{domain}"),
+        }
+    }
+}
+
+impl FromStr for WgHost {
+    type Err = WgConfError;
+
+    /// Parses IP address (IPv6 may be bracketed, e.g. \[fd00::1\]) or domain name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unbracketed = s
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .unwrap_or(s);
+
+        if let Ok(ip) = unbracketed.parse::<IpAddr>() {
+            return Ok(WgHost::Ip(ip));
+        }
+
+        if unbracketed != s || !wg_interface::is_domain(s) {
+            return Err(WgConfError::ValidationFailed(format!(
+                "host '{s}' must be an ip address or a domain"
+            )));
+        }
+
+        Ok(WgHost::Domain(s.to_owned()))
+    }
+}
+
+impl From<IpAddr> for WgHost {
+    fn from(ip: IpAddr) -> Self {
+        WgHost::Ip(ip)
+    }
+}
+
+/// WG peer endpoint which is socket address or host:port pair
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgEndpoint {
+    host: WgHost,
+    port: u16,
+}
+
+impl Display for WgEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.host {
+            WgHost::Ip(IpAddr::V6(ip)) => write!(f, "[{}]:{}", ip, self.port),
+            _ => write!(f, "{}:{}", self.host, self.port),
+        }
+    }
+}
+
+impl FromStr for WgEndpoint {
+    type Err = WgConfError;
+
+    /// Parses endpoint like `10.0.0.1:51820`, `[fd00::1]:51820` or `vpn.example.com:51820`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid_endpoint =
+            || WgConfError::ValidationFailed("invalid endpoint raw value".to_string());
+
+        let (host, port) = s.rsplit_once(':').ok_or_else(invalid_endpoint)?;
+
+        // IPv6 address must be bracketed to separate it from port
+        if host.contains(':') && !host.starts_with('[') {
+            return Err(invalid_endpoint());
+        }
+
+        let host: WgHost = host.parse().map_err(|_| invalid_endpoint())?;
+        let port: u16 = port.parse().map_err(|_| invalid_endpoint())?;
+
+        WgEndpoint::new(host, port)
+    }
+}
+
+impl From<SocketAddr> for WgEndpoint {
+    fn from(socket_addr: SocketAddr) -> Self {
+        WgEndpoint {
+            host: WgHost::Ip(socket_addr.ip()),
+            port: socket_addr.port(),
+        }
+    }
+}
+
+impl WgEndpoint {
+    /// Creates new [`WgEndpoint`]
+    pub fn new(host: WgHost, port: u16) -> Result<WgEndpoint, WgConfError> {
+        if port == 0 {
+            return Err(WgConfError::ValidationFailed("port can't be 0".to_string()));
+        }
+
+        Ok(WgEndpoint { host, port })
+    }
+
+    /// Returns endpoint as socket address if the host is IP address
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self.host {
+            WgHost::Ip(ip) => Some(SocketAddr::new(ip, self.port)),
+            WgHost::Domain(_) => None,
+        }
+    }
+
+    // getters
+    pub fn host(&self) -> &WgHost {
+        &self.host
+    }
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
 /// Represents WG \[Peer\] section
 #[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "crate::wg_serde::WgPeerDe"))]
 pub struct WgPeer {
-    pub(crate) public_key: WgKey,
+    pub(crate) public_key: WgPublicKey,
     pub(crate) allowed_ips: Vec<IpNetwork>,
-    pub(crate) endpoint: Option<SocketAddr>,
-    pub(crate) preshared_key: Option<WgKey>,
+    pub(crate) endpoint: Option<WgEndpoint>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::wg_serde::serialize_optional_secret")
+    )]
+    pub(crate) preshared_key: Option<WgPresharedKey>,
     pub(crate) persistent_keepalive: Option<u16>,
+    pub(crate) name: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) metadata: Vec<(String, String)>,
+    pub(crate) extra: Vec<(String, String)>,
 }
 
 impl Debug for WgPeer {
@@ -30,61 +189,29 @@ impl Debug for WgPeer {
             .field("public_key", &self.public_key)
             .field("allowed_ips", &self.allowed_ips)
             .field("endpoint", &self.endpoint)
-            .field("preshared_key", &"***")
+            .field("preshared_key", &self.preshared_key)
             .field("persistent_keepalive", &self.persistent_keepalive)
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("metadata", &self.metadata)
+            .field("extra", &self.extra)
             .finish()
     }
 }
 
 impl ToString for WgPeer {
     fn to_string(&self) -> String {
-        let mut allowed_ips_raw = String::new();
-        for (i, ip) in self.allowed_ips.iter().enumerate() {
-            allowed_ips_raw += &ip.to_string();
-            if i != self.allowed_ips.len() - 1 {
-                allowed_ips_raw += ", ";
-            }
-        }
-
-        let endpoint = match &self.endpoint {
-            Some(val) => format!("\n{} = {}", ENDPOINT, val.to_string()),
-            None => "".to_string(),
-        };
-
-        let preshared_key = match &self.preshared_key {
-            Some(val) => format!("\n{} = {}", PRESHARED_KEY, val.to_string()),
-            None => "".to_string(),
-        };
-
-        let keepalive = match self.persistent_keepalive {
-            Some(val) => format!("\n{} = {}", PERSISTENT_KEEPALIVE, &val),
-            None => "".to_string(),
-        };
-
-        format!(
-            "{}
-{} = {}
-{} = {}{}{}{}
-",
-            PEER_TAG,
-            PUBLIC_KEY,
-            self.public_key.to_string(),
-            ALLOWED_IPS,
-            allowed_ips_raw,
-            endpoint,
-            preshared_key,
-            keepalive
-        )
+        self.to_raw_string(false)
     }
 }
 
 impl WgPeer {
     /// Creates new [`WgPeer`]
     pub fn new(
-        public_key: WgKey,
+        public_key: WgPublicKey,
         allowed_ips: Vec<IpNetwork>,
-        endpoint: Option<SocketAddr>,
-        preshared_key: Option<WgKey>,
+        endpoint: Option<WgEndpoint>,
+        preshared_key: Option<WgPresharedKey>,
         persistent_keepalive: Option<u16>,
     ) -> WgPeer {
         WgPeer {
@@ -93,6 +220,10 @@ impl WgPeer {
             endpoint,
             preshared_key,
             persistent_keepalive,
+            name: None,
+            description: None,
+            metadata: vec![],
+            extra: vec![],
         }
     }
 
@@ -103,45 +234,30 @@ impl WgPeer {
         preshared_key: Option<String>,
         persistent_keepalive: Option<String>,
     ) -> Result<WgPeer, WgConfError> {
-        let public_key: WgKey = public_key.parse()?;
+        let public_key: WgPublicKey = public_key.parse().map_err(err_at_key(PUBLIC_KEY))?;
 
-        let allowed_ips: Result<Vec<IpNetwork>, WgConfError> = allowed_ips
+        let allowed_ips: Vec<IpNetwork> = allowed_ips
             .iter()
-            .map(|ip| {
-                ip.parse::<IpNetwork>().map_err(|_| {
-                    WgConfError::ValidationFailed(
-                        "allowed IPs must be addresses with mask (e.g. 10.0.0.1/8)".to_string(),
-                    )
-                })
-            })
-            .collect();
-        let allowed_ips = allowed_ips?;
-
-        let endpoint: Option<SocketAddr> = endpoint
-            .map(|endpoint| {
-                endpoint.parse().map_err(|_| {
-                    WgConfError::ValidationFailed("invalid endpoint raw value".to_string())
-                })
-            })
-            .transpose()?;
+            .map(|ip| parse_allowed_ip(ip))
+            .collect::<Result<_, _>>()
+            .map_err(err_at_key(ALLOWED_IPS))?;
 
-        let preshared_key: Option<WgKey> = preshared_key
-            .map(|key| {
-                key.parse().map_err(|_| {
-                    WgConfError::ValidationFailed("invalid preshared key raw value".to_string())
-                })
-            })
-            .transpose()?;
+        let endpoint: Option<WgEndpoint> = endpoint
+            .map(|endpoint| endpoint.parse())
+            .transpose()
+            .map_err(err_at_key(ENDPOINT))?;
+
+        let preshared_key: Option<WgPresharedKey> = preshared_key
+            .as_deref()
+            .map(parse_preshared_key)
+            .transpose()
+            .map_err(err_at_key(PRESHARED_KEY))?;
 
         let persistent_keepalive: Option<u16> = persistent_keepalive
-            .map(|p| {
-                p.parse().map_err(|_| {
-                    WgConfError::ValidationFailed(
-                        "invalid persistent keepalive raw value".to_string(),
-                    )
-                })
-            })
-            .transpose()?;
+            .as_deref()
+            .map(parse_persistent_keepalive)
+            .transpose()
+            .map_err(err_at_key(PERSISTENT_KEEPALIVE))?;
 
         Ok(WgPeer::new(
             public_key,
@@ -153,55 +269,356 @@ impl WgPeer {
     }
 
     // getters
-    pub fn public_key(&self) -> &WgKey {
+    pub fn public_key(&self) -> &WgPublicKey {
         &self.public_key
     }
     pub fn allowed_ips(&self) -> &[IpNetwork] {
         &self.allowed_ips
     }
-    pub fn endpoint(&self) -> Option<&SocketAddr> {
+    pub fn endpoint(&self) -> Option<&WgEndpoint> {
         self.endpoint.as_ref()
     }
-    pub fn preshared_key(&self) -> Option<&WgKey> {
+    pub fn preshared_key(&self) -> Option<&WgPresharedKey> {
         self.preshared_key.as_ref()
     }
     pub fn persistent_keepalive(&self) -> Option<u16> {
         self.persistent_keepalive
     }
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+    /// Returns ordered metadata key-values except name and description
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+    /// Returns metadata value of the provided key (key is case-insensitive)
+    pub fn metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the time when the preshared key was rotated last time which is stored
+    /// in `# PresharedKeyRotatedAt = <unix time>` comment
+    pub fn preshared_key_rotated_at(&self) -> Option<SystemTime> {
+        self.metadata_value(PRESHARED_KEY_ROTATED_AT)?
+            .parse()
+            .ok()
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Returns ordered key-values which are not recognized by [`WgPeer`]
+    pub fn extra(&self) -> &[(String, String)] {
+        &self.extra
+    }
+
+    /// Sets ordered key-values which are not recognized by [`WgPeer`].
+    ///
+    /// They are written as is after the known fields, so fields of newer WG tools are kept
+    pub fn set_extra(&mut self, extra: Vec<(String, String)>) -> Result<(), WgConfError> {
+        wg_interface::validate_extra(&extra, KEYS)?;
+        self.extra = extra;
+
+        Ok(())
+    }
+
+    /// Sets friendly name of the peer which is stored in `# Name = ...` comment
+    pub fn set_name(&mut self, name: Option<String>) -> Result<(), WgConfError> {
+        self.name = name.map(|name| metadata_value(&name)).transpose()?;
+
+        Ok(())
+    }
 
+    /// Sets description of the peer which is stored in `# Description = ...` comment
+    pub fn set_description(&mut self, description: Option<String>) -> Result<(), WgConfError> {
+        self.description = description
+            .map(|description| metadata_value(&description))
+            .transpose()?;
+
+        Ok(())
+    }
+
+    /// Sets metadata value which is stored in `# Meta.<key> = value` comment
+    ///
+    /// `key` must be a single word (letters, digits, '_' or '-') which is not name, description or WG field,
+    /// existing value of the key (key is case-insensitive) is replaced in place
+    pub fn set_metadata(&mut self, key: &str, value: &str) -> Result<(), WgConfError> {
+        let is_word = key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if key.is_empty()
+            || !is_word
+            || is_wg_key(key)
+            || key.eq_ignore_ascii_case(NAME)
+            || key.eq_ignore_ascii_case(DESCRIPTION)
+        {
+            return Err(WgConfError::ValidationFailed(format!(
+                "'{key}' can't be used as metadata key"
+            )));
+        }
+
+        let value = metadata_value(value)?;
+        match self
+            .metadata
+            .iter_mut()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        {
+            Some((_, cur_value)) => *cur_value = value,
+            None => self.metadata.push((key.to_owned(), value)),
+        }
+
+        Ok(())
+    }
+
+    /// Sets new preshared key and records the rotation time, see [`WgPeer::preshared_key_rotated_at`]
+    #[cfg(any(feature = "wg_engine", feature = "native_keys"))]
+    pub(crate) fn rotate_preshared_key(
+        &mut self,
+        preshared_key: WgPresharedKey,
+        rotated_at: SystemTime,
+    ) -> Result<(), WgConfError> {
+        let secs = rotated_at
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| WgConfError::ValidationFailed(format!("invalid rotation time: {err}")))?
+            .as_secs();
+
+        self.set_metadata(PRESHARED_KEY_ROTATED_AT, &secs.to_string())?;
+        self.preshared_key = Some(preshared_key);
+
+        Ok(())
+    }
+
+    /// Removes metadata key (key is case-insensitive) and returns its value
+    pub fn remove_metadata(&mut self, key: &str) -> Option<String> {
+        let pos = self
+            .metadata
+            .iter()
+            .position(|(k, _)| k.eq_ignore_ascii_case(key))?;
+
+        Some(self.metadata.remove(pos).1)
+    }
+
+    /// Same as `to_string()`, but the preshared key is replaced by [`REDACTED`], so the section is safe to log
+    pub fn to_string_redacted(&self) -> String {
+        self.to_raw_string(true)
+    }
+
+    fn to_raw_string(&self, redacted: bool) -> String {
+        let mut raw = PEER_TAG.to_string() + "\n";
+        for (k, v) in self.to_raw_metadata() {
+            raw += &format!("# {} = {}\n", k, v);
+        }
+        for (k, v) in self.to_raw_key_values() {
+            match redacted && k == PRESHARED_KEY {
+                true => raw += &format!("{} = {}\n", k, REDACTED),
+                false => raw += &format!("{} = {}\n", k, v),
+            }
+        }
+
+        raw
+    }
+
+    /// Returns ordered key-values as they are written into the \[Peer\] section,
+    /// extra key-values are the last ones
+    pub(crate) fn to_raw_key_values(&self) -> Vec<(&str, String)> {
+        let mut raw_key_values = vec![
+            (PUBLIC_KEY, self.public_key.to_string()),
+            (ALLOWED_IPS, wg_interface::join_list(&self.allowed_ips)),
+        ];
+
+        if let Some(endpoint) = &self.endpoint {
+            raw_key_values.push((ENDPOINT, endpoint.to_string()));
+        }
+
+        if let Some(preshared_key) = &self.preshared_key {
+            raw_key_values.push((PRESHARED_KEY, preshared_key.expose_secret().to_string()));
+        }
+
+        if let Some(persistent_keepalive) = self.persistent_keepalive {
+            raw_key_values.push((PERSISTENT_KEEPALIVE, persistent_keepalive.to_string()));
+        }
+
+        raw_key_values.extend(self.extra.iter().map(|(k, v)| (k.as_str(), v.clone())));
+
+        raw_key_values
+    }
+
+    /// Returns ordered metadata key-values as they are written into `# Key = value` comments,
+    /// arbitrary metadata keys are prefixed with `Meta.`
+    pub(crate) fn to_raw_metadata(&self) -> Vec<(String, String)> {
+        let mut raw_metadata = Vec::with_capacity(self.metadata.len() + 2);
+
+        if let Some(name) = &self.name {
+            raw_metadata.push((NAME.to_string(), name.clone()));
+        }
+
+        if let Some(description) = &self.description {
+            raw_metadata.push((DESCRIPTION.to_string(), description.clone()));
+        }
+
+        raw_metadata.extend(self.metadata.iter().map(|(k, v)| {
+            match k.eq_ignore_ascii_case(PRESHARED_KEY_ROTATED_AT) {
+                true => (k.clone(), v.clone()),
+                false => (format!("{METADATA_PREFIX}{k}"), v.clone()),
+            }
+        }));
+
+        raw_metadata
+    }
+
+    /// Sets name, description and metadata from `# Key = value` comments of the section
+    /// which keys satisfy [`is_metadata_key`]
+    ///
+    /// Keys are case-insensitive, for repeated keys the last value is used
+    pub(crate) fn set_raw_metadata(&mut self, raw_metadata: Vec<(String, String)>) {
+        self.name = None;
+        self.description = None;
+        self.metadata = vec![];
+
+        for (k, v) in raw_metadata {
+            let k = match strip_metadata_prefix(&k) {
+                Some(key) => key.to_owned(),
+                None => k,
+            };
+            match k {
+                _ if k.eq_ignore_ascii_case(NAME) => self.name = Some(v),
+                _ if k.eq_ignore_ascii_case(DESCRIPTION) => self.description = Some(v),
+                _ => match self
+                    .metadata
+                    .iter_mut()
+                    .find(|(cur_k, _)| cur_k.eq_ignore_ascii_case(&k))
+                {
+                    Some((_, cur_v)) => *cur_v = v,
+                    None => self.metadata.push((k, v)),
+                },
+            }
+        }
+    }
+
+    /// Creates [`WgPeer`] from ordered key-values of the section
+    ///
+    /// Repeated AllowedIPs are merged in order like wg-quick does,
+    /// for other repeated keys the last value is used.
+    /// Unknown key-values are kept in order as extra ones
     pub(crate) fn from_raw_key_values(
-        raw_key_values: HashMap<String, String>,
+        raw_key_values: Vec<(String, String)>,
     ) -> Result<WgPeer, WgConfError> {
         let mut public_key = String::new();
         let mut allowed_ips = Vec::<String>::new();
         let mut endpoint: Option<String> = None;
         let mut preshared_key: Option<String> = None;
         let mut persistent_keepalive: Option<String> = None;
+        let mut extra: Vec<(String, String)> = vec![];
 
         for (k, v) in raw_key_values {
             match k {
                 _ if k == PUBLIC_KEY => public_key = v,
                 _ if k == ALLOWED_IPS => {
-                    let ips: Vec<&str> = v.split(", ").collect();
-                    for ip in ips {
-                        allowed_ips.push(ip.to_string());
+                    for ip in v.split(',').map(|ip| ip.trim()) {
+                        if !ip.is_empty() {
+                            allowed_ips.push(ip.to_string());
+                        }
                     }
                 }
                 _ if k == ENDPOINT => endpoint = Some(v),
                 _ if k == PRESHARED_KEY => preshared_key = Some(v),
                 _ if k == PERSISTENT_KEEPALIVE => persistent_keepalive = Some(v),
-                _ => continue,
+                _ => extra.push((k, v)),
             }
         }
 
-        WgPeer::from_raw_values(
+        let mut peer = WgPeer::from_raw_values(
             public_key,
             allowed_ips,
             endpoint,
             preshared_key,
             persistent_keepalive,
+        )?;
+        peer.extra = extra;
+
+        Ok(peer)
+    }
+}
+
+/// Checks if `# Key = value` comment of \[Peer\] section is peer's metadata: name, description,
+/// preshared key rotation time or `# Meta.<key> = value`.
+///
+/// Other comments (e.g. operator's `# Owner = alice` or commented out `# PersistentKeepalive = 25`)
+/// are not metadata, so they are kept as is when the peer is updated
+pub(crate) fn is_metadata_key(key: &str) -> bool {
+    [NAME, DESCRIPTION, PRESHARED_KEY_ROTATED_AT]
+        .iter()
+        .any(|k| k.eq_ignore_ascii_case(key))
+        || strip_metadata_prefix(key).is_some_and(|key| !key.is_empty())
+}
+
+fn strip_metadata_prefix(key: &str) -> Option<&str> {
+    match key.get(..METADATA_PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(METADATA_PREFIX) => {
+            Some(&key[METADATA_PREFIX.len()..])
+        }
+        _ => None,
+    }
+}
+
+fn is_wg_key(key: &str) -> bool {
+    KEYS.iter()
+        .chain(wg_interface::KEYS.iter())
+        .any(|k| k.eq_ignore_ascii_case(key))
+}
+
+/// Validates raw value of the single \[Peer\] key, values of unknown keys are always valid
+pub(crate) fn validate_raw_key_value(key: &str, value: &str) -> Result<(), WgConfError> {
+    match key {
+        _ if key == PUBLIC_KEY => value.parse::<WgKey>().map(|_| ()),
+        _ if key == ALLOWED_IPS => value
+            .split(',')
+            .map(|ip| ip.trim())
+            .filter(|ip| !ip.is_empty())
+            .try_for_each(|ip| parse_allowed_ip(ip).map(|_| ())),
+        _ if key == ENDPOINT => value.parse::<WgEndpoint>().map(|_| ()),
+        _ if key == PRESHARED_KEY => parse_preshared_key(value).map(|_| ()),
+        _ if key == PERSISTENT_KEEPALIVE => parse_persistent_keepalive(value).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+/// Adds \[Peer\] section and `key` to the error location
+fn err_at_key(key: &str) -> impl Fn(WgConfError) -> WgConfError + '_ {
+    move |err| err.with_location(WgConfErrLocation::for_key(PEER_TAG, key))
+}
+
+fn parse_allowed_ip(raw: &str) -> Result<IpNetwork, WgConfError> {
+    raw.parse().map_err(|_| {
+        WgConfError::ValidationFailed(
+            "allowed IPs must be addresses with mask (e.g. 10.0.0.1/8)".to_string(),
         )
+    })
+}
+
+fn parse_preshared_key(raw: &str) -> Result<WgPresharedKey, WgConfError> {
+    raw.parse()
+        .map_err(|_| WgConfError::ValidationFailed("invalid preshared key raw value".to_string()))
+}
+
+fn parse_persistent_keepalive(raw: &str) -> Result<u16, WgConfError> {
+    raw.parse().map_err(|_| {
+        WgConfError::ValidationFailed("invalid persistent keepalive raw value".to_string())
+    })
+}
+
+fn metadata_value(value: &str) -> Result<String, WgConfError> {
+    if value.contains(['\n', '\r']) {
+        return Err(WgConfError::ValidationFailed(
+            "metadata value can't contain line breaks".to_string(),
+        ));
     }
+
+    Ok(value.trim().to_owned())
 }
 
 #[cfg(test)]
@@ -305,4 +722,201 @@ AllowedIPs = 10.0.0.1/32, 10.0.0.2/32
             peer_raw
         );
     }
+
+    #[test]
+    fn wg_endpoint_0_from_str_0_round_trip() {
+        let endpoints = ["127.0.0.2:8080", "[fd00::1]:51820", "vpn.example.com:51820"];
+
+        for raw in endpoints {
+            // Act
+            let endpoint: WgEndpoint = raw.parse().unwrap();
+
+            // Assert
+            assert_eq!(raw, endpoint.to_string());
+        }
+
+        let endpoint: WgEndpoint = "vpn.example.com:51820".parse().unwrap();
+        assert_eq!(
+            &WgHost::Domain("vpn.example.com".to_string()),
+            endpoint.host()
+        );
+        assert_eq!(51820, endpoint.port());
+        assert!(endpoint.socket_addr().is_none());
+    }
+
+    #[test]
+    fn wg_endpoint_0_from_str_0_invalid_0_returns_validation_err() {
+        let invalid_endpoints = [
+            "vpn.example.com",
+            "fd00::1:51820",
+            "[vpn.example.com]:51820",
+            "vpn example.com:51820",
+            "vpn.example.com:0",
+            "vpn.example.com:65536",
+        ];
+
+        for raw in invalid_endpoints {
+            // Act
+            let res = raw.parse::<WgEndpoint>();
+
+            // Assert
+            assert_eq!(
+                crate::WgConfErrKind::ValidationFailed,
+                res.unwrap_err().kind(),
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn from_raw_values_0_hostname_endpoint_0_round_trip() {
+        // Act
+        let peer = WgPeer::from_raw_values(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            vec!["0.0.0.0/0".to_string()],
+            Some("vpn.example.com:51820".to_string()),
+            None,
+            None,
+        )
+        .unwrap();
+
+        // Assert
+        assert_eq!(
+            "[Peer]
+PublicKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+AllowedIPs = 0.0.0.0/0
+Endpoint = vpn.example.com:51820
+",
+            peer.to_string()
+        );
+    }
+
+    #[test]
+    fn from_raw_key_values_0_repeated_allowed_ips_0_merges_in_order() {
+        // Arrange
+        let raw_key_values = vec![
+            (
+                PUBLIC_KEY.to_string(),
+                "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            ),
+            (
+                ALLOWED_IPS.to_string(),
+                "10.0.0.2/32,10.0.0.3/32".to_string(),
+            ),
+            (ALLOWED_IPS.to_string(), "fd00::2/128".to_string()),
+        ];
+
+        // Act
+        let peer = WgPeer::from_raw_key_values(raw_key_values).unwrap();
+
+        // Assert
+        assert_eq!(
+            vec![
+                "10.0.0.2/32".parse::<IpNetwork>().unwrap(),
+                "10.0.0.3/32".parse().unwrap(),
+                "fd00::2/128".parse().unwrap()
+            ],
+            peer.allowed_ips()
+        );
+    }
+
+    #[test]
+    fn wg_peer_0_to_string_0_metadata_comments() {
+        // Arrange
+        let mut peer = WgPeer::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.1/32".parse().unwrap()],
+            None,
+            None,
+            None,
+        );
+        peer.set_name(Some("alice-laptop".to_string())).unwrap();
+        peer.set_description(Some("Alice from support".to_string()))
+            .unwrap();
+        peer.set_metadata("Ticket", "SUP-42").unwrap();
+        peer.set_metadata("ticket", "SUP-43").unwrap();
+
+        // Act
+        let peer_raw = peer.to_string();
+
+        // Assert
+        assert_eq!(Some("SUP-43"), peer.metadata_value("TICKET"));
+        assert_eq!(
+            "[Peer]
+# Name = alice-laptop
+# Description = Alice from support
+# Meta.Ticket = SUP-43
+PublicKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+AllowedIPs = 10.0.0.1/32
+",
+            peer_raw
+        );
+    }
+
+    #[test]
+    fn set_metadata_0_invalid_0_returns_validation_err() {
+        // Arrange
+        let mut peer = WgPeer::new(
+            "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8="
+                .parse()
+                .unwrap(),
+            vec![],
+            None,
+            None,
+            None,
+        );
+        let invalid_key_values = [
+            ("", "value"),
+            ("two words", "value"),
+            ("Name", "value"),
+            ("endpoint", "value"),
+            ("Key", "multi\nline"),
+        ];
+
+        for (key, value) in invalid_key_values {
+            // Act
+            let res = peer.set_metadata(key, value);
+
+            // Assert
+            assert_eq!(
+                crate::WgConfErrKind::ValidationFailed,
+                res.unwrap_err().kind(),
+                "key: {key}"
+            );
+        }
+        assert!(peer.set_name(Some("a\nb".to_string())).is_err());
+        assert!(peer.metadata().is_empty());
+    }
+
+    #[test]
+    fn from_raw_key_values_0_unknown_keys_0_kept_as_extra() {
+        // Arrange
+        let raw_key_values = vec![
+            (
+                PUBLIC_KEY.to_string(),
+                "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=".to_string(),
+            ),
+            ("FutureKey".to_string(), "future value".to_string()),
+            (ALLOWED_IPS.to_string(), "10.0.0.1/32".to_string()),
+        ];
+
+        // Act
+        let peer = WgPeer::from_raw_key_values(raw_key_values).unwrap();
+
+        // Assert
+        assert_eq!(
+            &[("FutureKey".to_string(), "future value".to_string())],
+            peer.extra()
+        );
+        assert_eq!(
+            "[Peer]
+PublicKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+AllowedIPs = 10.0.0.1/32
+FutureKey = future value
+",
+            peer.to_string()
+        );
+    }
 }
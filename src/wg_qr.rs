@@ -0,0 +1,197 @@
+use qrcode::{
+    render::{svg, unicode},
+    types::QrError,
+    Color, EcLevel, QrCode,
+};
+
+use crate::{WgClientConf, WgConfError};
+
+/// Max bytes count of binary data in QR code (version 40, low error correction)
+pub const QR_MAX_BYTES: usize = 2953;
+
+/// Width of the empty border around QR code in modules which is required by QR spec
+const QUIET_ZONE: u32 = 4;
+/// Min size of SVG image in pixels
+const SVG_MIN_SIZE: u32 = 256;
+const PNG_DARK: u8 = 0x00;
+const PNG_LIGHT: u8 = 0xFF;
+/// Max PNG module size in pixels, so the largest QR code image is about 6000 x 6000 pixels
+const PNG_MAX_MODULE_SIZE: u32 = 32;
+
+impl WgClientConf {
+    /// Renders the config as QR code of Unicode half blocks which is printed to the terminal as is
+    ///
+    /// The code is rendered light on dark to be scanned from terminals with dark background.
+    /// **Note**, that the code contains the private key
+    ///
+    /// Returns [`WgConfError::QrCapacityExceeded`] if the config is longer than [`QR_MAX_BYTES`]
+    pub fn to_qr_unicode(&self) -> Result<String, WgConfError> {
+        let code = self.qr_code()?;
+
+        Ok(code
+            .render::<unicode::Dense1x2>()
+            .dark_color(unicode::Dense1x2::Light)
+            .light_color(unicode::Dense1x2::Dark)
+            .build())
+    }
+
+    /// Renders the config as QR code SVG image, see [`WgClientConf::to_qr_unicode`]
+    pub fn to_qr_svg(&self) -> Result<String, WgConfError> {
+        let code = self.qr_code()?;
+
+        Ok(code
+            .render::<svg::Color>()
+            .min_dimensions(SVG_MIN_SIZE, SVG_MIN_SIZE)
+            .build())
+    }
+
+    /// Renders the config as QR code PNG image (8-bit grayscale) where every module is
+    /// `module_size` x `module_size` pixels (1 - 32), see [`WgClientConf::to_qr_unicode`]
+    pub fn to_qr_png(&self, module_size: u32) -> Result<Vec<u8>, WgConfError> {
+        if module_size == 0 || module_size > PNG_MAX_MODULE_SIZE {
+            return Err(WgConfError::ValidationFailed(format!(
+                "QR module size must be from 1 to {PNG_MAX_MODULE_SIZE}, got {module_size}"
+            )));
+        }
+
+        let code = self.qr_code()?;
+
+        let too_large_err =
+            || WgConfError::ValidationFailed(format!("QR module size {module_size} is too large"));
+        let modules_count = code.width() as u32;
+        let image_size = (modules_count + 2 * QUIET_ZONE)
+            .checked_mul(module_size)
+            .ok_or_else(too_large_err)?;
+        let pixels_count = (image_size as usize)
+            .checked_mul(image_size as usize)
+            .ok_or_else(too_large_err)?;
+
+        let colors = code.to_colors();
+        let mut pixels: Vec<u8> = Vec::with_capacity(pixels_count);
+        for y in 0..image_size {
+            for x in 0..image_size {
+                pixels.push(
+                    match module(&colors, modules_count, x / module_size, y / module_size) {
+                        Color::Dark => PNG_DARK,
+                        Color::Light => PNG_LIGHT,
+                    },
+                );
+            }
+        }
+
+        let mut png_bytes: Vec<u8> = vec![];
+        let mut encoder = png::Encoder::new(&mut png_bytes, image_size, image_size);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&pixels))
+            .map_err(|err| WgConfError::Unexpected(format!("Couldn't encode QR PNG: {err}")))?;
+
+        Ok(png_bytes)
+    }
+
+    fn qr_code(&self) -> Result<QrCode, WgConfError> {
+        let content = self.to_string();
+
+        QrCode::with_error_correction_level(&content, EcLevel::L).map_err(|err| match err {
+            QrError::DataTooLong => WgConfError::QrCapacityExceeded(format!(
+                "config is {} bytes, max is {QR_MAX_BYTES} bytes",
+                content.len()
+            )),
+            _ => WgConfError::Unexpected(format!("Couldn't create QR code: {err}")),
+        })
+    }
+}
+
+/// Returns color of the module at the image coordinates, quiet zone is light
+fn module(colors: &[Color], modules_count: u32, x: u32, y: u32) -> Color {
+    let is_quiet_zone =
+        |coordinate: u32| coordinate < QUIET_ZONE || coordinate >= modules_count + QUIET_ZONE;
+    if is_quiet_zone(x) || is_quiet_zone(y) {
+        return Color::Light;
+    }
+
+    colors[((y - QUIET_ZONE) * modules_count + x - QUIET_ZONE) as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WgConfErrKind;
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/32
+DNS = 10.0.0.1
+
+[Peer]
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 0.0.0.0/0
+Endpoint = vpn.example.com:51820
+";
+
+    #[test]
+    fn to_qr_0_all_formats_0_rendered() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+        let modules_count = QrCode::with_error_correction_level(CLIENT_CONTENT, EcLevel::L)
+            .unwrap()
+            .width() as u32;
+
+        // Act
+        let unicode = client_conf.to_qr_unicode().unwrap();
+        let svg = client_conf.to_qr_svg().unwrap();
+        let png_bytes = client_conf.to_qr_png(3).unwrap();
+
+        // Assert
+        assert!(unicode.lines().count() > 0);
+        assert!(unicode.chars().any(|c| c == '█' || c == '▀' || c == '▄'));
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.contains("<svg"));
+        let png_info = png::Decoder::new(png_bytes.as_slice())
+            .read_info()
+            .unwrap()
+            .info()
+            .clone();
+        assert_eq!((modules_count + 2 * QUIET_ZONE) * 3, png_info.width);
+        assert_eq!(png_info.width, png_info.height);
+    }
+
+    #[test]
+    fn to_qr_0_config_exceeds_capacity_0_returns_capacity_err() {
+        // Arrange
+        let mut client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+        let peer = client_conf.peers()[0].clone();
+        client_conf.peers = vec![peer; 30];
+
+        // Act
+        let unicode_res = client_conf.to_qr_unicode();
+        let png_res = client_conf.to_qr_png(1);
+
+        // Assert
+        assert!(client_conf.to_string().len() > QR_MAX_BYTES);
+        assert_eq!(
+            WgConfErrKind::QrCapacityExceeded,
+            unicode_res.unwrap_err().kind()
+        );
+        assert_eq!(
+            WgConfErrKind::QrCapacityExceeded,
+            png_res.unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn to_qr_png_0_zero_or_too_large_module_size_0_returns_validation_err() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        for module_size in [0, PNG_MAX_MODULE_SIZE + 1, u32::MAX] {
+            // Act
+            let res = client_conf.to_qr_png(module_size);
+
+            // Assert
+            assert_eq!(WgConfErrKind::ValidationFailed, res.unwrap_err().kind());
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    wg_networkd::route_destinations, wg_parse::validate_interface_name, WgClientConf, WgConfError,
+    WgHost, WgTable,
+};
+
+// Menus
+const WIREGUARD_MENU: &'static str = "/interface wireguard";
+const WIREGUARD_PEERS_MENU: &'static str = "/interface wireguard peers";
+const IP_ADDRESS_MENU: &'static str = "/ip address";
+const IPV6_ADDRESS_MENU: &'static str = "/ipv6 address";
+const IP_ROUTE_MENU: &'static str = "/ip route";
+const IPV6_ROUTE_MENU: &'static str = "/ipv6 route";
+
+impl WgClientConf {
+    /// Exports the config as MikroTik RouterOS script which adds `/interface wireguard` interface
+    /// with the peers, its addresses and routes to the peers' allowed IPs
+    ///
+    /// Routes are not added if Table is off and for allowed IPs which are inside the interface addresses,
+    /// Table id or name is used as `routing-table`. Peer name is exported as comment.
+    /// DNS, FwMark, hook commands and unknown keys are not exported, RouterOS DNS is global for the router.
+    ///
+    /// **Note**, that a default route (e.g. `0.0.0.0/0`) to the peer is added as is, the route to the peer
+    /// endpoint must be kept outside the tunnel. To export a single peer with the interface use
+    /// `WgClientConf::new(interface, vec![peer])`
+    ///
+    /// Referenced private key is resolved and exported inline, see [`WgInterface::resolve_private_key`](crate::WgInterface::resolve_private_key)
+    pub fn to_routeros_script(&self, interface_name: &str) -> Result<String, WgConfError> {
+        validate_interface_name(interface_name)?;
+
+        let interface = self.interface();
+        let name = quote(interface_name);
+
+        let mut raw = format!("{WIREGUARD_MENU}\n");
+        let mut params = vec![
+            format!("name={name}"),
+            format!(
+                "private-key={}",
+                quote(interface.resolve_private_key(None)?.expose_secret())
+            ),
+        ];
+        if let Some(listen_port) = interface.listen_port() {
+            params.push(format!("listen-port={listen_port}"));
+        }
+        if let Some(mtu) = interface.mtu() {
+            params.push(format!("mtu={mtu}"));
+        }
+        raw += &add(&params);
+
+        if !self.peers().is_empty() {
+            raw += &format!("{WIREGUARD_PEERS_MENU}\n");
+        }
+        for peer in self.peers() {
+            let mut params = vec![
+                format!("interface={name}"),
+                format!("public-key={}", quote(&peer.public_key().to_string())),
+            ];
+            if let Some(preshared_key) = peer.preshared_key() {
+                params.push(format!(
+                    "preshared-key={}",
+                    quote(preshared_key.expose_secret())
+                ));
+            }
+            if let Some(endpoint) = peer.endpoint() {
+                let host = match endpoint.host() {
+                    WgHost::Ip(ip) => ip.to_string(),
+                    WgHost::Domain(domain) => domain.to_owned(),
+                };
+                params.push(format!("endpoint-address={host}"));
+                params.push(format!("endpoint-port={}", endpoint.port()));
+            }
+            params.push(format!(
+                "allowed-address={}",
+                peer.allowed_ips()
+                    .iter()
+                    .map(|ip| ip.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ));
+            if let Some(persistent_keepalive) = peer.persistent_keepalive() {
+                params.push(format!("persistent-keepalive={persistent_keepalive}s"));
+            }
+            if let Some(peer_name) = peer.name() {
+                params.push(format!("comment={}", quote(peer_name)));
+            }
+            raw += &add(&params);
+        }
+
+        for (menu, is_ipv4) in [(IP_ADDRESS_MENU, true), (IPV6_ADDRESS_MENU, false)] {
+            let addresses: Vec<String> = interface
+                .addresses()
+                .iter()
+                .filter(|address| address.is_ipv4() == is_ipv4)
+                .map(|address| address.to_string())
+                .collect();
+            if addresses.is_empty() {
+                continue;
+            }
+
+            raw += &format!("{menu}\n");
+            for address in addresses {
+                let mut params = vec![format!("address={address}"), format!("interface={name}")];
+                if !is_ipv4 {
+                    params.push("advertise=no".to_string());
+                }
+                raw += &add(&params);
+            }
+        }
+
+        let routing_table = match interface.table() {
+            Some(WgTable::Off) => return Ok(raw),
+            Some(WgTable::Id(id)) => Some(id.to_string()),
+            Some(WgTable::Name(table_name)) => Some(table_name.to_owned()),
+            Some(WgTable::Auto) | None => None,
+        };
+        for (menu, is_ipv4) in [(IP_ROUTE_MENU, true), (IPV6_ROUTE_MENU, false)] {
+            let destinations: Vec<String> = route_destinations(interface, self.peers())
+                .into_iter()
+                .filter(|destination| destination.is_ipv4() == is_ipv4)
+                .map(|destination| destination.to_string())
+                .collect();
+            if destinations.is_empty() {
+                continue;
+            }
+
+            raw += &format!("{menu}\n");
+            for destination in destinations {
+                let mut params = vec![
+                    format!("dst-address={destination}"),
+                    format!("gateway={name}"),
+                ];
+                if let Some(routing_table) = &routing_table {
+                    params.push(format!("routing-table={}", quote(routing_table)));
+                }
+                raw += &add(&params);
+            }
+        }
+
+        Ok(raw)
+    }
+}
+
+fn add(params: &[String]) -> String {
+    format!("add {}\n", params.join(" "))
+}
+
+/// Double-quotes RouterOS string, `"`, `\` and `$` are escaped with `\`
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT_CONTENT: &'static str = "[Interface]
+PrivateKey = 6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=
+Address = 10.0.0.2/24, fd00::2/64
+DNS = 10.0.0.1
+MTU = 1420
+
+[Peer]
+# Name = \"HQ\" $office
+PublicKey = LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=
+AllowedIPs = 10.0.0.0/24, 192.168.1.0/24, fd00::/64
+Endpoint = vpn.example.com:51820
+PresharedKey = Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=
+PersistentKeepalive = 25
+";
+
+    const SCRIPT_CONTENT: &'static str = r#"/interface wireguard
+add name="wg0" private-key="6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=" mtu=1420
+/interface wireguard peers
+add interface="wg0" public-key="LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=" preshared-key="Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4=" endpoint-address=vpn.example.com endpoint-port=51820 allowed-address=10.0.0.0/24,192.168.1.0/24,fd00::/64 persistent-keepalive=25s comment="\"HQ\" \$office"
+/ip address
+add address=10.0.0.2/24 interface="wg0"
+/ipv6 address
+add address=fd00::2/64 interface="wg0" advertise=no
+/ip route
+add dst-address=192.168.1.0/24 gateway="wg0"
+"#;
+
+    #[test]
+    fn to_routeros_script_0_common_scenario() {
+        // Arrange
+        let client_conf = WgClientConf::parse(CLIENT_CONTENT).unwrap();
+
+        // Act
+        let script = client_conf.to_routeros_script("wg0");
+
+        // Assert
+        assert_eq!(SCRIPT_CONTENT, script.unwrap());
+    }
+
+    #[test]
+    fn to_routeros_script_0_table_off_0_no_routes() {
+        // Arrange
+        let content = CLIENT_CONTENT.replace("MTU = 1420", "Table = off");
+        let client_conf = WgClientConf::parse(&content).unwrap();
+
+        // Act
+        let script = client_conf.to_routeros_script("wg0").unwrap();
+
+        // Assert
+        assert!(!script.contains(IP_ROUTE_MENU));
+        assert!(script.ends_with("advertise=no\n"));
+    }
+}
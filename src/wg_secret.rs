@@ -0,0 +1,284 @@
+use std::{env, fmt::Debug, fs, str::FromStr};
+
+use zeroize::Zeroizing;
+
+use crate::{WgConfError, WgPrivateKey};
+
+/// Command of wg-quick hook which sets the interface private key, `%i` is the interface name
+const SET_PRIVATE_KEY_CMD: &'static str = "wg set %i private-key";
+const PRINTENV_CMD: &'static str = "printenv";
+
+/// Where \[Interface\] private key is taken from
+///
+/// Only [`WgPrivateKeyRef::Inline`] key is written as `PrivateKey`, the other ones are written
+/// as wg-quick hook `PostUp = wg set %i private-key <source>`, so the secret itself is kept out of the config
+/// and wg-quick is still able to bring the interface up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WgPrivateKeyRef {
+    /// The key is written into the config (`PrivateKey = <key>`)
+    Inline(WgPrivateKey),
+    /// The key is read from the file (`PostUp = wg set %i private-key <path>`),
+    /// e.g. networkd's `PrivateKeyFile=`
+    ///
+    /// The path may contain only ASCII alphanumerics and `/._-+@:,=` chars,
+    /// as wg-quick evaluates the hook by shell
+    File(String),
+    /// The key is read from the environment variable (`PostUp = wg set %i private-key <(printenv <name>)`)
+    Env(String),
+    /// The key is resolved by [`WgSecretProvider`] with the provided reference
+    /// (`PostUp = wg set %i private-key <(<command> <reference>)`)
+    ///
+    /// `command` is supplied by the caller and prints the key for wg-quick, e.g. `pass show`
+    /// or `vault kv get -field=private_key`, it's written as is and must be a single line without parentheses.
+    /// `reference` may contain only ASCII alphanumerics and `/._-+@:,=` chars, e.g. `wg/server`
+    Provider { command: String, reference: String },
+}
+
+/// Resolves [`WgPrivateKeyRef::Provider`] references to the private keys, e.g. from a secret manager
+pub trait WgSecretProvider: Debug + Send + Sync {
+    /// Returns the private key which `reference` of the provider `command` points to,
+    /// see [`WgPrivateKeyRef::Provider`]
+    fn private_key(&self, command: &str, reference: &str) -> Result<WgPrivateKey, WgConfError>;
+}
+
+impl From<WgPrivateKey> for WgPrivateKeyRef {
+    fn from(private_key: WgPrivateKey) -> Self {
+        WgPrivateKeyRef::Inline(private_key)
+    }
+}
+
+impl WgPrivateKeyRef {
+    /// Returns the key if it's inline one
+    pub fn inline(&self) -> Option<&WgPrivateKey> {
+        match self {
+            WgPrivateKeyRef::Inline(private_key) => Some(private_key),
+            _ => None,
+        }
+    }
+
+    /// Returns the private key: inline key is returned as is, the file or the environment variable is read,
+    /// provider reference is resolved with `provider`
+    ///
+    /// Returns [`WgConfError::NotFound`] if the file or the environment variable doesn't exist
+    /// or provider is required but not set
+    pub fn resolve(
+        &self,
+        provider: Option<&dyn WgSecretProvider>,
+    ) -> Result<WgPrivateKey, WgConfError> {
+        match self {
+            WgPrivateKeyRef::Inline(private_key) => Ok(private_key.clone()),
+            WgPrivateKeyRef::File(path) => read_key_file(path),
+            WgPrivateKeyRef::Env(name) => {
+                let raw = Zeroizing::new(env::var(name).map_err(|_| {
+                    WgConfError::NotFound(format!("private key environment variable '{name}'"))
+                })?);
+
+                raw.trim().parse()
+            }
+            WgPrivateKeyRef::Provider { command, reference } => provider
+                .ok_or(WgConfError::NotFound(format!(
+                    "secret provider for private key '{command} {reference}'"
+                )))?
+                .private_key(command, reference),
+        }
+    }
+
+    /// Validates that the reference may be written into wg-quick hook and parsed back
+    pub(crate) fn validate(&self) -> Result<(), WgConfError> {
+        let is_valid = match self {
+            WgPrivateKeyRef::Inline(_) => true,
+            WgPrivateKeyRef::File(path) => is_shell_safe_arg(path),
+            WgPrivateKeyRef::Env(name) => is_env_name(name),
+            WgPrivateKeyRef::Provider { command, reference } => {
+                is_provider_command(command) && is_shell_safe_arg(reference)
+            }
+        };
+
+        match is_valid {
+            true => Ok(()),
+            false => Err(WgConfError::ValidationFailed(format!(
+                "invalid private key reference {self:?}"
+            ))),
+        }
+    }
+
+    /// Returns wg-quick `PostUp` hook which sets the key of the interface, inline key has no hook
+    pub(crate) fn to_post_up(&self) -> Option<String> {
+        let source = match self {
+            WgPrivateKeyRef::Inline(_) => return None,
+            WgPrivateKeyRef::File(path) => path.clone(),
+            WgPrivateKeyRef::Env(name) => format!("<({PRINTENV_CMD} {name})"),
+            WgPrivateKeyRef::Provider { command, reference } => format!("<({command} {reference})"),
+        };
+
+        Some(format!("{SET_PRIVATE_KEY_CMD} {source}"))
+    }
+
+    /// Parses the reference from wg-quick `PostUp` hook, `None` if the hook doesn't set the interface key
+    pub(crate) fn from_post_up(hook: &str) -> Option<WgPrivateKeyRef> {
+        let source = hook.trim().strip_prefix(SET_PRIVATE_KEY_CMD)?;
+        if !source.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let source = source.trim();
+
+        let key_ref = match source
+            .strip_prefix("<(")
+            .and_then(|source| source.strip_suffix(')'))
+        {
+            Some(cmd) => match cmd.trim().split_once(char::is_whitespace) {
+                Some((PRINTENV_CMD, name)) if is_env_name(name.trim()) => {
+                    WgPrivateKeyRef::Env(name.trim().to_string())
+                }
+                _ => {
+                    let (command, reference) = cmd.trim().rsplit_once(char::is_whitespace)?;
+                    WgPrivateKeyRef::Provider {
+                        command: command.trim_end().to_string(),
+                        reference: reference.to_string(),
+                    }
+                }
+            },
+            None => WgPrivateKeyRef::File(source.to_string()),
+        };
+
+        key_ref.validate().ok().map(|_| key_ref)
+    }
+}
+
+/// Reads the key from the file, surrounding whitespaces are ignored
+pub(crate) fn read_key_file<K: FromStr<Err = WgConfError>>(
+    file_name: &str,
+) -> Result<K, WgConfError> {
+    let raw = Zeroizing::new(
+        fs::read_to_string(file_name).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => WgConfError::NotFound(file_name.to_string()),
+            _ => WgConfError::Unexpected(format!("Couldn't read key file {file_name}: {err}")),
+        })?,
+    );
+
+    raw.trim().parse()
+}
+
+/// Checks that the argument is passed to the hook command as is: it has no shell metacharacters,
+/// quotes, whitespaces or `%` (wg-quick replaces `%i` by the interface name) and isn't an option
+fn is_shell_safe_arg(arg: &str) -> bool {
+    !arg.is_empty()
+        && !arg.starts_with('-')
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "/._-+@:,=".contains(c))
+}
+
+/// Checks that the provider command may be written into `<(...)` of the hook and parsed back
+fn is_provider_command(command: &str) -> bool {
+    !command.is_empty()
+        && command.trim() == command
+        && command != PRINTENV_CMD
+        && !command.contains(|c: char| c.is_control() || c == '(' || c == ')')
+}
+
+fn is_env_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wg_conf::tests::prepare_test_conf;
+
+    #[test]
+    fn from_post_up_0_to_post_up_0_same_ref() {
+        let key_refs = [
+            WgPrivateKeyRef::File("/etc/wireguard/wg0.key".to_string()),
+            WgPrivateKeyRef::Env("WG_PRIVATE_KEY".to_string()),
+            WgPrivateKeyRef::Provider {
+                command: "pass show".to_string(),
+                reference: "wg/server".to_string(),
+            },
+            WgPrivateKeyRef::Provider {
+                command: "vault kv get -field=private_key".to_string(),
+                reference: "secret/wg0".to_string(),
+            },
+        ];
+
+        for key_ref in key_refs {
+            // Act
+            let hook = key_ref.to_post_up().unwrap();
+
+            // Assert
+            assert_eq!(Some(key_ref), WgPrivateKeyRef::from_post_up(&hook));
+        }
+    }
+
+    #[test]
+    fn from_post_up_0_other_hooks_0_returns_none() {
+        let hooks = [
+            "ufw allow 8080/udp",
+            "wg set %i private-keys /etc/wireguard/wg0.key",
+            "wg set %i private-key",
+            "wg set %i private-key /etc/wireguard/wg0.key; ip link set %i up",
+            "wg set %i private-key /etc/wireguard/$(id).key",
+            "wg set %i private-key /etc/wireguard/`id`.key",
+            "wg set %i private-key <(pass show wg/$(id))",
+            "wg set %i private-key <(pass show wg/`id`)",
+            "wg set %i private-key <(pass-show)",
+        ];
+
+        for hook in hooks {
+            // Act
+            let key_ref = WgPrivateKeyRef::from_post_up(hook);
+
+            // Assert
+            assert!(key_ref.is_none(), "{hook}");
+        }
+    }
+
+    #[test]
+    fn resolve_0_file_env_and_provider_0_returns_key() {
+        // Arrange
+        const KEY: &str = "6FyM4Sq5zanp+9UPXIygLJQBYvlLsfF5lYcrSoa3CX8=";
+        const KEY_FILE: &str = "wg_secret0.key";
+        const KEY_ENV: &str = "WG_CONFIG_TEST_SECRET_PRIVATE_KEY";
+
+        #[derive(Debug)]
+        struct TestProvider;
+
+        impl WgSecretProvider for TestProvider {
+            fn private_key(
+                &self,
+                command: &str,
+                reference: &str,
+            ) -> Result<WgPrivateKey, WgConfError> {
+                match (command, reference) {
+                    ("vault read", "wg0") => KEY.parse(),
+                    _ => Err(WgConfError::NotFound(reference.to_string())),
+                }
+            }
+        }
+
+        let _cleanup = prepare_test_conf(KEY_FILE, &format!("{KEY}\n"));
+        env::set_var(KEY_ENV, KEY);
+
+        // Act
+        let from_file = WgPrivateKeyRef::File(KEY_FILE.to_string()).resolve(None);
+        let from_env = WgPrivateKeyRef::Env(KEY_ENV.to_string()).resolve(None);
+        let provider_ref = WgPrivateKeyRef::Provider {
+            command: "vault read".to_string(),
+            reference: "wg0".to_string(),
+        };
+        let from_provider = provider_ref.resolve(Some(&TestProvider));
+        let without_provider = provider_ref.resolve(None);
+
+        // Assert
+        env::remove_var(KEY_ENV);
+        assert_eq!(KEY, from_file.unwrap().expose_secret());
+        assert_eq!(KEY, from_env.unwrap().expose_secret());
+        assert_eq!(KEY, from_provider.unwrap().expose_secret());
+        assert_eq!(
+            crate::WgConfErrKind::NotFound,
+            without_provider.unwrap_err().kind()
+        );
+    }
+}
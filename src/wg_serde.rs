@@ -0,0 +1,430 @@
+use std::{cell::Cell, str::FromStr};
+
+use ipnetwork::IpNetwork;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    WgConfError, WgDns, WgEndpoint, WgHost, WgInterface, WgKey, WgPeer, WgPresharedKey,
+    WgPrivateKey, WgPrivateKeyRef, WgPublicKey, WgTable, REDACTED,
+};
+
+thread_local! {
+    static REDACT_SECRETS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Serializes the wrapped value with secret fields (private and preshared keys) replaced by [`REDACTED`]
+///
+/// E.g. `serde_json::to_string(&WgRedacted(&interface))`. **Note**, that redacted value can't be deserialized back
+pub struct WgRedacted<'a, T: ?Sized>(pub &'a T);
+
+impl<T: Serialize + ?Sized> Serialize for WgRedacted<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let _redaction = RedactionScope::enter();
+
+        self.0.serialize(serializer)
+    }
+}
+
+/// Enables secrets redaction for the current thread till drop, the previous state is restored on drop
+struct RedactionScope {
+    prev: bool,
+}
+
+impl RedactionScope {
+    fn enter() -> RedactionScope {
+        RedactionScope {
+            prev: REDACT_SECRETS.with(|redact| redact.replace(true)),
+        }
+    }
+}
+
+impl Drop for RedactionScope {
+    fn drop(&mut self) {
+        REDACT_SECRETS.with(|redact| redact.set(self.prev));
+    }
+}
+
+/// Serializes secret key or [`REDACTED`] if serialization is made with [`WgRedacted`]
+pub(crate) fn serialize_secret<K: Serialize, S: Serializer>(
+    key: &K,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match REDACT_SECRETS.with(|redact| redact.get()) {
+        true => serializer.serialize_str(REDACTED),
+        false => key.serialize(serializer),
+    }
+}
+
+/// Same as [`serialize_secret`] for optional key
+pub(crate) fn serialize_optional_secret<K: Serialize, S: Serializer>(
+    key: &Option<K>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match key {
+        Some(key) => serializer.serialize_some(&Secret(key)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Secret key which is serialized with [`serialize_secret`]
+struct Secret<'a, K>(&'a K);
+
+impl<K: Serialize> Serialize for Secret<'_, K> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_secret(self.0, serializer)
+    }
+}
+
+/// Implements serde traits for the type as its string representation, deserialized value is validated with `FromStr`
+macro_rules! impl_serde_as_str {
+    ($($t:ty),*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $t {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    deserialize_from_str(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_as_str!(WgDns, WgTable, WgHost, WgEndpoint);
+
+impl Serialize for WgKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.key)
+    }
+}
+
+impl<'de> Deserialize<'de> for WgKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+/// Implements serde traits for the key newtype the same way as for [`WgKey`]
+macro_rules! impl_serde_as_key {
+    ($($t:ty),*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    self.0.serialize(serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $t {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    deserialize_from_str(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+impl_serde_as_key!(WgPrivateKey, WgPublicKey, WgPresharedKey);
+
+/// Inline key is serialized as secret key string, the other references are serialized as single-entry maps,
+/// e.g. `{"file": "/etc/wireguard/wg0.key"}`, so the secret itself is never serialized
+impl Serialize for WgPrivateKeyRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        const NAME: &str = "WgPrivateKeyRef";
+
+        match self {
+            WgPrivateKeyRef::Inline(private_key) => serialize_secret(private_key, serializer),
+            WgPrivateKeyRef::File(path) => {
+                serializer.serialize_newtype_variant(NAME, 1, "file", path)
+            }
+            WgPrivateKeyRef::Env(name) => {
+                serializer.serialize_newtype_variant(NAME, 2, "env", name)
+            }
+            WgPrivateKeyRef::Provider { command, reference } => serializer
+                .serialize_newtype_variant(
+                    NAME,
+                    3,
+                    "provider",
+                    &WgProviderRefSerde {
+                        command: command.clone(),
+                        reference: reference.clone(),
+                    },
+                ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WgPrivateKeyRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key_ref = match WgPrivateKeyRefDe::deserialize(deserializer)? {
+            WgPrivateKeyRefDe::Inline(private_key) => WgPrivateKeyRef::Inline(private_key),
+            WgPrivateKeyRefDe::File { file } => WgPrivateKeyRef::File(file),
+            WgPrivateKeyRefDe::Env { env } => WgPrivateKeyRef::Env(env),
+            WgPrivateKeyRefDe::Provider { provider } => WgPrivateKeyRef::Provider {
+                command: provider.command,
+                reference: provider.reference,
+            },
+        };
+        key_ref.validate().map_err(de::Error::custom)?;
+
+        Ok(key_ref)
+    }
+}
+
+/// Raw [`WgPrivateKeyRef`], see its `Serialize` implementation
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WgPrivateKeyRefDe {
+    Inline(WgPrivateKey),
+    File { file: String },
+    Env { env: String },
+    Provider { provider: WgProviderRefSerde },
+}
+
+/// Raw [`WgPrivateKeyRef::Provider`], e.g. `{"command": "pass show", "reference": "wg/server"}`
+#[derive(Serialize, Deserialize)]
+struct WgProviderRefSerde {
+    command: String,
+    reference: String,
+}
+
+/// Raw [`WgInterface`] which is validated while converting into [`WgInterface`]
+#[derive(Deserialize)]
+pub(crate) struct WgInterfaceDe {
+    private_key: WgPrivateKeyRef,
+    addresses: Vec<IpNetwork>,
+    #[serde(default)]
+    listen_port: Option<u16>,
+    #[serde(default)]
+    dns: Vec<WgDns>,
+    #[serde(default)]
+    post_up: Vec<String>,
+    #[serde(default)]
+    post_down: Vec<String>,
+    #[serde(default)]
+    mtu: Option<u16>,
+    #[serde(default)]
+    table: Option<WgTable>,
+    #[serde(default)]
+    fw_mark: Option<u32>,
+    #[serde(default)]
+    pre_up: Vec<String>,
+    #[serde(default)]
+    pre_down: Vec<String>,
+    #[serde(default)]
+    save_config: bool,
+    #[serde(default)]
+    extra: Vec<(String, String)>,
+}
+
+impl TryFrom<WgInterfaceDe> for WgInterface {
+    type Error = WgConfError;
+
+    fn try_from(raw: WgInterfaceDe) -> Result<Self, Self::Error> {
+        let mut interface = WgInterface::with_private_key_ref(
+            raw.private_key,
+            raw.addresses,
+            raw.listen_port,
+            raw.dns,
+            raw.post_up,
+            raw.post_down,
+        )?;
+        interface.set_mtu(raw.mtu)?;
+        interface.set_table(raw.table);
+        interface.set_fw_mark(raw.fw_mark);
+        interface.set_pre_up(raw.pre_up);
+        interface.set_pre_down(raw.pre_down);
+        interface.set_save_config(raw.save_config);
+        interface.set_extra(raw.extra)?;
+
+        Ok(interface)
+    }
+}
+
+/// Raw [`WgPeer`] which is validated while converting into [`WgPeer`]
+#[derive(Deserialize)]
+pub(crate) struct WgPeerDe {
+    public_key: WgPublicKey,
+    #[serde(default)]
+    allowed_ips: Vec<IpNetwork>,
+    #[serde(default)]
+    endpoint: Option<WgEndpoint>,
+    #[serde(default)]
+    preshared_key: Option<WgPresharedKey>,
+    #[serde(default)]
+    persistent_keepalive: Option<u16>,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    metadata: Vec<(String, String)>,
+    #[serde(default)]
+    extra: Vec<(String, String)>,
+}
+
+impl TryFrom<WgPeerDe> for WgPeer {
+    type Error = WgConfError;
+
+    fn try_from(raw: WgPeerDe) -> Result<Self, Self::Error> {
+        let mut peer = WgPeer::new(
+            raw.public_key,
+            raw.allowed_ips,
+            raw.endpoint,
+            raw.preshared_key,
+            raw.persistent_keepalive,
+        );
+        peer.set_name(raw.name)?;
+        peer.set_description(raw.description)?;
+        for (key, value) in raw.metadata {
+            peer.set_metadata(&key, &value)?;
+        }
+        peer.set_extra(raw.extra)?;
+
+        Ok(peer)
+    }
+}
+
+fn deserialize_from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr<Err = WgConfError>,
+    D: Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+
+    raw.parse().map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WgClientConf;
+
+    const INTERFACE_JSON: &'static str = r#"{"private_key":"4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=","addresses":["10.0.0.1/24"],"listen_port":8080,"dns":["8.8.8.8","example.com"],"post_up":[],"post_down":[],"mtu":1420,"table":"off","fw_mark":null,"pre_up":[],"pre_down":[],"save_config":false,"extra":[["FutureKey","value"]]}"#;
+
+    #[test]
+    fn serialize_0_interface_0_canonical_strings() {
+        // Arrange
+        let mut interface = WgInterface::new(
+            "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.1/24".parse().unwrap()],
+            Some(8080),
+            vec!["8.8.8.8".parse().unwrap(), "example.com".parse().unwrap()],
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        interface.set_mtu(Some(1420)).unwrap();
+        interface.set_table(Some(WgTable::Off));
+        interface
+            .set_extra(vec![("FutureKey".to_string(), "value".to_string())])
+            .unwrap();
+
+        // Act
+        let json = serde_json::to_string(&interface).unwrap();
+        let deserialized: WgInterface = serde_json::from_str(&json).unwrap();
+
+        // Assert
+        assert_eq!(INTERFACE_JSON, json);
+        assert_eq!(interface, deserialized);
+    }
+
+    #[test]
+    fn serialize_0_redacted_0_secrets_replaced() {
+        // Arrange
+        let interface: WgInterface = serde_json::from_str(INTERFACE_JSON).unwrap();
+        let peer = WgPeer::new(
+            "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE="
+                .parse()
+                .unwrap(),
+            vec!["10.0.0.2/32".parse().unwrap()],
+            Some("vpn.example.com:51820".parse().unwrap()),
+            Some(
+                "Rrr2pT8pOvcEKdp1KpsvUi8OO/fYIWnkVcnXJ3dtUE4="
+                    .parse()
+                    .unwrap(),
+            ),
+            None,
+        );
+        let client_conf = WgClientConf::new(interface, vec![peer]);
+
+        // Act
+        let redacted = serde_json::to_value(WgRedacted(&client_conf)).unwrap();
+        let not_redacted = serde_json::to_value(&client_conf).unwrap();
+
+        // Assert
+        assert_eq!(REDACTED, redacted["interface"]["private_key"]);
+        assert_eq!(REDACTED, redacted["peers"][0]["preshared_key"]);
+        assert_eq!(
+            "LyXP6s7mzMlrlcZ5STONcPwTQFOUJuD8yQg6FYDeTzE=",
+            redacted["peers"][0]["public_key"]
+        );
+        assert_eq!("vpn.example.com:51820", redacted["peers"][0]["endpoint"]);
+        assert_eq!(
+            "4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=",
+            not_redacted["interface"]["private_key"]
+        );
+        assert_eq!(
+            client_conf,
+            serde_json::from_value::<WgClientConf>(not_redacted).unwrap()
+        );
+    }
+
+    #[test]
+    fn serialize_0_private_key_ref_0_keeps_ref() {
+        // Arrange
+        let json = INTERFACE_JSON
+            .replace(
+                r#""4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=""#,
+                r#"{"env":"WG_PRIVATE_KEY"}"#,
+            )
+            .replace(
+                r#""post_up":[]"#,
+                r#""post_up":["ufw allow 8080/udp","wg set %i private-key <(printenv WG_PRIVATE_KEY)"]"#,
+            );
+
+        // Act
+        let interface: WgInterface = serde_json::from_str(&json).unwrap();
+        let serialized = serde_json::to_string(&interface).unwrap();
+
+        // Assert
+        assert_eq!(
+            &WgPrivateKeyRef::Env("WG_PRIVATE_KEY".to_string()),
+            interface.private_key_ref()
+        );
+        assert_eq!(json, serialized);
+        assert_eq!(
+            json,
+            serde_json::to_string(&WgRedacted(&interface)).unwrap()
+        );
+    }
+
+    #[test]
+    fn deserialize_0_invalid_values_0_returns_err() {
+        // Arrange
+        let invalid_jsons = [
+            INTERFACE_JSON.replace("4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=", REDACTED),
+            INTERFACE_JSON.replace(r#"["10.0.0.1/24"]"#, "[]"),
+            INTERFACE_JSON.replace("1420", "10"),
+            INTERFACE_JSON.replace("FutureKey", "PrivateKey"),
+            INTERFACE_JSON.replace("example.com", "exa mple"),
+            INTERFACE_JSON.replace(
+                r#""4DIjxC8pEzYZGvLLEbzHRb2dCxiyAOAfx9dx/NMlL2c=""#,
+                r#"{"file":"wg 0.key"}"#,
+            ),
+        ];
+
+        for invalid_json in invalid_jsons {
+            // Act
+            let res = serde_json::from_str::<WgInterface>(&invalid_json);
+
+            // Assert
+            assert!(res.is_err(), "{invalid_json}");
+        }
+    }
+}